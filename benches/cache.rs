@@ -0,0 +1,47 @@
+use std::sync::Arc;
+use std::time::Duration;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use link::store::Cache;
+
+const KEYS: usize = 1024;
+const READS: usize = 10_000;
+
+/// Runs `READS` lookups on each of `readers` tasks at once
+async fn bench_cache_read(cache: Arc<Cache>, readers: usize) {
+    let tasks: Vec<_> = (0..readers)
+        .map(|reader| {
+            let cache = cache.clone();
+            tokio::spawn(async move {
+                for i in 0..READS {
+                    let key = format!("key{}", (i * 31 + reader) % KEYS);
+                    cache.get(&key).await.unwrap();
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    let cache = Arc::new(Cache::new(Duration::from_secs(3600)));
+    rt.block_on(async {
+        for i in 0..KEYS {
+            cache.set(&format!("key{}", i), vec![0u8; 64]).await.unwrap();
+        }
+    });
+
+    let mut group = c.benchmark_group("cache_read");
+    for readers in [1, 2, 4, 8] {
+        group.throughput(Throughput::Elements((readers * READS) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(readers), &readers, |b, &readers| {
+            b.iter(|| rt.block_on(bench_cache_read(cache.clone(), readers)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
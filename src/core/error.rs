@@ -16,10 +16,87 @@ pub enum Error {
     #[error("Store error: {0}")]
     Store(String), // Represents errors related to data storage or retrieval operations.
 
+    #[error("Timeout error: {0}")]
+    Timeout(String), // Represents operations that exceeded their deadline, such as connect, read, write or idle.
+
+    #[error("Not found: {0}")]
+    NotFound(String), // Represents lookups of routes, keys or files that do not exist.
+
+    #[error("Capacity exceeded: {0}")]
+    Capacity(String), // Represents full pools and buffers, or data larger than the configured size.
+
+    #[error("Authentication failed: {0}")]
+    Auth(String), // Represents signatures that are missing or do not verify.
+
+    #[error("Access denied: {0}")]
+    Denied(String), // Represents peers that are banned or not permitted to perform an operation.
+
+    #[error("Decryption failed: {0}")]
+    Decrypt(String), // Represents ciphertext that is malformed or fails authentication.
+
+    #[error("Protocol violation: {0}")]
+    Protocol(String), // Represents frames from the peer that do not follow the wire format.
+
+    #[error("Validation failed: rule {rule}: {reason}")]
+    Invalid { rule: String, reason: String }, // Represents data rejected by a named validation rule.
+
+    #[error("Closed: {0}")]
+    Closed(String), // Represents operations on a link, socket or pool that has already been closed.
+
+    #[error("I/O error on {context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    }, // Represents I/O failures, keeping the underlying error and what was being done.
+
     #[error("System error: {0}")]
     System(#[from] std::io::Error), // Represents errors related to system-level operations, such as I/O operations.
 }
 
+impl Error {
+    /// Wraps an I/O error together with a description of the failed operation.
+    /// The original error stays reachable through `std::error::Error::source`.
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Error::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Returns whether retrying, reconnecting or failing over may succeed.
+    /// Transport failures and deadlines are retryable; rejected input, failed
+    /// authentication and missing routes are not, since a retry would fail the same way.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::Net(_) | Error::Timeout(_) | Error::Closed(_) | Error::Io { .. } | Error::System(_)
+        )
+    }
+
+    /// Returns a short, stable name for the error category.
+    /// The name is used as a label when counting errors per kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Net(_) => "net",
+            Error::Guard(_) => "guard",
+            Error::State(_) => "state",
+            Error::Store(_) => "store",
+            Error::Timeout(_) => "timeout",
+            Error::NotFound(_) => "not_found",
+            Error::Capacity(_) => "capacity",
+            Error::Auth(_) => "auth",
+            Error::Denied(_) => "denied",
+            Error::Decrypt(_) => "decrypt",
+            Error::Protocol(_) => "protocol",
+            Error::Invalid { .. } => "invalid",
+            Error::Closed(_) => "closed",
+            Error::Io { .. } => "io",
+            Error::System(_) => "system",
+        }
+    }
+}
+
 /// Represents the result of an operation that may fail with an `Error`.
 /// This type alias is used to simplify the handling of operations that may result in an error.
-pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, Error>;
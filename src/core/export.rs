@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::core::error::{Error, Result};
+use crate::core::link::Linkable;
+use crate::core::state::{Measure, Mode, Rate, State};
+
+/// Kích thước tối đa của một yêu cầu HTTP được chấp nhận
+const REQUEST: usize = 8192;
+
+/// Thời gian chờ mặc định để đọc xong một yêu cầu
+const READ: Duration = Duration::from_secs(5);
+
+/// Tên chỉ số, mô tả và hàm đọc giá trị từ `Measure`
+type Metric<T> = (&'static str, &'static str, fn(&Measure) -> T);
+
+/// Danh sách các `State` được xuất chỉ số, theo tên
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    states: Arc<RwLock<BTreeMap<String, State>>>,
+}
+
+impl Registry {
+    /// Tạo một danh sách rỗng
+    ///
+    /// # Returns
+    /// * `Self` - Danh sách mới
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Đăng ký một trạng thái dưới một tên, thay thế trạng thái cũ cùng tên
+    ///
+    /// # Arguments
+    /// * `name` - Tên dùng làm nhãn `state` khi xuất
+    /// * `state` - Trạng thái cần theo dõi
+    pub async fn register(&self, name: &str, state: State) {
+        self.states.write().await.insert(name.to_string(), state);
+    }
+
+    /// Hủy đăng ký một trạng thái
+    ///
+    /// # Arguments
+    /// * `name` - Tên đã đăng ký
+    pub async fn unregister(&self, name: &str) {
+        self.states.write().await.remove(name);
+    }
+
+    /// Lấy trạng thái đã đăng ký dưới một tên
+    ///
+    /// # Arguments
+    /// * `name` - Tên đã đăng ký
+    ///
+    /// # Returns
+    /// * `Option<State>` - Trạng thái, hoặc `None` nếu chưa đăng ký
+    pub async fn get(&self, name: &str) -> Option<State> {
+        self.states.read().await.get(name).cloned()
+    }
+
+    /// Lấy danh sách các trạng thái đã đăng ký
+    ///
+    /// # Returns
+    /// * `Vec<(String, State)>` - Các cặp tên và trạng thái, theo thứ tự tên
+    pub async fn list(&self) -> Vec<(String, State)> {
+        self.states.read().await
+            .iter()
+            .map(|(name, state)| (name.clone(), state.clone()))
+            .collect()
+    }
+
+    /// Kết xuất tất cả chỉ số theo định dạng văn bản của Prometheus
+    ///
+    /// # Returns
+    /// * `Result<String>` - Nội dung văn bản
+    pub async fn render(&self) -> Result<String> {
+        let mut samples = Vec::new();
+        for (name, state) in self.list().await {
+            samples.push((escape(&name), state.mode().await?, state.measure().await?));
+        }
+
+        let mut out = String::new();
+
+        family(&mut out, "link_mode", "gauge", "Current mode of the link (1 for the active mode)");
+        for (name, mode, _) in &samples {
+            for candidate in [Mode::Init, Mode::Ready, Mode::Active, Mode::Pause, Mode::Close] {
+                let value = u8::from(*mode == candidate);
+                let _ = writeln!(out, "link_mode{{state=\"{}\",mode=\"{:?}\"}} {}", name, candidate, value);
+            }
+        }
+
+        let counters: [Metric<usize>; 5] = [
+            ("link_send_bytes_total", "Bytes sent", |m| m.send),
+            ("link_receive_bytes_total", "Bytes received", |m| m.receive),
+            ("link_send_frames_total", "Frames sent", |m| m.frames.send),
+            ("link_receive_frames_total", "Frames received", |m| m.frames.receive),
+            ("link_errors_total", "Errors recorded", |m| m.error),
+        ];
+        for (metric, help, value) in counters {
+            family(&mut out, metric, "counter", help);
+            for (name, _, measure) in &samples {
+                let _ = writeln!(out, "{}{{state=\"{}\"}} {}", metric, name, value(measure));
+            }
+        }
+
+        family(&mut out, "link_errors_by_kind_total", "counter", "Errors recorded per error kind");
+        for (name, _, measure) in &samples {
+            for (kind, count) in &measure.errors {
+                let _ = writeln!(out, "link_errors_by_kind_total{{state=\"{}\",kind=\"{}\"}} {}", name, escape(kind), count);
+            }
+        }
+
+        family(&mut out, "link_events_total", "counter", "Named event counters, such as rate limit actions");
+        for (name, _, measure) in &samples {
+            for (event, count) in &measure.counters {
+                let _ = writeln!(out, "link_events_total{{state=\"{}\",event=\"{}\"}} {}", name, escape(event), count);
+            }
+        }
+
+        let rates: [Metric<Rate>; 2] = [
+            ("link_send_rate_bytes", "Bytes sent per second over a sliding window", |m| m.send_rate),
+            ("link_receive_rate_bytes", "Bytes received per second over a sliding window", |m| m.receive_rate),
+        ];
+        for (metric, help, rate) in rates {
+            family(&mut out, metric, "gauge", help);
+            for (name, _, measure) in &samples {
+                let rate = rate(measure);
+                let _ = writeln!(out, "{}{{state=\"{}\",window=\"10s\"}} {}", metric, name, rate.recent);
+                let _ = writeln!(out, "{}{{state=\"{}\",window=\"60s\"}} {}", metric, name, rate.minute);
+            }
+        }
+
+        let gauges: [Metric<Option<Duration>>; 3] = [
+            ("link_rtt_seconds", "Smoothed round-trip time", |m| m.rtt),
+            ("link_jitter_seconds", "Round-trip time variation", |m| m.jitter),
+            ("link_uptime_seconds", "Time since the first frame", |m| m.uptime()),
+        ];
+        for (metric, help, value) in gauges {
+            family(&mut out, metric, "gauge", help);
+            for (name, _, measure) in &samples {
+                if let Some(value) = value(measure) {
+                    let _ = writeln!(out, "{}{{state=\"{}\"}} {}", metric, name, value.as_secs_f64());
+                }
+            }
+        }
+
+        family(&mut out, "link_latency_seconds", "histogram", "Latency distribution");
+        for (name, _, measure) in &samples {
+            let latency = &measure.latency;
+            let mut cumulative = 0;
+            for (bound, count) in latency.bounds.iter().zip(&latency.counts) {
+                cumulative += count;
+                let _ = writeln!(out, "link_latency_seconds_bucket{{state=\"{}\",le=\"{}\"}} {}", name, bound, cumulative);
+            }
+            let _ = writeln!(out, "link_latency_seconds_bucket{{state=\"{}\",le=\"+Inf\"}} {}", name, latency.count);
+            let _ = writeln!(out, "link_latency_seconds_sum{{state=\"{}\"}} {}", name, latency.sum);
+            let _ = writeln!(out, "link_latency_seconds_count{{state=\"{}\"}} {}", name, latency.count);
+        }
+
+        Ok(out)
+    }
+}
+
+/// Ghi phần mô tả và loại của một họ chỉ số
+fn family(out: &mut String, metric: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", metric, help);
+    let _ = writeln!(out, "# TYPE {} {}", metric, kind);
+}
+
+/// Thoát các ký tự đặc biệt trong giá trị nhãn
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Điểm cuối HTTP cục bộ phục vụ `GET /metrics`
+pub struct Export {
+    /// Danh sách trạng thái được xuất
+    registry: Registry,
+    /// Địa chỉ cần lắng nghe
+    addr: String,
+    /// Địa chỉ thực tế sau khi bắt đầu
+    local: Option<SocketAddr>,
+    /// Tác vụ phục vụ yêu cầu
+    task: Option<JoinHandle<()>>,
+    /// Thời gian chờ để đọc xong một yêu cầu
+    read: Duration,
+    /// Trạng thái của điểm cuối
+    state: State,
+}
+
+impl Export {
+    /// Tạo một điểm cuối xuất chỉ số
+    ///
+    /// # Arguments
+    /// * `registry` - Danh sách trạng thái được xuất
+    /// * `addr` - Địa chỉ lắng nghe, ví dụ `127.0.0.1:9100`
+    ///
+    /// # Returns
+    /// * `Self` - Điểm cuối chưa bắt đầu
+    pub fn new(registry: Registry, addr: &str) -> Self {
+        Self {
+            registry,
+            addr: addr.to_string(),
+            local: None,
+            task: None,
+            read: READ,
+            state: State::new(),
+        }
+    }
+
+    /// Đặt thời gian chờ để đọc xong một yêu cầu, có hiệu lực từ lần bắt đầu tiếp theo
+    ///
+    /// # Arguments
+    /// * `read` - Máy khách chưa gửi hết yêu cầu sau thời gian này bị ngắt kết nối
+    pub fn set_read_timeout(&mut self, read: Duration) {
+        self.read = read;
+    }
+
+    /// Địa chỉ đang lắng nghe
+    ///
+    /// # Returns
+    /// * `Option<SocketAddr>` - `None` nếu chưa bắt đầu
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local
+    }
+}
+
+/// Trả lời một yêu cầu HTTP
+async fn serve(mut stream: TcpStream, registry: Registry, read: Duration) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    let complete = tokio::time::timeout(read, async {
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 || request.len() + n > REQUEST {
+                return Ok::<bool, Error>(false);
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        Ok(true)
+    })
+    .await
+    .map_err(|_| Error::Timeout("metrics request read deadline exceeded".into()))??;
+    if !complete {
+        return Ok(());
+    }
+
+    let line = request.split(|b| *b == b'\r').next().unwrap_or_default();
+    let (status, body) = match line {
+        b"GET /metrics HTTP/1.1" | b"GET /metrics HTTP/1.0" => ("200 OK", registry.render().await?),
+        _ => ("404 Not Found", String::from("not found\n")),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[async_trait]
+impl Linkable for Export {
+    /// Lắng nghe và phục vụ yêu cầu trong một tác vụ nền
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả lắng nghe
+    async fn start(&mut self) -> Result<()> {
+        let listener = TcpListener::bind(&self.addr)
+            .await
+            .map_err(|e| Error::io("bind", e))?;
+        self.local = Some(listener.local_addr()?);
+
+        let registry = self.registry.clone();
+        let read = self.read;
+        self.task = Some(tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        // Lỗi tạm thời như hết mô tả tệp không được dừng điểm cuối
+                        tracing::debug!("metrics accept failed: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let registry = registry.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, registry, read).await {
+                        tracing::debug!("metrics request failed: {}", e);
+                    }
+                });
+            }
+        }));
+        self.state.set_mode(Mode::Ready).await
+    }
+
+    /// Dừng phục vụ yêu cầu
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả dừng
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.state.set_mode(Mode::Close).await
+    }
+
+    /// Lấy trạng thái của điểm cuối
+    ///
+    /// # Returns
+    /// * `Result<Mode>` - Trạng thái hiện tại
+    async fn state(&self) -> Result<Mode> {
+        self.state.mode().await
+    }
+}
@@ -1,5 +1,7 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use async_trait::async_trait;
+use tokio::time::timeout;
 
 use crate::core::error::{Error, Result};
 use crate::core::state::{Mode, State};
@@ -68,6 +70,87 @@ pub trait Guardable {
     async fn expose(&self, data: &[u8]) -> Result<Vec<u8>>;
 }
 
+/// Một thao tác ghi trong lô của `Storable::batch`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// Ghi giá trị cho khóa
+    Put(String, Vec<u8>),
+    /// Xóa khóa
+    Delete(String),
+}
+
+/// Trait chung cho các kho lưu trữ khóa-giá trị
+#[async_trait]
+pub trait Storable: Send + Sync {
+    /// Đọc giá trị của khóa
+    ///
+    /// # Arguments
+    /// * `key` - Khóa cần đọc
+    ///
+    /// # Returns
+    /// * `Result<Option<Vec<u8>>>` - Giá trị, hoặc `None` nếu khóa không tồn tại
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Ghi giá trị cho khóa, thay thế giá trị cũ nếu có
+    ///
+    /// # Arguments
+    /// * `key` - Khóa cần ghi
+    /// * `value` - Giá trị mới
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả ghi
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()>;
+
+    /// Xóa khóa; xóa khóa không tồn tại không phải là lỗi
+    ///
+    /// # Arguments
+    /// * `key` - Khóa cần xóa
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả xóa
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Kiểm tra khóa có tồn tại không
+    ///
+    /// # Arguments
+    /// * `key` - Khóa cần kiểm tra
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `true` nếu khóa tồn tại
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.get(key).await?.is_some())
+    }
+
+    /// Liệt kê các khóa bắt đầu bằng một tiền tố
+    ///
+    /// # Arguments
+    /// * `prefix` - Tiền tố, chuỗi rỗng để liệt kê tất cả
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>>` - Các khóa theo thứ tự tăng dần
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+
+    /// Áp dụng một lô thao tác theo thứ tự
+    ///
+    /// Cài đặt mặc định áp dụng lần lượt từng thao tác; kho nào có thể
+    /// ghi cả lô một cách nguyên tử thì ghi đè phương thức này.
+    ///
+    /// # Arguments
+    /// * `ops` - Các thao tác cần áp dụng
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả áp dụng
+    async fn batch(&self, ops: Vec<Op>) -> Result<()> {
+        for op in ops {
+            match op {
+                Op::Put(key, value) => self.put(&key, value).await?,
+                Op::Delete(key) => self.delete(&key).await?,
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Cấu hình cho liên kết
 #[derive(Debug, Clone)]
 pub struct Settings {
@@ -86,11 +169,44 @@ impl Default for Settings {
     }
 }
 
+impl Settings {
+    /// Tính các thời hạn thao tác từ `wait` (tính bằng giây)
+    ///
+    /// Kết nối, đọc và ghi dùng trực tiếp `wait`; thời gian rảnh cho phép gấp đôi.
+    ///
+    /// # Returns
+    /// * `Deadline` - Các thời hạn cho từng thao tác
+    pub fn deadline(&self) -> Deadline {
+        let wait = Duration::from_secs(self.wait);
+        Deadline {
+            connect: wait,
+            read: wait,
+            write: wait,
+            idle: wait * 2,
+        }
+    }
+}
+
+/// Thời hạn cho từng loại thao tác trên liên kết
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    /// Thời gian tối đa để thiết lập kết nối
+    pub connect: Duration,
+    /// Thời gian tối đa để đọc xong một khung dữ liệu
+    pub read: Duration,
+    /// Thời gian tối đa để ghi xong một khung dữ liệu
+    pub write: Duration,
+    /// Thời gian tối đa không có hoạt động nào trước khi đóng
+    pub idle: Duration,
+}
+
 /// Thực hiện chính của liên kết
 pub struct Link {
     settings: Arc<Settings>,
     state: Arc<State>,
     handlers: Vec<Box<dyn Handler>>,
+    deadline: Deadline,
+    last: Instant,
 }
 
 impl Link {
@@ -102,10 +218,13 @@ impl Link {
     /// # Returns
     /// * `Self` - Liên kết mới được tạo
     pub fn new(settings: Settings) -> Self {
+        let deadline = settings.deadline();
         Self {
             settings: Arc::new(settings),
             state: Arc::new(State::new()),
             handlers: Vec::new(),
+            deadline,
+            last: Instant::now(),
         }
     }
 
@@ -116,12 +235,63 @@ impl Link {
     pub fn add_handler<H: Handler + 'static>(&mut self, handler: H) {
         self.handlers.push(Box::new(handler));
     }
+
+    /// Ghi đè các thời hạn được tính từ cấu hình
+    ///
+    /// # Arguments
+    /// * `deadline` - Các thời hạn mới
+    pub fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    /// Lấy bản sao chia sẻ của trạng thái, ví dụ để đăng ký với `Registry`
+    ///
+    /// # Returns
+    /// * `State` - Trạng thái dùng chung với liên kết
+    pub fn shared(&self) -> State {
+        (*self.state).clone()
+    }
+
+    /// Chuyển liên kết sang trạng thái khác, ví dụ `Mode::Active` hoặc `Mode::Pause`
+    ///
+    /// # Arguments
+    /// * `mode` - Trạng thái mới, phải hợp lệ theo `Mode::can`
+    ///
+    /// # Returns
+    /// * `Result<()>` - Lỗi `State` nếu chuyển trạng thái không hợp lệ
+    pub async fn set_mode(&self, mode: Mode) -> Result<()> {
+        self.state.set_mode(mode).await
+    }
+
+    /// Kiểm tra thời gian rảnh và đóng liên kết nếu đã quá hạn
+    ///
+    /// # Returns
+    /// * `Result<()>` - Lỗi `Timeout` nếu liên kết đã rảnh quá lâu
+    async fn touch(&mut self) -> Result<()> {
+        if self.last.elapsed() > self.deadline.idle {
+            self.expire("link idle").await?;
+            return Err(Error::Timeout("link idle".into()));
+        }
+        self.last = Instant::now();
+        Ok(())
+    }
+
+    /// Đóng liên kết sau khi một thao tác quá hạn
+    ///
+    /// # Arguments
+    /// * `reason` - Lý do quá hạn
+    async fn expire(&self, reason: &str) -> Result<()> {
+        tracing::debug!("{} expired: {}", self.settings.name, reason);
+        self.state.record_fault(&Error::Timeout(reason.into())).await?;
+        self.state.set_mode(Mode::Close).await
+    }
 }
 
 #[async_trait]
 impl Linkable for Link {
     async fn start(&mut self) -> Result<()> {
         self.state.set_mode(Mode::Ready).await?;
+        self.last = Instant::now();
         Ok(())
     }
 
@@ -140,19 +310,32 @@ impl Movable for Link {
     async fn send(&mut self, data: &[u8]) -> Result<usize> {
         // Kiểm tra trạng thái trước khi gửi
         let mode = self.state.mode().await?;
-        if mode != Mode::Ready {
+        if !matches!(mode, Mode::Ready | Mode::Active) {
             return Err(Error::State("link is not ready".into()));
         }
 
         if data.len() > self.settings.size {
-            return Err(Error::Net("data too large".into()));
+            return Err(Error::Capacity("data too large".into()));
         }
 
-        // Chạy dữ liệu qua các bộ xử lý
-        let mut processed = data.to_vec();
-        for handler in &self.handlers {
-            processed = handler.handle(&processed).await?;
-        }
+        self.touch().await?;
+
+        // Chạy dữ liệu qua các bộ xử lý trong thời hạn ghi
+        let handlers = &self.handlers;
+        let processed = timeout(self.deadline.write, async {
+            let mut processed = data.to_vec();
+            for handler in handlers {
+                processed = handler.handle(&processed).await?;
+            }
+            Ok::<_, Error>(processed)
+        }).await;
+        let processed = match processed {
+            Ok(processed) => processed?,
+            Err(_) => {
+                self.expire("write deadline").await?;
+                return Err(Error::Timeout("write deadline exceeded".into()));
+            }
+        };
 
         self.state.record_send(processed.len()).await?;
         Ok(processed.len())
@@ -161,14 +344,16 @@ impl Movable for Link {
     async fn receive(&mut self, buf: &mut [u8]) -> Result<usize> {
         // Kiểm tra trạng thái trước khi nhận
         let mode = self.state.mode().await?;
-        if mode != Mode::Ready {
+        if !matches!(mode, Mode::Ready | Mode::Active) {
             return Err(Error::State("link is not ready".into()));
         }
 
         if buf.len() > self.settings.size {
-            return Err(Error::Net("buffer too large".into()));
+            return Err(Error::Capacity("buffer too large".into()));
         }
 
+        self.touch().await?;
+
         self.state.record_receive(buf.len()).await?;
         Ok(buf.len())
     }
@@ -6,6 +6,10 @@ pub mod error;
 /// Module này được sử dụng để quản lý các kết nối và trạng thái của hệ thống.
 pub mod link;
 
+/// Module xuất chỉ số chứa danh sách trạng thái và điểm cuối HTTP.
+/// Module này được sử dụng để kết xuất chỉ số của các liên kết theo định dạng Prometheus.
+pub mod export;
+
 /// Module trạng thái chứa các trạng thái cốt lõi trong hệ thống.
 /// Module này được sử dụng để quản lý trạng thái của hệ thống.
 pub mod state;
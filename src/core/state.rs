@@ -1,12 +1,23 @@
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::{BTreeMap, VecDeque};
+use tokio::sync::{watch, RwLock};
 use serde::{Serialize, Deserialize};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::core::error::Result;
+use crate::core::error::{Error, Result};
+
+/// Số lần chuyển trạng thái gần nhất được lưu lại
+const HISTORY: usize = 64;
+
+/// Độ dài cửa sổ ngắn và dài để tính tốc độ, tính bằng giây
+const RECENT: u64 = 10;
+const MINUTE: u64 = 60;
+
+/// Các ngưỡng mặc định của biểu đồ độ trễ, tính bằng giây
+const BOUNDS: [f64; 12] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
 
 /// Trạng thái hiện tại của một liên kết
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Mode {
     Init,
     Ready,
@@ -15,6 +26,137 @@ pub enum Mode {
     Close,
 }
 
+impl Mode {
+    /// Kiểm tra một lần chuyển trạng thái có hợp lệ hay không
+    ///
+    /// `Close` là trạng thái cuối: không thể chuyển ra khỏi nó.
+    ///
+    /// # Arguments
+    /// * `next` - Trạng thái muốn chuyển tới
+    ///
+    /// # Returns
+    /// * `bool` - `true` nếu được phép chuyển
+    pub fn can(self, next: Mode) -> bool {
+        use Mode::*;
+        matches!(
+            (self, next),
+            (Init, Ready) | (Init, Close)
+                | (Ready, Active) | (Ready, Pause) | (Ready, Close)
+                | (Active, Ready) | (Active, Pause) | (Active, Close)
+                | (Pause, Ready) | (Pause, Active) | (Pause, Close)
+        )
+    }
+}
+
+/// Một lần chuyển trạng thái đã xảy ra
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: Mode,
+    pub to: Mode,
+    /// Thời điểm chuyển, tính bằng mili giây Unix
+    pub at: i64,
+}
+
+/// Số khung đã gửi và nhận
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frames {
+    pub send: usize,
+    pub receive: usize,
+}
+
+/// Tốc độ truyền trung bình (byte/giây) trên các cửa sổ trượt
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rate {
+    /// Trung bình trong 10 giây gần nhất
+    pub recent: f64,
+    /// Trung bình trong 60 giây gần nhất
+    pub minute: f64,
+}
+
+/// Biểu đồ phân bố độ trễ theo các ngưỡng cố định
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// Ngưỡng trên của từng nhóm, tính bằng giây
+    pub bounds: Vec<f64>,
+    /// Số mẫu trong từng nhóm; phần tử cuối đếm các mẫu vượt mọi ngưỡng
+    pub counts: Vec<u64>,
+    /// Tổng giá trị các mẫu, tính bằng giây
+    pub sum: f64,
+    /// Tổng số mẫu
+    pub count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            bounds: BOUNDS.to_vec(),
+            counts: vec![0; BOUNDS.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    /// Ghi nhận một mẫu độ trễ
+    ///
+    /// # Arguments
+    /// * `sample` - Độ trễ đo được
+    pub fn observe(&mut self, sample: Duration) {
+        let secs = sample.as_secs_f64();
+        let slot = self.bounds.iter().position(|bound| secs <= *bound).unwrap_or(self.bounds.len());
+        self.counts[slot] += 1;
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+/// Một giây trong cửa sổ trượt
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    second: u64,
+    send: usize,
+    receive: usize,
+}
+
+/// Số byte theo từng giây trong 60 giây gần nhất
+#[derive(Debug, Default, Clone)]
+struct Window {
+    base: Option<Instant>,
+    slots: VecDeque<Slot>,
+}
+
+impl Window {
+    fn now(&mut self) -> u64 {
+        self.base.get_or_insert_with(Instant::now).elapsed().as_secs()
+    }
+
+    fn add(&mut self, send: usize, receive: usize) {
+        let now = self.now();
+        match self.slots.back_mut() {
+            Some(slot) if slot.second == now => {
+                slot.send += send;
+                slot.receive += receive;
+            }
+            _ => self.slots.push_back(Slot { second: now, send, receive }),
+        }
+        while self.slots.front().is_some_and(|slot| slot.second + MINUTE <= now) {
+            self.slots.pop_front();
+        }
+    }
+
+    /// Tốc độ gửi và nhận trung bình trong `span` giây gần nhất
+    fn rate(&mut self, span: u64) -> (f64, f64) {
+        let now = self.now();
+        // Liên kết mới chưa đủ `span` giây thì chia cho thời gian đã chạy
+        let span = span.min(now + 1);
+        let (send, receive) = self.slots.iter()
+            .filter(|slot| slot.second + span > now)
+            .fold((0, 0), |(send, receive), slot| (send + slot.send, receive + slot.receive));
+        (send as f64 / span as f64, receive as f64 / span as f64)
+    }
+}
+
 /// Các chỉ số để theo dõi
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Measure {
@@ -24,12 +166,56 @@ pub struct Measure {
     #[serde(skip)]
     start_instant: Option<Instant>,
     pub start_timestamp: Option<i64>,
+    /// Thời gian khứ hồi đã được làm mượt
+    pub rtt: Option<Duration>,
+    /// Độ dao động của thời gian khứ hồi
+    pub jitter: Option<Duration>,
+    /// Số khung đã gửi và nhận
+    pub frames: Frames,
+    /// Số lỗi theo từng loại, xem `Error::kind`
+    pub errors: BTreeMap<String, usize>,
+    /// Tốc độ gửi trên các cửa sổ trượt
+    pub send_rate: Rate,
+    /// Tốc độ nhận trên các cửa sổ trượt
+    pub receive_rate: Rate,
+    /// Phân bố độ trễ
+    pub latency: Histogram,
+    /// Các bộ đếm theo tên, ví dụ số khung bị giới hạn tốc độ
+    pub counters: BTreeMap<String, usize>,
+    #[serde(skip)]
+    window: Window,
+}
+
+impl Measure {
+    /// Thời điểm bắt đầu có dữ liệu đi qua
+    ///
+    /// # Returns
+    /// * `Option<Instant>` - `None` nếu chưa có dữ liệu nào
+    pub fn started(&self) -> Option<Instant> {
+        self.start_instant
+    }
+
+    /// Thời gian đã trôi qua kể từ khi bắt đầu có dữ liệu
+    ///
+    /// # Returns
+    /// * `Option<Duration>` - `None` nếu chưa có dữ liệu nào
+    pub fn uptime(&self) -> Option<Duration> {
+        self.start_instant.map(|start| start.elapsed())
+    }
+
+    fn begin(&mut self) {
+        if self.start_instant.is_none() {
+            self.start_instant = Some(Instant::now());
+            self.start_timestamp = Some(chrono::Utc::now().timestamp());
+        }
+    }
 }
 
 /// Container trạng thái an toàn cho luồng
 #[derive(Debug)]
 pub struct State {
-    mode: Arc<RwLock<Mode>>,
+    mode: Arc<watch::Sender<Mode>>,
+    history: Arc<RwLock<Vec<Transition>>>,
     measure: Arc<RwLock<Measure>>,
 }
 
@@ -37,19 +223,28 @@ impl Clone for State {
     fn clone(&self) -> Self {
         Self {
             mode: Arc::clone(&self.mode),
+            history: Arc::clone(&self.history),
             measure: Arc::clone(&self.measure),
         }
     }
 }
 
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl State {
     /// Tạo một thể hiện mới của `State`
     ///
     /// # Returns
     /// * `Self` - Một thể hiện mới của `State`
     pub fn new() -> Self {
+        let (mode, _) = watch::channel(Mode::Init);
         Self {
-            mode: Arc::new(RwLock::new(Mode::Init)),
+            mode: Arc::new(mode),
+            history: Arc::new(RwLock::new(Vec::new())),
             measure: Arc::new(RwLock::new(Measure::default())),
         }
     }
@@ -59,18 +254,84 @@ impl State {
     /// # Returns
     /// * `Result<Mode>` - Trạng thái hiện tại của `State`
     pub async fn mode(&self) -> Result<Mode> {
-        Ok(*self.mode.read().await)
+        Ok(*self.mode.borrow())
     }
 
     /// Đặt trạng thái mới cho `State`
     ///
+    /// Đặt lại trạng thái hiện tại không có tác dụng; các lần chuyển
+    /// không có trong bảng `Mode::can` bị từ chối.
+    ///
     /// # Arguments
     /// * `mode` - Trạng thái mới để đặt
     ///
     /// # Returns
     /// * `Result<()>` - Kết quả của việc đặt trạng thái mới
     pub async fn set_mode(&self, mode: Mode) -> Result<()> {
-        *self.mode.write().await = mode;
+        let mut from = None;
+        let mut denied = None;
+        self.mode.send_if_modified(|current| {
+            if *current == mode {
+                return false;
+            }
+            if !current.can(mode) {
+                denied = Some(*current);
+                return false;
+            }
+            from = Some(*current);
+            *current = mode;
+            true
+        });
+
+        if let Some(current) = denied {
+            return Err(Error::State(format!("invalid transition from {:?} to {:?}", current, mode)));
+        }
+        if let Some(from) = from {
+            let mut history = self.history.write().await;
+            if history.len() >= HISTORY {
+                history.remove(0);
+            }
+            history.push(Transition {
+                from,
+                to: mode,
+                at: chrono::Utc::now().timestamp_millis(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Lấy các lần chuyển trạng thái gần nhất, cũ nhất trước
+    ///
+    /// # Returns
+    /// * `Result<Vec<Transition>>` - Lịch sử chuyển trạng thái
+    pub async fn history(&self) -> Result<Vec<Transition>> {
+        Ok(self.history.read().await.clone())
+    }
+
+    /// Đăng ký nhận thông báo mỗi khi trạng thái thay đổi
+    ///
+    /// # Returns
+    /// * `watch::Receiver<Mode>` - Bộ nhận trạng thái
+    pub fn subscribe(&self) -> watch::Receiver<Mode> {
+        self.mode.subscribe()
+    }
+
+    /// Chờ cho tới khi trạng thái đạt `mode`
+    ///
+    /// # Arguments
+    /// * `mode` - Trạng thái cần chờ
+    ///
+    /// # Returns
+    /// * `Result<()>` - Lỗi nếu liên kết đóng trước khi đạt trạng thái
+    pub async fn until(&self, mode: Mode) -> Result<()> {
+        let mut receiver = self.mode.subscribe();
+        let reached = receiver
+            .wait_for(|current| *current == mode || *current == Mode::Close)
+            .await
+            .map_err(|_| Error::State("state dropped".into()))?;
+        if *reached != mode {
+            return Err(Error::Closed(format!("closed before reaching {:?}", mode)));
+        }
         Ok(())
     }
 
@@ -79,10 +340,38 @@ impl State {
     /// # Returns
     /// * `Result<Measure>` - Các chỉ số hiện tại của `State`
     pub async fn measure(&self) -> Result<Measure> {
-        Ok(self.measure.read().await.clone())
+        let mut measure = self.measure.write().await;
+        let (send, receive) = measure.window.rate(RECENT);
+        measure.send_rate.recent = send;
+        measure.receive_rate.recent = receive;
+        let (send, receive) = measure.window.rate(MINUTE);
+        measure.send_rate.minute = send;
+        measure.receive_rate.minute = receive;
+        Ok(measure.clone())
+    }
+
+    /// Khôi phục các chỉ số, ví dụ từ bản sao lưu khi chuyển nút
+    ///
+    /// Tốc độ truyền được tính lại từ đầu; thời điểm bắt đầu được giữ theo `start_timestamp`.
+    ///
+    /// # Arguments
+    /// * `measure` - Các chỉ số cần khôi phục
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả của việc khôi phục
+    pub async fn restore(&self, measure: Measure) -> Result<()> {
+        let elapsed = measure.start_timestamp
+            .map(|start| chrono::Utc::now().timestamp().saturating_sub(start).max(0) as u64);
+        let start_instant = elapsed.and_then(|secs| Instant::now().checked_sub(Duration::from_secs(secs)));
+        *self.measure.write().await = Measure {
+            start_instant,
+            window: Window::default(),
+            ..measure
+        };
+        Ok(())
     }
 
-    /// Ghi nhận số lượng byte đã gửi
+    /// Ghi nhận một khung đã gửi
     ///
     /// # Arguments
     /// * `bytes` - Số lượng byte đã gửi
@@ -92,14 +381,13 @@ impl State {
     pub async fn record_send(&self, bytes: usize) -> Result<()> {
         let mut measure = self.measure.write().await;
         measure.send += bytes;
-        if measure.start_instant.is_none() {
-            measure.start_instant = Some(Instant::now());
-            measure.start_timestamp = Some(chrono::Utc::now().timestamp());
-        }
+        measure.frames.send += 1;
+        measure.window.add(bytes, 0);
+        measure.begin();
         Ok(())
     }
 
-    /// Ghi nhận số lượng byte đã nhận
+    /// Ghi nhận một khung đã nhận
     ///
     /// # Arguments
     /// * `bytes` - Số lượng byte đã nhận
@@ -109,10 +397,9 @@ impl State {
     pub async fn record_receive(&self, bytes: usize) -> Result<()> {
         let mut measure = self.measure.write().await;
         measure.receive += bytes;
-        if measure.start_instant.is_none() {
-            measure.start_instant = Some(Instant::now());
-            measure.start_timestamp = Some(chrono::Utc::now().timestamp());
-        }
+        measure.frames.receive += 1;
+        measure.window.add(0, bytes);
+        measure.begin();
         Ok(())
     }
 
@@ -125,4 +412,70 @@ impl State {
         measure.error += 1;
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// Ghi nhận lỗi kèm loại lỗi
+    ///
+    /// # Arguments
+    /// * `error` - Lỗi đã xảy ra
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả của việc ghi nhận lỗi
+    pub async fn record_fault(&self, error: &Error) -> Result<()> {
+        let mut measure = self.measure.write().await;
+        measure.error += 1;
+        *measure.errors.entry(error.kind().to_string()).or_insert(0) += 1;
+        Ok(())
+    }
+
+    /// Tăng một bộ đếm theo tên
+    ///
+    /// # Arguments
+    /// * `name` - Tên bộ đếm
+    /// * `count` - Giá trị cần cộng thêm
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả của việc ghi nhận
+    pub async fn record_count(&self, name: &str, count: usize) -> Result<()> {
+        *self.measure.write().await.counters.entry(name.to_string()).or_insert(0) += count;
+        Ok(())
+    }
+
+    /// Ghi nhận một mẫu độ trễ
+    ///
+    /// # Arguments
+    /// * `sample` - Độ trễ đo được
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả của việc ghi nhận
+    pub async fn record_latency(&self, sample: Duration) -> Result<()> {
+        self.measure.write().await.latency.observe(sample);
+        Ok(())
+    }
+
+    /// Ghi nhận một mẫu thời gian khứ hồi
+    ///
+    /// Mẫu cũng được đưa vào biểu đồ độ trễ. Làm mượt theo RFC 6298: `rtt` là trung bình trượt với hệ số 1/8,
+    /// `jitter` là độ lệch trung bình với hệ số 1/4.
+    ///
+    /// # Arguments
+    /// * `sample` - Thời gian khứ hồi vừa đo được
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả của việc ghi nhận
+    pub async fn record_rtt(&self, sample: Duration) -> Result<()> {
+        let mut measure = self.measure.write().await;
+        measure.latency.observe(sample);
+        match (measure.rtt, measure.jitter) {
+            (Some(rtt), Some(jitter)) => {
+                let delta = rtt.abs_diff(sample);
+                measure.jitter = Some(jitter * 3 / 4 + delta / 4);
+                measure.rtt = Some(rtt * 7 / 8 + sample / 8);
+            }
+            _ => {
+                measure.rtt = Some(sample);
+                measure.jitter = Some(sample / 2);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::{Error, Result};
+
+/// Number of decisions kept in the log
+const LOG: usize = 1024;
+
+/// Direction of a connection relative to the local node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// A peer asks the local node to reach a route
+    Inbound,
+    /// The local node dials a route on behalf of a peer
+    Outbound,
+    /// Either direction
+    Both,
+}
+
+impl Direction {
+    fn covers(self, other: Direction) -> bool {
+        self == Direction::Both || self == other
+    }
+}
+
+/// Whether a matching grant allows or denies the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+/// One entry of the access control list
+///
+/// `subjects` holds peer identities, `@group` names or `*`; `routes` holds route
+/// names, `prefix*` patterns or `*`. An empty `ports` list matches every port.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grant {
+    pub subjects: Vec<String>,
+    pub routes: Vec<String>,
+    #[serde(default)]
+    pub ports: Vec<(u16, u16)>,
+    #[serde(default = "both")]
+    pub direction: Direction,
+    #[serde(default = "allow")]
+    pub effect: Effect,
+}
+
+fn both() -> Direction {
+    Direction::Both
+}
+
+fn allow() -> Effect {
+    Effect::Allow
+}
+
+impl Grant {
+    /// Allows `subject` to reach `route` on any port and in either direction
+    pub fn allow(subject: &str, route: &str) -> Self {
+        Self {
+            subjects: vec![subject.to_string()],
+            routes: vec![route.to_string()],
+            ports: Vec::new(),
+            direction: Direction::Both,
+            effect: Effect::Allow,
+        }
+    }
+
+    /// Denies `subject` from reaching `route` on any port and in either direction
+    pub fn deny(subject: &str, route: &str) -> Self {
+        Self {
+            effect: Effect::Deny,
+            ..Self::allow(subject, route)
+        }
+    }
+}
+
+/// Policy file contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// Effect when no grant matches
+    #[serde(default = "deny")]
+    pub default: Effect,
+    /// Group name to member identities
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub grants: Vec<Grant>,
+}
+
+fn deny() -> Effect {
+    Effect::Deny
+}
+
+/// What is being asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub peer: String,
+    pub route: String,
+    pub port: u16,
+    pub direction: Direction,
+}
+
+/// Result of one evaluation, as recorded in the decision log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub request: Request,
+    pub effect: Effect,
+    /// Index of the grant that decided, `None` when the default applied
+    pub grant: Option<usize>,
+    /// Milliseconds since the Unix epoch
+    pub at: u64,
+}
+
+/// Access control policy mapping peers and groups to the routes they may reach
+///
+/// A matching `Deny` grant always wins over a matching `Allow` grant; when nothing
+/// matches, the default effect applies, which is `Deny` unless configured otherwise.
+pub struct Policy {
+    default: Effect,
+    groups: HashMap<String, Vec<String>>,
+    grants: Vec<Grant>,
+    log: Mutex<VecDeque<Decision>>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Policy {
+    /// Creates an empty deny-by-default policy
+    pub fn new() -> Self {
+        Self::from_config(Config {
+            default: Effect::Deny,
+            groups: HashMap::new(),
+            grants: Vec::new(),
+        })
+    }
+
+    pub fn from_config(config: Config) -> Self {
+        Self {
+            default: config.default,
+            groups: config.groups,
+            grants: config.grants,
+            log: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Loads a JSON policy file
+    pub async fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = tokio::fs::read(path)
+            .await
+            .map_err(|e| Error::io(path.display().to_string(), e))?;
+        let config = serde_json::from_slice(&text)
+            .map_err(|e| Error::Guard(format!("invalid access policy: {}", e)))?;
+        Ok(Self::from_config(config))
+    }
+
+    /// Sets the effect used when no grant matches
+    pub fn set_default(&mut self, effect: Effect) {
+        self.default = effect;
+    }
+
+    /// Adds `members` to `group`, referenced in grants as `@group`
+    pub fn add_group(&mut self, group: &str, members: &[&str]) {
+        self.groups
+            .entry(group.to_string())
+            .or_default()
+            .extend(members.iter().map(|member| member.to_string()));
+    }
+
+    pub fn add_grant(&mut self, grant: Grant) {
+        self.grants.push(grant);
+    }
+
+    /// Decides a request and records the decision
+    pub fn evaluate(&self, request: Request) -> Decision {
+        let mut decided = None;
+        for (index, grant) in self.grants.iter().enumerate() {
+            if !self.matches(grant, &request) {
+                continue;
+            }
+            match grant.effect {
+                Effect::Deny => {
+                    decided = Some(index);
+                    break;
+                }
+                Effect::Allow if decided.is_none() => decided = Some(index),
+                Effect::Allow => {}
+            }
+        }
+
+        let decision = Decision {
+            effect: decided.map_or(self.default, |index| self.grants[index].effect),
+            grant: decided,
+            at: now(),
+            request,
+        };
+        tracing::info!(
+            "access {:?}: peer {} route {} port {} {:?} (grant {:?})",
+            decision.effect,
+            decision.request.peer,
+            decision.request.route,
+            decision.request.port,
+            decision.request.direction,
+            decision.grant,
+        );
+
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        if log.len() == LOG {
+            log.pop_front();
+        }
+        log.push_back(decision.clone());
+        decision
+    }
+
+    /// Evaluates a request, failing with `Error::Denied` unless it is allowed
+    pub fn check(&self, request: Request) -> Result<()> {
+        let decision = self.evaluate(request);
+        match decision.effect {
+            Effect::Allow => Ok(()),
+            Effect::Deny => Err(Error::Denied(format!(
+                "peer {} may not reach route {} on port {}",
+                decision.request.peer, decision.request.route, decision.request.port,
+            ))),
+        }
+    }
+
+    /// Returns the most recent decisions, oldest first
+    pub fn decisions(&self) -> Vec<Decision> {
+        self.log.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    fn matches(&self, grant: &Grant, request: &Request) -> bool {
+        grant.direction.covers(request.direction)
+            && grant.subjects.iter().any(|subject| self.subject(subject, &request.peer))
+            && grant.routes.iter().any(|route| pattern(route, &request.route))
+            && (grant.ports.is_empty() || grant.ports.iter().any(|(low, high)| (*low..=*high).contains(&request.port)))
+    }
+
+    fn subject(&self, subject: &str, peer: &str) -> bool {
+        match subject.strip_prefix('@') {
+            Some(group) => self.groups.get(group).is_some_and(|members| members.iter().any(|m| m == peer)),
+            None => pattern(subject, peer),
+        }
+    }
+}
+
+/// Matches `*`, `prefix*` or an exact name
+fn pattern(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
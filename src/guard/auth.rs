@@ -1,35 +1,61 @@
 use std::sync::Arc;
+use std::time::Duration;
 use async_trait::async_trait;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 
 use crate::core::error::{Error, Result};
 use crate::core::link::Guardable;
+use crate::guard::replay::{Replay, Role, HEADER};
+use crate::guard::ring::{self, Ring, ID};
 
 type HmacSha256 = Hmac<Sha256>;
 
 const SIGNATURE_LENGTH: usize = 32;
 
 pub struct Auth {
-    key: Arc<[u8]>,
+    ring: Arc<Ring>,
+    replay: Replay,
 }
 
 impl Auth {
     pub fn new(key: &[u8]) -> Self {
+        Self::with_ring(Arc::new(Ring::new(key)))
+    }
+
+    /// Uses a shared keyring, so keys can be rotated while links stay up
+    pub fn with_ring(ring: Arc<Ring>) -> Self {
         Self {
-            key: Arc::from(key),
+            ring,
+            replay: Replay::default(),
         }
     }
 
-    fn compute_hmac(&self, data: &[u8]) -> Vec<u8> {
-        let mut mac = HmacSha256::new_from_slice(&self.key)
+    /// Returns the keyring used to sign and verify frames
+    pub fn ring(&self) -> Arc<Ring> {
+        self.ring.clone()
+    }
+
+    /// Sets how far a frame timestamp may drift from the local clock before it is rejected
+    pub fn set_age(&mut self, age: Duration) {
+        self.replay.set_age(age);
+    }
+
+    /// Binds frames to this side of the connection; the peer must set the opposite role
+    pub fn set_role(&mut self, role: Role) {
+        self.replay.set_role(role);
+    }
+
+    fn compute_hmac(key: &[u8], header: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = HmacSha256::new_from_slice(key)
             .expect("HMAC can take key of any size");
+        mac.update(header);
         mac.update(data);
         mac.finalize().into_bytes().to_vec()
     }
 
-    fn verify_hmac(&self, data: &[u8], signature: &[u8]) -> bool {
-        let computed = self.compute_hmac(data);
+    fn verify_hmac(key: &[u8], header: &[u8], data: &[u8], signature: &[u8]) -> bool {
+        let computed = Self::compute_hmac(key, header, data);
         if computed.len() != signature.len() {
             return false;
         }
@@ -45,12 +71,17 @@ impl Auth {
 #[async_trait]
 impl Guardable for Auth {
     async fn protect(&self, data: &[u8]) -> Result<Vec<u8>> {
-        // Compute HMAC signature
-        let signature = self.compute_hmac(data);
+        // Sign with the newest active key; the key id and replay header are covered by the signature
+        let key = self.ring.current()?;
+        let mut header = Vec::with_capacity(ID + HEADER);
+        header.extend_from_slice(&key.id.to_be_bytes());
+        header.extend_from_slice(&self.replay.stamp());
+        let signature = Self::compute_hmac(key.auth(), &header, data);
         
-        // Create protected data with signature
-        let mut protected = Vec::with_capacity(SIGNATURE_LENGTH + data.len());
+        // Create protected data with signature and header
+        let mut protected = Vec::with_capacity(SIGNATURE_LENGTH + header.len() + data.len());
         protected.extend_from_slice(&signature);
+        protected.extend_from_slice(&header);
         protected.extend_from_slice(data);
         
         Ok(protected)
@@ -58,18 +89,24 @@ impl Guardable for Auth {
 
     async fn expose(&self, data: &[u8]) -> Result<Vec<u8>> {
         // Verify data length
-        if data.len() < SIGNATURE_LENGTH {
-            return Err(Error::Guard("data too short for signature".into()));
+        if data.len() < SIGNATURE_LENGTH + ID + HEADER {
+            return Err(Error::Auth("data too short for signature".into()));
         }
         
-        // Split signature and content
-        let (signature, content) = data.split_at(SIGNATURE_LENGTH);
+        // Split signature, header and content
+        let (signature, rest) = data.split_at(SIGNATURE_LENGTH);
+        let (header, content) = rest.split_at(ID + HEADER);
+        let (id, stamp) = ring::split(header).ok_or_else(|| Error::Auth("missing key id".into()))?;
+        let key = self.ring.get(id).ok_or_else(|| Error::Auth(format!("unknown or retired key {}", id)))?;
         
         // Verify signature
-        if !self.verify_hmac(content, signature) {
-            return Err(Error::Guard("invalid signature".into()));
+        if !Self::verify_hmac(key.auth(), header, content, signature) {
+            return Err(Error::Auth("invalid signature".into()));
         }
+
+        // Reject duplicate or stale frames only once the header is known to be authentic
+        self.replay.accept(stamp)?;
         
         Ok(content.to_vec())
     }
-} 
\ No newline at end of file
+}
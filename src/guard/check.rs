@@ -1,10 +1,231 @@
+use std::collections::BTreeMap;
+use std::path::Path;
 use async_trait::async_trait;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::core::error::{Error, Result};
 use crate::core::link::Guardable;
 
+/// Closure rule added with `add_rule`
+type Closure = Box<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
+/// Byte pattern given in config either as text or as a list of bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Pattern {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Pattern {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Pattern::Text(text) => text.as_bytes(),
+            Pattern::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+/// JSON type accepted by a `Schema`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Null,
+    Boolean,
+    Number,
+    Integer,
+    String,
+    Array,
+    Object,
+}
+
+/// Subset of JSON Schema: type, required properties, nested properties and items, and bounds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Schema {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, Schema>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<Schema>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    #[serde(rename = "maxLength", skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+}
+
+impl Schema {
+    /// Returns where and why `value` does not match, if it does not
+    fn validate(&self, value: &Value, path: &str) -> std::result::Result<(), String> {
+        if let Some(kind) = self.kind {
+            let matches = match kind {
+                Kind::Null => value.is_null(),
+                Kind::Boolean => value.is_boolean(),
+                Kind::Number => value.is_number(),
+                Kind::Integer => value.is_i64() || value.is_u64(),
+                Kind::String => value.is_string(),
+                Kind::Array => value.is_array(),
+                Kind::Object => value.is_object(),
+            };
+            if !matches {
+                return Err(format!("{} is not of type {:?}", path, kind));
+            }
+        }
+        if let Some(number) = value.as_f64() {
+            if self.minimum.is_some_and(|min| number < min) || self.maximum.is_some_and(|max| number > max) {
+                return Err(format!("{} is out of range", path));
+            }
+        }
+        if let (Some(text), Some(max)) = (value.as_str(), self.max_length) {
+            if text.chars().count() > max {
+                return Err(format!("{} is longer than {} characters", path, max));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            if let Some(missing) = self.required.iter().find(|key| !object.contains_key(*key)) {
+                return Err(format!("{} is missing property {}", path, missing));
+            }
+            for (key, schema) in &self.properties {
+                if let Some(value) = object.get(key) {
+                    schema.validate(value, &format!("{}.{}", path, key))?;
+                }
+            }
+        }
+        if let (Some(array), Some(items)) = (value.as_array(), &self.items) {
+            for (index, value) in array.iter().enumerate() {
+                items.validate(value, &format!("{}[{}]", path, index))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Built-in validation rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Rule {
+    /// At least `len` bytes
+    Min { len: usize },
+    /// At most `len` bytes
+    Max { len: usize },
+    /// Valid UTF-8
+    Utf8,
+    /// A JSON document matching the schema
+    Json { schema: Schema },
+    /// Matches the regular expression somewhere in the data
+    Regex { pattern: String },
+    /// Contains none of the byte patterns
+    Deny { patterns: Vec<Pattern> },
+    /// Starts with one of the magic numbers
+    Magic { allow: Vec<Pattern> },
+}
+
+impl Rule {
+    /// Name used in errors when the config does not give one
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::Min { .. } => "min",
+            Rule::Max { .. } => "max",
+            Rule::Utf8 => "utf8",
+            Rule::Json { .. } => "json",
+            Rule::Regex { .. } => "regex",
+            Rule::Deny { .. } => "deny",
+            Rule::Magic { .. } => "magic",
+        }
+    }
+}
+
+/// A rule entry in a config file, optionally named
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Spec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub rule: Rule,
+}
+
+/// Rules loaded from a config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    pub rules: Vec<Spec>,
+}
+
+/// Rule that failed for some data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: String,
+    pub reason: String,
+}
+
+impl From<Violation> for Error {
+    fn from(violation: Violation) -> Self {
+        Error::Invalid {
+            rule: violation.rule,
+            reason: violation.reason,
+        }
+    }
+}
+
+/// Closure or built-in rule, with the compiled regex if any
+enum Test {
+    Custom(Closure),
+    Builtin(Rule, Option<Regex>),
+}
+
+struct Named {
+    name: String,
+    test: Test,
+}
+
+impl Named {
+    fn apply(&self, data: &[u8]) -> std::result::Result<(), String> {
+        let rule = match &self.test {
+            Test::Custom(test) => return if test(data) { Ok(()) } else { Err("data validation failed".into()) },
+            Test::Builtin(rule, _) => rule,
+        };
+        match rule {
+            Rule::Min { len } if data.len() < *len => Err(format!("{} bytes is shorter than {}", data.len(), len)),
+            Rule::Max { len } if data.len() > *len => Err(format!("{} bytes is longer than {}", data.len(), len)),
+            Rule::Utf8 => std::str::from_utf8(data).map(|_| ()).map_err(|e| e.to_string()),
+            Rule::Json { schema } => {
+                let value: Value = serde_json::from_slice(data).map_err(|e| format!("invalid JSON: {}", e))?;
+                schema.validate(&value, "$")
+            }
+            Rule::Regex { pattern } => match &self.test {
+                Test::Builtin(_, Some(regex)) if regex.is_match(data) => Ok(()),
+                _ => Err(format!("does not match {}", pattern)),
+            },
+            Rule::Deny { patterns } => match patterns.iter().find(|p| contains(data, p.as_bytes())) {
+                Some(pattern) => Err(format!("contains denied pattern {:?}", pattern)),
+                None => Ok(()),
+            },
+            Rule::Magic { allow } if !allow.iter().any(|p| data.starts_with(p.as_bytes())) => {
+                Err("does not start with an allowed magic number".into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn contains(data: &[u8], pattern: &[u8]) -> bool {
+    data.windows(pattern.len()).any(|window| window == pattern)
+}
+
 pub struct Check {
-    rules: Vec<Box<dyn Fn(&[u8]) -> bool + Send + Sync>>,
+    rules: Vec<Named>,
+}
+
+impl Default for Check {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Check {
@@ -14,11 +235,82 @@ impl Check {
         }
     }
 
+    /// Builds a check from parsed config
+    pub fn from_config(config: Config) -> Result<Self> {
+        let mut check = Self::new();
+        for spec in config.rules {
+            let name = spec.name.unwrap_or_else(|| spec.rule.name().to_string());
+            check.add_named(&name, spec.rule)?;
+        }
+        Ok(check)
+    }
+
+    /// Loads rules from a JSON config file of the form `{"rules": [{"type": "max", "len": 1024}]}`
+    pub async fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = tokio::fs::read(path)
+            .await
+            .map_err(|e| Error::io(path.display().to_string(), e))?;
+        let config = serde_json::from_slice(&text)
+            .map_err(|e| Error::Guard(format!("invalid check config: {}", e)))?;
+        Self::from_config(config)
+    }
+
     pub fn add_rule<F>(&mut self, rule: F)
     where
         F: Fn(&[u8]) -> bool + Send + Sync + 'static,
     {
-        self.rules.push(Box::new(rule));
+        let name = format!("custom#{}", self.rules.len());
+        self.rules.push(Named {
+            name,
+            test: Test::Custom(Box::new(rule)),
+        });
+    }
+
+    /// Adds a built-in rule, named after its type
+    pub fn add(&mut self, rule: Rule) -> Result<()> {
+        let name = rule.name().to_string();
+        self.add_named(&name, rule)
+    }
+
+    /// Adds a built-in rule under the name reported when it fails
+    pub fn add_named(&mut self, name: &str, rule: Rule) -> Result<()> {
+        let regex = match &rule {
+            Rule::Regex { pattern } => Some(Regex::new(pattern).map_err(|e| Error::Invalid {
+                rule: name.to_string(),
+                reason: e.to_string(),
+            })?),
+            Rule::Deny { patterns } if patterns.iter().any(|p| p.as_bytes().is_empty()) => {
+                return Err(Error::Invalid {
+                    rule: name.to_string(),
+                    reason: "empty deny pattern would reject all data".into(),
+                });
+            }
+            _ => None,
+        };
+        self.rules.push(Named {
+            name: name.to_string(),
+            test: Test::Builtin(rule, regex),
+        });
+        Ok(())
+    }
+
+    /// Runs the rules in order and fails with the first one that rejects the data
+    pub fn validate(&self, data: &[u8]) -> Result<()> {
+        for rule in &self.rules {
+            if let Err(reason) = rule.apply(data) {
+                return Err(Violation { rule: rule.name.clone(), reason }.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs every rule and lists all that reject the data
+    pub fn report(&self, data: &[u8]) -> Vec<Violation> {
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.apply(data).err().map(|reason| Violation { rule: rule.name.clone(), reason }))
+            .collect()
     }
 }
 
@@ -26,21 +318,13 @@ impl Check {
 impl Guardable for Check {
     async fn protect(&self, data: &[u8]) -> Result<Vec<u8>> {
         // Verify all rules pass
-        for rule in &self.rules {
-            if !rule(data) {
-                return Err(Error::Guard("data validation failed".into()));
-            }
-        }
+        self.validate(data)?;
         Ok(data.to_vec())
     }
 
     async fn expose(&self, data: &[u8]) -> Result<Vec<u8>> {
         // Re-verify rules on expose
-        for rule in &self.rules {
-            if !rule(data) {
-                return Err(Error::Guard("data validation failed".into()));
-            }
-        }
+        self.validate(data)?;
         Ok(data.to_vec())
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,85 @@
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::aead::generic_array::typenum::Unsigned;
+use rand::{RngCore, thread_rng};
+
+use crate::core::error::{Error, Result};
+
+/// AEAD algorithm used by `Crypt`
+pub trait Cipher: Send + Sync {
+    /// Length of the nonce carried in each frame
+    fn nonce_len(&self) -> usize;
+
+    /// Encrypts `msg` and authenticates it together with `aad`
+    fn seal(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> Result<Vec<u8>>;
+
+    /// Decrypts `msg`, failing if it or `aad` was modified
+    fn open(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> Result<Vec<u8>>;
+
+    /// Generates a random nonce of the right length
+    fn nonce(&self) -> Vec<u8> {
+        let mut nonce = vec![0u8; self.nonce_len()];
+        thread_rng().fill_bytes(&mut nonce);
+        nonce
+    }
+}
+
+impl<A> Cipher for A
+where
+    A: Aead + Send + Sync,
+{
+    fn nonce_len(&self) -> usize {
+        <A as AeadCore>::NonceSize::USIZE
+    }
+
+    fn seal(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt(nonce.into(), Payload { msg, aad })
+            .map_err(|e| Error::Guard(e.to_string()))
+    }
+
+    fn open(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+        if nonce.len() != self.nonce_len() {
+            return Err(Error::Decrypt("invalid nonce length".into()));
+        }
+        self.decrypt(nonce.into(), Payload { msg, aad })
+            .map_err(|e| Error::Decrypt(e.to_string()))
+    }
+}
+
+/// Cipher suite identifier carried in the first byte of every `Crypt` frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Suite {
+    /// ChaCha20-Poly1305 with 12-byte random nonces
+    ChaCha20Poly1305 = 1,
+    /// AES-256-GCM with 12-byte random nonces
+    Aes256Gcm = 2,
+    /// XChaCha20-Poly1305 with 24-byte random nonces, safe for long sessions
+    XChaCha20Poly1305 = 3,
+}
+
+impl Suite {
+    /// All suites, in default order of preference
+    pub const ALL: [Suite; 3] = [Suite::ChaCha20Poly1305, Suite::XChaCha20Poly1305, Suite::Aes256Gcm];
+
+    /// Identifier written to the frame header
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Looks up a suite by identifier
+    pub fn from_id(id: u8) -> Option<Suite> {
+        Suite::ALL.into_iter().find(|suite| suite.id() == id)
+    }
+
+    /// Creates the cipher for a 32-byte key
+    pub fn cipher(self, key: &[u8]) -> Result<Box<dyn Cipher>> {
+        let invalid = |_| Error::Guard(format!("{:?} needs a 32-byte key, got {}", self, key.len()));
+        Ok(match self {
+            Suite::ChaCha20Poly1305 => Box::new(ChaCha20Poly1305::new_from_slice(key).map_err(invalid)?),
+            Suite::Aes256Gcm => Box::new(Aes256Gcm::new_from_slice(key).map_err(invalid)?),
+            Suite::XChaCha20Poly1305 => Box::new(XChaCha20Poly1305::new_from_slice(key).map_err(invalid)?),
+        })
+    }
+}
@@ -1,68 +1,201 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
 use async_trait::async_trait;
-use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, KeyInit};
-use chacha20poly1305::aead::Aead;
-use rand::{RngCore, thread_rng};
 
 use crate::core::error::{Error, Result};
 use crate::core::link::Guardable;
+use crate::guard::cipher::Suite;
+use crate::guard::derive::{self, Phrase, KEY};
+use crate::guard::replay::{Replay, Role, HEADER};
+use crate::guard::ring::{self, Ring, ID};
+
+/// Length of the cipher suite identifier at the start of each frame
+const SUITE: usize = 1;
 
 pub struct Crypt {
-    key: Arc<[u8]>,
+    ring: Arc<Ring>,
+    replay: Replay,
+    suites: Vec<Suite>,
+    suite: AtomicU8,
 }
 
 impl Crypt {
+    /// Derives the encryption key from a master secret of any length with HKDF-SHA256
     pub fn new(key: &[u8]) -> Self {
-        // Ensure key is exactly 32 bytes
-        let mut fixed_key = [0u8; 32];
-        let len = std::cmp::min(key.len(), 32);
-        fixed_key[..len].copy_from_slice(&key[..len]);
-        
+        Self::with_ring(Arc::new(Ring::new(&derive::crypt(key))))
+    }
+
+    /// Uses a 32-byte key as is, rejecting any other length
+    pub fn raw(key: &[u8]) -> Result<Self> {
+        if key.len() != KEY {
+            return Err(Error::Guard(format!("key must be {} bytes, got {}", KEY, key.len())));
+        }
+        Ok(Self::with_ring(Arc::new(Ring::new(key))))
+    }
+
+    /// Derives the key from a passphrase with the stored salt and cost parameters
+    pub fn passphrase(passphrase: &[u8], phrase: &Phrase) -> Result<Self> {
+        Ok(Self::with_ring(Arc::new(Ring::new(&phrase.derive(passphrase)?))))
+    }
+
+    /// Uses a shared keyring, so keys can be rotated while links stay up
+    ///
+    /// Frames are encrypted with the ring's encryption subkey of each key, so the
+    /// same ring can also back an `Auth`.
+    pub fn with_ring(ring: Arc<Ring>) -> Self {
         Self {
-            key: Arc::from(&fixed_key[..]),
+            ring,
+            replay: Replay::default(),
+            suites: Suite::ALL.to_vec(),
+            suite: AtomicU8::new(Suite::ALL[0].id()),
         }
     }
 
-    fn create_cipher(&self) -> ChaCha20Poly1305 {
-        let key = Key::from_slice(&self.key);
-        ChaCha20Poly1305::new(key)
+    /// Returns the keyring used to encrypt and decrypt frames
+    pub fn ring(&self) -> Arc<Ring> {
+        self.ring.clone()
+    }
+
+    /// Derives a key from a new master secret, as `new` does, and rotates to it
+    pub fn rotate(&self, key: &[u8], grace: Duration) -> u32 {
+        self.ring.rotate(&derive::crypt(key), grace)
+    }
+
+    /// Sets how far a frame timestamp may drift from the local clock before it is rejected
+    pub fn set_age(&mut self, age: Duration) {
+        self.replay.set_age(age);
     }
 
-    fn generate_nonce() -> [u8; 12] {
-        let mut nonce = [0u8; 12];
-        thread_rng().fill_bytes(&mut nonce);
-        nonce
+    /// Binds frames to this side of the connection; the peer must set the opposite role
+    pub fn set_role(&mut self, role: Role) {
+        self.replay.set_role(role);
+    }
+
+    /// Restricts the accepted suites, in order of preference, and protects with the first one
+    pub fn set_suites(&mut self, suites: &[Suite]) -> Result<()> {
+        let first = suites.first().ok_or_else(|| Error::Guard("at least one cipher suite is required".into()))?;
+        self.suite.store(first.id(), Ordering::Relaxed);
+        self.suites = suites.to_vec();
+        Ok(())
+    }
+
+    /// Returns the accepted suites, in order of preference
+    pub fn suites(&self) -> Vec<Suite> {
+        self.suites.clone()
+    }
+
+    /// Switches the suite used by `protect`, for example after negotiation
+    pub fn set_suite(&self, suite: Suite) -> Result<()> {
+        if !self.suites.contains(&suite) {
+            return Err(Error::Guard(format!("cipher suite {:?} is not allowed", suite)));
+        }
+        self.suite.store(suite.id(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Creates the guard for one connection, accepting only the negotiated `suite`
+    ///
+    /// It shares the keyring but keeps its own replay window bound to `role`, so
+    /// connections that negotiate different suites do not affect each other.
+    pub fn session(&self, suite: Suite, role: Role) -> Result<Self> {
+        if !self.suites.contains(&suite) {
+            return Err(Error::Guard(format!("cipher suite {:?} is not allowed", suite)));
+        }
+        let mut session = Self::with_ring(self.ring.clone());
+        session.set_suites(&[suite])?;
+        session.set_age(self.replay.age());
+        session.set_role(role);
+        Ok(session)
+    }
+
+    /// Returns the suite used by `protect`
+    pub fn suite(&self) -> Suite {
+        Suite::from_id(self.suite.load(Ordering::Relaxed)).unwrap_or(Suite::ALL[0])
+    }
+
+    /// Encrypts data kept at rest, for example files or journal records
+    ///
+    /// Unlike `protect` there is no replay header, so the output can be opened any
+    /// number of times. `context` is authenticated but not stored; pass the same
+    /// value to `open`, for example the path the data is stored under.
+    pub fn seal(&self, data: &[u8], context: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt(&[], context, data)
+    }
+
+    /// Decrypts the output of `seal`, failing if it, its key id or `context` differs
+    pub fn open(&self, data: &[u8], context: &[u8]) -> Result<Vec<u8>> {
+        let (plaintext, _) = self.decrypt(0, context, data)?;
+        Ok(plaintext)
+    }
+
+    /// Reads the id of the key that sealed or protected `data`, without decrypting it
+    pub fn key_id(data: &[u8]) -> Option<u32> {
+        ring::split(data.get(SUITE..)?).map(|(id, _)| id)
+    }
+
+    /// Frames `data` as `suite | key id | extra | nonce | ciphertext`
+    ///
+    /// Everything before the nonce travels in clear and is bound as associated data,
+    /// together with `context`.
+    fn encrypt(&self, extra: &[u8], context: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        // Encrypt with the current suite and the newest active key
+        let suite = self.suite();
+        let key = self.ring.current()?;
+        let cipher = suite.cipher(key.crypt())?;
+        let nonce = cipher.nonce();
+
+        let mut header = Vec::with_capacity(SUITE + ID + extra.len() + context.len());
+        header.push(suite.id());
+        header.extend_from_slice(&key.id.to_be_bytes());
+        header.extend_from_slice(extra);
+        let clear = header.len();
+        header.extend_from_slice(context);
+        let ciphertext = cipher.seal(&nonce, &header, data)?;
+
+        let mut framed = Vec::with_capacity(clear + nonce.len() + ciphertext.len());
+        framed.extend_from_slice(&header[..clear]);
+        framed.extend_from_slice(&nonce);
+        framed.extend(ciphertext);
+        Ok(framed)
+    }
+
+    /// Opens a frame built by `encrypt`, returning the plaintext and the `extra` bytes
+    fn decrypt<'a>(&self, extra_len: usize, context: &[u8], data: &'a [u8]) -> Result<(Vec<u8>, &'a [u8])> {
+        if data.len() < SUITE + ID + extra_len {
+            return Err(Error::Decrypt("invalid data length".into()));
+        }
+
+        let (header, rest) = data.split_at(SUITE + ID + extra_len);
+        let suite = Suite::from_id(header[0])
+            .filter(|suite| self.suites.contains(suite))
+            .ok_or_else(|| Error::Decrypt(format!("cipher suite {} is not allowed", header[0])))?;
+        let (id, extra) = ring::split(&header[SUITE..]).ok_or_else(|| Error::Decrypt("missing key id".into()))?;
+        let key = self.ring.get(id).ok_or_else(|| Error::Decrypt(format!("unknown or retired key {}", id)))?;
+        let cipher = suite.cipher(key.crypt())?;
+
+        if rest.len() < cipher.nonce_len() {
+            return Err(Error::Decrypt("invalid data length".into()));
+        }
+        let (nonce, ciphertext) = rest.split_at(cipher.nonce_len());
+        let aad = [header, context].concat();
+        let plaintext = cipher.open(nonce, &aad, ciphertext)?;
+        Ok((plaintext, extra))
     }
 }
 
 #[async_trait]
 impl Guardable for Crypt {
     async fn protect(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let cipher = self.create_cipher();
-        let nonce = Self::generate_nonce();
-        let nonce = Nonce::from_slice(&nonce);
-
-        let ciphertext = cipher
-            .encrypt(nonce, data)
-            .map_err(|e| Error::Guard(e.to_string()))?;
-
-        let mut protected = Vec::with_capacity(12 + ciphertext.len());
-        protected.extend_from_slice(nonce.as_slice());
-        protected.extend(ciphertext);
-        Ok(protected)
+        // The replay header travels in clear between the key id and the nonce
+        self.encrypt(&self.replay.stamp(), &[], data)
     }
 
     async fn expose(&self, data: &[u8]) -> Result<Vec<u8>> {
-        if data.len() < 12 {
-            return Err(Error::Guard("invalid data length".into()));
-        }
+        let (plaintext, stamp) = self.decrypt(HEADER, &[], data)?;
 
-        let (nonce, ciphertext) = data.split_at(12);
-        let cipher = self.create_cipher();
-        let nonce = Nonce::from_slice(nonce);
-
-        cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|e| Error::Guard(e.to_string()))
+        // Reject duplicate or stale frames only once the header is known to be authentic
+        self.replay.accept(stamp)?;
+        Ok(plaintext)
     }
-} 
\ No newline at end of file
+}
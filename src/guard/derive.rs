@@ -0,0 +1,107 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use hkdf::Hkdf;
+use rand::{RngCore, thread_rng};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::core::error::{Error, Result};
+
+/// Length of every derived key
+pub const KEY: usize = 32;
+
+/// Length of generated salts
+pub const SALT: usize = 16;
+
+/// HKDF info label for the encryption subkey
+const CRYPT: &[u8] = b"link/crypt/v1";
+
+/// HKDF info label for the MAC subkey
+const AUTH: &[u8] = b"link/auth/v1";
+
+/// Separate subkeys derived from one master secret
+pub struct Subkeys {
+    /// Key for `Crypt::raw`
+    pub crypt: [u8; KEY],
+    /// Key for `Auth::new`
+    pub auth: [u8; KEY],
+}
+
+/// Derives independent encryption and MAC subkeys from a master secret with HKDF-SHA256
+pub fn split(master: &[u8]) -> Subkeys {
+    Subkeys {
+        crypt: expand(master, CRYPT),
+        auth: expand(master, AUTH),
+    }
+}
+
+/// Derives the encryption subkey, as `Crypt::new` does
+pub fn crypt(master: &[u8]) -> [u8; KEY] {
+    expand(master, CRYPT)
+}
+
+/// Derives a 32-byte key bound to `info` from a master secret with HKDF-SHA256
+pub fn expand(master: &[u8], info: &[u8]) -> [u8; KEY] {
+    let mut key = [0u8; KEY];
+    Hkdf::<Sha256>::new(None, master)
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Password hashing function and its cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kdf {
+    /// Argon2id with memory in KiB, number of passes and lanes
+    Argon2id { memory: u32, passes: u32, lanes: u32 },
+    /// scrypt with log2 of the cost, block size and parallelism
+    Scrypt { log: u8, block: u32, lanes: u32 },
+}
+
+impl Default for Kdf {
+    fn default() -> Self {
+        Kdf::Argon2id {
+            memory: 19 * 1024,
+            passes: 2,
+            lanes: 1,
+        }
+    }
+}
+
+/// Everything needed to derive the same key from a passphrase again
+///
+/// The salt is not secret; store it next to the data it protects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Phrase {
+    pub kdf: Kdf,
+    pub salt: Vec<u8>,
+}
+
+impl Phrase {
+    /// Creates parameters with a fresh random salt
+    pub fn new(kdf: Kdf) -> Self {
+        let mut salt = vec![0u8; SALT];
+        thread_rng().fill_bytes(&mut salt);
+        Self { kdf, salt }
+    }
+
+    /// Stretches a passphrase into a 32-byte key
+    pub fn derive(&self, passphrase: &[u8]) -> Result<[u8; KEY]> {
+        let mut key = [0u8; KEY];
+        match self.kdf {
+            Kdf::Argon2id { memory, passes, lanes } => {
+                let params = Params::new(memory, passes, lanes, Some(KEY))
+                    .map_err(|e| Error::Guard(format!("invalid argon2 parameters: {}", e)))?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(passphrase, &self.salt, &mut key)
+                    .map_err(|e| Error::Guard(format!("argon2 failed: {}", e)))?;
+            }
+            Kdf::Scrypt { log, block, lanes } => {
+                let params = scrypt::Params::new(log, block, lanes, KEY)
+                    .map_err(|e| Error::Guard(format!("invalid scrypt parameters: {}", e)))?;
+                scrypt::scrypt(passphrase, &self.salt, &params, &mut key)
+                    .map_err(|e| Error::Guard(format!("scrypt failed: {}", e)))?;
+            }
+        }
+        Ok(key)
+    }
+}
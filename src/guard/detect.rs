@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::core::error::{Error, Result};
+use crate::core::state::State;
+use crate::store::File;
+
+/// Suspicious behaviour reported for a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// A frame failed `Auth` signature verification
+    Auth,
+    /// A frame failed `Crypt` decryption
+    Decrypt,
+    /// A frame or request exceeded the allowed size or rate
+    Oversize,
+    /// Too many connections in a short time, see `Tuning::burst`
+    Burst,
+    /// A frame did not follow the wire format
+    Protocol,
+}
+
+impl Event {
+    /// Classifies an error returned while handling a peer's traffic
+    pub fn from_error(error: &Error) -> Option<Event> {
+        match error {
+            Error::Auth(_) => Some(Event::Auth),
+            Error::Decrypt(_) => Some(Event::Decrypt),
+            Error::Capacity(_) => Some(Event::Oversize),
+            Error::Protocol(_) => Some(Event::Protocol),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Event::Auth => "ids_auth",
+            Event::Decrypt => "ids_decrypt",
+            Event::Oversize => "ids_oversize",
+            Event::Burst => "ids_burst",
+            Event::Protocol => "ids_protocol",
+        }
+    }
+}
+
+/// Weights of each event, and when and for how long a peer is banned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    pub auth: f64,
+    pub decrypt: f64,
+    pub oversize: f64,
+    pub burst: f64,
+    pub protocol: f64,
+    /// Score at which a peer is banned
+    pub threshold: f64,
+    /// Time for a score to halve
+    pub decay: Duration,
+    /// How long a ban lasts
+    pub cooldown: Duration,
+    /// Number of connections within the duration that counts as a burst
+    pub connections: (usize, Duration),
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            auth: 20.0,
+            decrypt: 20.0,
+            oversize: 10.0,
+            burst: 25.0,
+            protocol: 15.0,
+            threshold: 100.0,
+            decay: Duration::from_secs(60),
+            cooldown: Duration::from_secs(15 * 60),
+            connections: (20, Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Tuning {
+    fn weight(&self, event: Event) -> f64 {
+        match event {
+            Event::Auth => self.auth,
+            Event::Decrypt => self.decrypt,
+            Event::Oversize => self.oversize,
+            Event::Burst => self.burst,
+            Event::Protocol => self.protocol,
+        }
+    }
+}
+
+/// A banned peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ban {
+    /// End of the ban, in milliseconds since the Unix epoch
+    pub until: u64,
+    /// Score that triggered the ban
+    pub score: f64,
+    /// Event that pushed the score over the threshold
+    pub reason: String,
+}
+
+#[derive(Debug)]
+struct Suspect {
+    score: f64,
+    last: Instant,
+    connections: VecDeque<Instant>,
+}
+
+impl Suspect {
+    fn new() -> Self {
+        Self {
+            score: 0.0,
+            last: Instant::now(),
+            connections: VecDeque::new(),
+        }
+    }
+
+    /// Applies exponential decay up to `now`
+    fn decay(&mut self, now: Instant, half: Duration) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        if !half.is_zero() {
+            self.score *= 0.5f64.powf(elapsed / half.as_secs_f64());
+        }
+        self.last = now;
+    }
+}
+
+struct Inner {
+    suspects: HashMap<String, Suspect>,
+    bans: BTreeMap<String, Ban>,
+}
+
+/// Intrusion detection: scores peers from reported events and bans offenders for a cooldown
+///
+/// Peers are identified by a string, such as an IP address or an identity from `Auth`.
+/// When a store is attached, the ban list survives restarts.
+#[derive(Clone)]
+pub struct Detect {
+    tuning: Tuning,
+    inner: Arc<Mutex<Inner>>,
+    store: Option<(File, String)>,
+    state: State,
+}
+
+impl Detect {
+    pub fn new(tuning: Tuning) -> Self {
+        Self {
+            tuning,
+            inner: Arc::new(Mutex::new(Inner {
+                suspects: HashMap::new(),
+                bans: BTreeMap::new(),
+            })),
+            store: None,
+            state: State::new(),
+        }
+    }
+
+    /// Persists the ban list as JSON at `path` in `file`, loading any bans already stored there
+    pub async fn with_store(tuning: Tuning, file: File, path: &str) -> Result<Self> {
+        let mut detect = Self::new(tuning);
+        if file.exists(path).await? {
+            let bans: BTreeMap<String, Ban> = serde_json::from_slice(&file.read(path).await?)
+                .map_err(|e| Error::Store(format!("invalid ban list: {}", e)))?;
+            let now = now();
+            detect.inner.lock().await.bans = bans.into_iter().filter(|(_, ban)| ban.until > now).collect();
+        }
+        detect.store = Some((file, path.to_string()));
+        Ok(detect)
+    }
+
+    /// Returns the state holding the `ids_*` counters, for example to register with `Registry`
+    pub fn shared(&self) -> State {
+        self.state.clone()
+    }
+
+    /// Rejects a banned peer with `Error::Denied`, for use at accept time and before each request
+    pub async fn admit(&self, peer: &str) -> Result<()> {
+        match self.ban(peer).await {
+            Some(ban) => Err(Error::Denied(format!("peer {} is banned for {}", peer, ban.reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the active ban of a peer, if any
+    pub async fn ban(&self, peer: &str) -> Option<Ban> {
+        let now = now();
+        self.inner.lock().await.bans.get(peer).filter(|ban| ban.until > now).cloned()
+    }
+
+    /// Lists active bans
+    pub async fn bans(&self) -> BTreeMap<String, Ban> {
+        let now = now();
+        self.inner.lock().await.bans.iter()
+            .filter(|(_, ban)| ban.until > now)
+            .map(|(peer, ban)| (peer.clone(), ban.clone()))
+            .collect()
+    }
+
+    /// Returns the current score of a peer
+    pub async fn score(&self, peer: &str) -> f64 {
+        let mut inner = self.inner.lock().await;
+        match inner.suspects.get_mut(peer) {
+            Some(suspect) => {
+                suspect.decay(Instant::now(), self.tuning.decay);
+                suspect.score
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Records a new connection, reporting a `Burst` event when there are too many
+    pub async fn connect(&self, peer: &str) -> Result<Option<Ban>> {
+        let now = Instant::now();
+        let (limit, window) = self.tuning.connections;
+        let burst = {
+            let mut inner = self.inner.lock().await;
+            let suspect = inner.suspects.entry(peer.to_string()).or_insert_with(Suspect::new);
+            suspect.connections.push_back(now);
+            while suspect.connections.front().is_some_and(|at| now.duration_since(*at) > window) {
+                suspect.connections.pop_front();
+            }
+            suspect.connections.len() > limit
+        };
+        if burst {
+            return self.record(peer, Event::Burst).await;
+        }
+        Ok(None)
+    }
+
+    /// Records an error from a peer's traffic; errors that are not suspicious are ignored
+    pub async fn observe(&self, peer: &str, error: &Error) -> Result<Option<Ban>> {
+        match Event::from_error(error) {
+            Some(event) => self.record(peer, event).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Adds the weight of an event to a peer's score and bans it once over the threshold
+    pub async fn record(&self, peer: &str, event: Event) -> Result<Option<Ban>> {
+        self.state.record_count(event.name(), 1).await?;
+
+        let ban = {
+            let mut inner = self.inner.lock().await;
+            let suspect = inner.suspects.entry(peer.to_string()).or_insert_with(Suspect::new);
+            suspect.decay(Instant::now(), self.tuning.decay);
+            suspect.score += self.tuning.weight(event);
+            if suspect.score < self.tuning.threshold {
+                return Ok(None);
+            }
+
+            let ban = Ban {
+                until: now() + self.tuning.cooldown.as_millis() as u64,
+                score: suspect.score,
+                reason: format!("{:?}", event),
+            };
+            inner.suspects.remove(peer);
+            inner.bans.insert(peer.to_string(), ban.clone());
+            ban
+        };
+
+        tracing::warn!("banned peer {} (score {:.1}, {})", peer, ban.score, ban.reason);
+        self.state.record_count("ids_banned", 1).await?;
+        self.persist().await?;
+        Ok(Some(ban))
+    }
+
+    /// Lifts the ban of a peer
+    pub async fn unban(&self, peer: &str) -> Result<()> {
+        self.inner.lock().await.bans.remove(peer);
+        self.persist().await
+    }
+
+    /// Drops expired bans and forgets peers whose score has decayed below 1
+    pub async fn prune(&self) -> Result<usize> {
+        let removed = {
+            let mut inner = self.inner.lock().await;
+            let (now, instant) = (now(), Instant::now());
+            let window = self.tuning.connections.1;
+            let before = inner.bans.len();
+            inner.bans.retain(|_, ban| ban.until > now);
+            inner.suspects.retain(|_, suspect| {
+                suspect.decay(instant, self.tuning.decay);
+                while suspect.connections.front().is_some_and(|at| instant.duration_since(*at) > window) {
+                    suspect.connections.pop_front();
+                }
+                suspect.score >= 1.0 || !suspect.connections.is_empty()
+            });
+            before - inner.bans.len()
+        };
+        if removed > 0 {
+            self.persist().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn persist(&self) -> Result<()> {
+        let Some((file, path)) = &self.store else {
+            return Ok(());
+        };
+        // Held across the write so an older snapshot never lands after a newer one
+        let inner = self.inner.lock().await;
+        let json = serde_json::to_vec(&inner.bans).map_err(|e| Error::Store(e.to_string()))?;
+        file.write(path, &json).await
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
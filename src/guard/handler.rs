@@ -20,6 +20,6 @@ impl Handler for Crypt {
 #[async_trait]
 impl Handler for Check {
     async fn handle(&self, data: &[u8]) -> Result<Vec<u8>> {
-        Ok(data.to_vec())
+        self.expose(data).await
     }
 } 
\ No newline at end of file
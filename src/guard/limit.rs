@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use tokio::time::sleep;
+
+use crate::core::error::{Error, Result};
+use crate::core::link::Handler;
+use crate::core::state::State;
+
+/// Sustained rate and burst size of a token bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    /// Tokens added per second
+    pub rate: f64,
+    /// Maximum number of tokens held, and the size of the largest burst
+    pub burst: f64,
+}
+
+impl Budget {
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Self { rate, burst }
+    }
+}
+
+/// Limits applied to one peer or to all peers together; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Quota {
+    /// Frames per second
+    pub frames: Option<Budget>,
+    /// Bytes per second
+    pub bytes: Option<Budget>,
+    /// Accepted connections per second
+    pub connections: Option<Budget>,
+}
+
+/// What happens to a frame that exceeds its quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Wait until enough tokens are available
+    Delay,
+    /// Reject the frame with `Error::Capacity`
+    Drop,
+    /// Reject the frame with `Error::Denied` and refuse the peer at accept time for `cooldown`
+    Disconnect,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    budget: Budget,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            tokens: budget.burst,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.budget.rate).min(self.budget.burst);
+        self.last = now;
+    }
+
+    /// Time until `cost` tokens are available
+    fn wait(&self, cost: f64) -> Duration {
+        if self.tokens >= cost {
+            return Duration::ZERO;
+        }
+        if self.budget.rate <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64((cost - self.tokens) / self.budget.rate)
+    }
+}
+
+/// Buckets for one quota
+#[derive(Debug, Default)]
+struct Meter {
+    frames: Option<Bucket>,
+    bytes: Option<Bucket>,
+    connections: Option<Bucket>,
+    blocked: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Meter {
+    fn new(quota: &Quota) -> Self {
+        Self {
+            frames: quota.frames.map(Bucket::new),
+            bytes: quota.bytes.map(Bucket::new),
+            connections: quota.connections.map(Bucket::new),
+            blocked: None,
+            last: None,
+        }
+    }
+
+    fn buckets(&mut self) -> [(Option<&mut Bucket>, Kind); 3] {
+        [
+            (self.frames.as_mut(), Kind::Frame),
+            (self.bytes.as_mut(), Kind::Byte),
+            (self.connections.as_mut(), Kind::Connection),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Frame,
+    Byte,
+    Connection,
+}
+
+/// Token-bucket rate limiter shared by all sockets of a node
+pub struct Limit {
+    peer: Quota,
+    action: Action,
+    cooldown: Duration,
+    idle: Duration,
+    global: Mutex<Meter>,
+    peers: Mutex<HashMap<String, Meter>>,
+    /// When idle peers were last forgotten
+    swept: Mutex<Instant>,
+    state: State,
+}
+
+impl Limit {
+    /// Creates a limiter with a quota per peer and one for all peers together
+    pub fn new(peer: Quota, global: Quota, action: Action) -> Self {
+        Self {
+            peer,
+            action,
+            cooldown: Duration::from_secs(60),
+            idle: Duration::from_secs(600),
+            global: Mutex::new(Meter::new(&global)),
+            peers: Mutex::new(HashMap::new()),
+            swept: Mutex::new(Instant::now()),
+            state: State::new(),
+        }
+    }
+
+    /// Sets how long a disconnected peer is refused at accept time
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = cooldown;
+    }
+
+    /// Sets how long a peer may stay idle before it is forgotten, checked at most once per `idle`
+    pub fn set_idle(&mut self, idle: Duration) {
+        self.idle = idle;
+    }
+
+    /// Returns the state holding the `limit_*` counters, for example to register with `Registry`
+    pub fn shared(&self) -> State {
+        self.state.clone()
+    }
+
+    /// Returns a pipeline stage that charges every frame to `peer`
+    pub fn throttle(self: &Arc<Self>, peer: &str) -> Throttle {
+        Throttle {
+            limit: self.clone(),
+            peer: peer.to_string(),
+        }
+    }
+
+    /// Charges one frame of `len` bytes to `peer`, applying the configured action when over quota
+    pub async fn frame(&self, peer: &str, len: usize) -> Result<()> {
+        self.charge(peer, &[(Kind::Frame, 1.0), (Kind::Byte, len as f64)]).await
+    }
+
+    /// Decides whether a new connection from `peer` is accepted
+    ///
+    /// Connections are never delayed: they are refused with `Error::Capacity` when over quota,
+    /// or with `Error::Denied` while the peer is cooling down after a disconnect.
+    pub async fn admit(&self, peer: &str) -> Result<()> {
+        let now = Instant::now();
+        let blocked = self.peers.lock().unwrap_or_else(|e| e.into_inner())
+            .get(peer)
+            .and_then(|meter| meter.blocked)
+            .is_some_and(|until| until > now);
+        if blocked {
+            self.state.record_count("limit_refused", 1).await?;
+            return Err(Error::Denied(format!("peer {} is cooling down", peer)));
+        }
+        match self.take(peer, &[(Kind::Connection, 1.0)], false) {
+            Ok(()) => self.state.record_count("limit_admitted", 1).await,
+            Err(_) => {
+                self.state.record_count("limit_refused", 1).await?;
+                Err(Error::Capacity(format!("peer {} exceeded its connection rate", peer)))
+            }
+        }
+    }
+
+    /// Forgets peers that have been idle for longer than `idle`
+    pub fn prune(&self, idle: Duration) -> usize {
+        let mut peers = self.peers.lock().unwrap_or_else(|e| e.into_inner());
+        Self::sweep(&mut peers, Instant::now(), idle)
+    }
+
+    fn sweep(peers: &mut HashMap<String, Meter>, now: Instant, idle: Duration) -> usize {
+        let before = peers.len();
+        peers.retain(|_, meter| {
+            meter.blocked.is_some_and(|until| until > now)
+                || meter.last.is_some_and(|last| now.duration_since(last) < idle)
+        });
+        before - peers.len()
+    }
+
+    async fn charge(&self, peer: &str, costs: &[(Kind, f64)]) -> Result<()> {
+        let wait = match self.take(peer, costs, self.action == Action::Delay) {
+            Ok(()) => return Ok(()),
+            Err(wait) => wait,
+        };
+        match self.action {
+            Action::Delay => {
+                self.state.record_count("limit_delayed", 1).await?;
+                sleep(wait).await;
+                Ok(())
+            }
+            Action::Drop => {
+                self.state.record_count("limit_dropped", 1).await?;
+                Err(Error::Capacity(format!("peer {} exceeded its rate limit", peer)))
+            }
+            Action::Disconnect => {
+                let until = Instant::now() + self.cooldown;
+                if let Some(meter) = self.peers.lock().unwrap_or_else(|e| e.into_inner()).get_mut(peer) {
+                    meter.blocked = Some(until);
+                }
+                self.state.record_count("limit_disconnected", 1).await?;
+                Err(Error::Denied(format!("peer {} disconnected for exceeding its rate limit", peer)))
+            }
+        }
+    }
+
+    /// Takes tokens from the peer and global buckets together
+    ///
+    /// When `debt` is set the tokens are taken even if not available, and the time
+    /// needed to pay them back is returned as the error.
+    fn take(&self, peer: &str, costs: &[(Kind, f64)], debt: bool) -> std::result::Result<(), Duration> {
+        let now = Instant::now();
+        let mut peers = self.peers.lock().unwrap_or_else(|e| e.into_inner());
+        {
+            // Peers that stopped sending would otherwise be kept forever
+            let mut swept = self.swept.lock().unwrap_or_else(|e| e.into_inner());
+            if now.duration_since(*swept) >= self.idle {
+                Self::sweep(&mut peers, now, self.idle);
+                *swept = now;
+            }
+        }
+        let mut global = self.global.lock().unwrap_or_else(|e| e.into_inner());
+        let meter = peers.entry(peer.to_string()).or_insert_with(|| Meter::new(&self.peer));
+        meter.last = Some(now);
+
+        let mut wait = Duration::ZERO;
+        for meter in [&mut *meter, &mut *global] {
+            for (bucket, kind) in meter.buckets() {
+                let (Some(bucket), Some((_, cost))) = (bucket, costs.iter().find(|(k, _)| *k == kind)) else {
+                    continue;
+                };
+                bucket.refill(now);
+                wait = wait.max(bucket.wait(*cost));
+            }
+        }
+
+        if wait > Duration::ZERO && !debt {
+            return Err(wait);
+        }
+        for meter in [&mut *meter, &mut *global] {
+            for (bucket, kind) in meter.buckets() {
+                if let (Some(bucket), Some((_, cost))) = (bucket, costs.iter().find(|(k, _)| *k == kind)) {
+                    bucket.tokens -= cost;
+                }
+            }
+        }
+        if wait > Duration::ZERO {
+            return Err(wait);
+        }
+        Ok(())
+    }
+}
+
+/// Pipeline stage charging each frame that passes through it to one peer
+pub struct Throttle {
+    limit: Arc<Limit>,
+    peer: String,
+}
+
+#[async_trait]
+impl Handler for Throttle {
+    async fn handle(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.limit.frame(&self.peer, data.len()).await?;
+        Ok(data.to_vec())
+    }
+}
@@ -1,8 +1,21 @@
+pub mod access;
 pub mod auth;
+pub mod cipher;
 pub mod crypt;
 pub mod check;
+pub mod derive;
+pub mod detect;
+pub mod limit;
+pub mod replay;
+pub mod ring;
 pub mod handler;
 
+pub use access::Policy;
 pub use auth::Auth;
+pub use cipher::Suite;
 pub use crypt::Crypt;
-pub use check::Check; 
\ No newline at end of file
+pub use check::Check;
+pub use detect::Detect;
+pub use limit::Limit;
+pub use replay::{Replay, Role};
+pub use ring::Ring; 
\ No newline at end of file
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::core::error::{Error, Result};
+
+/// Length of the replay header: session epoch, sequence number and timestamp in milliseconds, each a u64
+pub const HEADER: usize = 24;
+
+/// Top bit of the sequence number, set on frames sent by a `Role::Responder`
+const RESPONDER: u64 = 1 << 63;
+
+/// Number of sequence numbers tracked below the highest one seen
+const WIDTH: u64 = 64;
+
+/// Number of sender sessions tracked at once
+const SESSIONS: usize = 16;
+
+/// Side of a connection, bound into every header so a frame reflected back to its sender is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Initiator,
+    Responder,
+}
+
+/// Sliding window over the sequence numbers received in one sender session
+struct Window {
+    top: u64,
+    seen: u64,
+    /// Latest timestamp received in the session
+    last: u64,
+}
+
+/// Stamps outgoing frames and rejects duplicate or stale incoming frames
+///
+/// Each `Replay` picks a random epoch that prefixes its sequence numbers, so a peer
+/// that restarts or reconnects with a fresh guard starts a new window instead of
+/// having its frames rejected as old. Windows of earlier epochs are kept until their
+/// frames would fail the age check anyway, so they cannot be replayed meanwhile; when
+/// more than `SESSIONS` are active, the oldest is evicted and unknown epochs stamped no
+/// later than the evicted one are rejected. Without a role, frames from either side are accepted.
+pub struct Replay {
+    age: Duration,
+    epoch: u64,
+    role: Option<Role>,
+    next: AtomicU64,
+    windows: Mutex<HashMap<u64, Window>>,
+    /// Latest timestamp of an evicted session
+    floor: AtomicU64,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl Replay {
+    /// Frames whose timestamp differs from the local clock by more than `age` are rejected
+    pub fn new(age: Duration) -> Self {
+        Self {
+            age,
+            epoch: rand::random(),
+            role: None,
+            next: AtomicU64::new(1),
+            windows: Mutex::new(HashMap::new()),
+            floor: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns how far a frame timestamp may drift from the local clock
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+
+    /// Changes the accepted clock difference, keeping the session and its windows
+    pub fn set_age(&mut self, age: Duration) {
+        self.age = age;
+    }
+
+    /// Stamps frames with `role` and rejects frames stamped with the same one; both sides must set opposite roles
+    pub fn set_role(&mut self, role: Role) {
+        self.role = Some(role);
+    }
+
+    /// Builds the header for the next outgoing frame
+    pub fn stamp(&self) -> [u8; HEADER] {
+        let mut seq = self.next.fetch_add(1, Ordering::Relaxed);
+        if self.role == Some(Role::Responder) {
+            seq |= RESPONDER;
+        }
+        let mut header = [0u8; HEADER];
+        header[..8].copy_from_slice(&self.epoch.to_be_bytes());
+        header[8..16].copy_from_slice(&seq.to_be_bytes());
+        header[16..].copy_from_slice(&now().to_be_bytes());
+        header
+    }
+
+    /// Accepts a header that has already been authenticated, recording its sequence number
+    pub fn accept(&self, header: &[u8]) -> Result<()> {
+        if header.len() != HEADER {
+            return Err(Error::Protocol("invalid replay header".into()));
+        }
+        let field = |i: usize| u64::from_be_bytes(header[i * 8..(i + 1) * 8].try_into().unwrap_or_default());
+        let (epoch, seq, stamp) = (field(0), field(1) & !RESPONDER, field(2));
+        let role = if field(1) & RESPONDER != 0 { Role::Responder } else { Role::Initiator };
+        if self.role == Some(role) {
+            return Err(Error::Auth(format!("frame {} was reflected back to its sender", seq)));
+        }
+
+        let now = now();
+        let age = self.age.as_millis() as u64;
+        if now.abs_diff(stamp) > age {
+            return Err(Error::Auth(format!("frame {} is outside the {}ms replay window", seq, age)));
+        }
+
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if !windows.contains_key(&epoch) {
+            // An evicted session would otherwise start over with an empty window
+            let floor = self.floor.load(Ordering::Relaxed);
+            if stamp <= floor {
+                return Err(Error::Auth(format!("frame {} belongs to an unknown or evicted session", seq)));
+            }
+            // Sessions idle for longer than `age` can only send frames that fail the age check
+            windows.retain(|_, window| now.saturating_sub(window.last) <= age);
+            if windows.len() >= SESSIONS {
+                if let Some(oldest) = windows.iter().min_by_key(|(_, window)| window.last).map(|(epoch, _)| *epoch) {
+                    if let Some(evicted) = windows.remove(&oldest) {
+                        self.floor.store(floor.max(evicted.last), Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+        let window = windows.entry(epoch).or_insert(Window { top: 0, seen: 0, last: stamp });
+        window.last = window.last.max(stamp);
+
+        if seq > window.top {
+            let shift = seq - window.top;
+            window.seen = if shift >= WIDTH { 0 } else { window.seen << shift };
+            window.seen |= 1;
+            window.top = seq;
+            return Ok(());
+        }
+
+        let offset = window.top - seq;
+        if offset >= WIDTH {
+            return Err(Error::Auth(format!("frame {} is too old", seq)));
+        }
+        if window.seen & (1 << offset) != 0 {
+            return Err(Error::Auth(format!("frame {} was replayed", seq)));
+        }
+        window.seen |= 1 << offset;
+        Ok(())
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crate::core::error::{Error, Result};
+use crate::guard::derive::{self, KEY};
+
+/// Length of the key id carried in protected frames
+pub const ID: usize = 4;
+
+/// HKDF info label for the subkey `Crypt` uses
+const CRYPT: &[u8] = b"link/ring/crypt/v1";
+
+/// HKDF info label for the subkey `Auth` uses
+const AUTH: &[u8] = b"link/ring/auth/v1";
+
+/// A key together with the period in which it may be used
+#[derive(Clone)]
+pub struct Key {
+    pub id: u32,
+    pub secret: Arc<[u8]>,
+    /// `protect` starts using the key from this moment
+    pub active: SystemTime,
+    /// `expose` stops accepting the key from this moment
+    pub retire: Option<SystemTime>,
+    crypt: [u8; KEY],
+    auth: [u8; KEY],
+}
+
+impl Key {
+    /// Encryption subkey derived from the secret
+    pub fn crypt(&self) -> &[u8] {
+        &self.crypt
+    }
+
+    /// MAC subkey derived from the secret, independent of the encryption subkey
+    pub fn auth(&self) -> &[u8] {
+        &self.auth
+    }
+
+    fn retired(&self, now: SystemTime) -> bool {
+        self.retire.is_some_and(|retire| retire <= now)
+    }
+}
+
+/// Set of keys shared by `Auth` and `Crypt`, rotated without rebuilding the guards
+///
+/// `protect` uses the newest key that is already active; `expose` accepts any key
+/// that has not been retired, including keys scheduled for later activation, so
+/// peers whose clocks disagree slightly keep talking during a rotation. Ids are
+/// never reused, and each guard uses its own subkey of a secret.
+pub struct Ring {
+    keys: RwLock<BTreeMap<u32, Key>>,
+    /// Id of the last key inserted
+    last: AtomicU32,
+}
+
+impl Ring {
+    /// Creates a ring holding a single key with id 1, active immediately
+    pub fn new(secret: &[u8]) -> Self {
+        let ring = Self {
+            keys: RwLock::new(BTreeMap::new()),
+            last: AtomicU32::new(0),
+        };
+        ring.insert(secret, SystemTime::now());
+        ring
+    }
+
+    /// Adds a key that becomes active at `at` and returns its id
+    pub fn schedule(&self, secret: &[u8], at: SystemTime) -> u32 {
+        self.insert(secret, at)
+    }
+
+    /// Activates a new key now and retires the keys in use after `grace`
+    ///
+    /// Frames protected with the old keys keep being accepted during `grace`,
+    /// so links that are still draining them are not dropped.
+    pub fn rotate(&self, secret: &[u8], grace: Duration) -> u32 {
+        let now = SystemTime::now();
+        let id = self.insert(secret, now);
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        for key in keys.values_mut() {
+            if key.id != id && key.retire.is_none() && key.active <= now {
+                key.retire = Some(now + grace);
+            }
+        }
+        id
+    }
+
+    /// Retires a key at `at`
+    pub fn retire(&self, id: u32, at: SystemTime) -> Result<()> {
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        let key = keys.get_mut(&id).ok_or_else(|| Error::NotFound(format!("key {}", id)))?;
+        key.retire = Some(at);
+        Ok(())
+    }
+
+    /// Removes keys that have been retired and returns how many were removed
+    pub fn prune(&self) -> usize {
+        let now = SystemTime::now();
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        let before = keys.len();
+        keys.retain(|_, key| !key.retired(now));
+        before - keys.len()
+    }
+
+    /// Lists all keys, ordered by id
+    pub fn list(&self) -> Vec<Key> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner()).values().cloned().collect()
+    }
+
+    /// Returns the key `protect` should use now
+    pub fn current(&self) -> Result<Key> {
+        let now = SystemTime::now();
+        self.keys.read().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|key| key.active <= now && !key.retired(now))
+            .max_by_key(|key| (key.active, key.id))
+            .cloned()
+            .ok_or_else(|| Error::Guard("no active key".into()))
+    }
+
+    /// Returns the key with the given id if `expose` may still use it
+    pub fn get(&self, id: u32) -> Option<Key> {
+        let now = SystemTime::now();
+        self.keys.read().unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+            .filter(|key| !key.retired(now))
+            .cloned()
+    }
+
+    fn insert(&self, secret: &[u8], at: SystemTime) -> u32 {
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        let id = self.last.fetch_add(1, Ordering::Relaxed) + 1;
+        keys.insert(id, Key {
+            id,
+            secret: Arc::from(secret),
+            active: at,
+            retire: None,
+            crypt: derive::expand(secret, CRYPT),
+            auth: derive::expand(secret, AUTH),
+        });
+        id
+    }
+}
+
+/// Reads the key id at the start of a protected frame
+pub fn split(data: &[u8]) -> Option<(u32, &[u8])> {
+    if data.len() < ID {
+        return None;
+    }
+    let (id, rest) = data.split_at(ID);
+    Some((u32::from_be_bytes([id[0], id[1], id[2], id[3]]), rest))
+}
@@ -70,7 +70,7 @@ impl Group {
         let mut inner = self.inner.lock().await;
         if inner.sockets.len() >= inner.semaphore.available_permits() {
             println!("Group is full");
-            return Err(Error::Net("group is full".into()));
+            return Err(Error::Capacity("group is full".into()));
         }
         inner.sockets.push(socket);
         println!("Socket added successfully");
@@ -114,11 +114,11 @@ impl Group {
                 }
                 Ok(Err(_)) => {
                     println!("Failed to acquire permit");
-                    return Err(Error::Net("failed to acquire semaphore".into()));
+                    return Err(Error::Closed("group is closed".into()));
                 }
                 Err(_) => {
                     println!("Permit acquisition timed out");
-                    return Err(Error::Net("permit acquisition timeout".into()));
+                    return Err(Error::Timeout("permit acquisition timeout".into()));
                 }
             }
         };
@@ -133,7 +133,7 @@ impl Group {
             } else {
                 println!("No available sockets");
                 drop(permit);
-                return Err(Error::Net("no available sockets".into()));
+                return Err(Error::Capacity("no available sockets".into()));
             }
         };
 
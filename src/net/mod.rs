@@ -1,7 +1,14 @@
 pub mod socket;
 pub mod group;
 pub mod route;
+pub mod retry;
+pub mod tls;
+pub mod transfer;
+pub mod negotiate;
 
 pub use socket::Socket;
 pub use group::Group;
-pub use route::Route; 
\ No newline at end of file
+pub use route::Route;
+pub use retry::Retry;
+pub use transfer::Transfer;
+pub use negotiate::Negotiate;
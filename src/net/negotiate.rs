@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+
+use crate::core::error::{Error, Result};
+use crate::core::link::{Guardable, Movable};
+use crate::guard::{Crypt, Role, Suite};
+use crate::net::Socket;
+use crate::net::retry::Handshake;
+
+/// Vai trò bên đề nghị trong khung xác nhận
+const OFFER: u8 = b'o';
+/// Vai trò bên trả lời trong khung xác nhận
+const ANSWER: u8 = b'a';
+
+/// Đề nghị các bộ mã hóa của `crypt` theo thứ tự ưu tiên và dùng bộ mà bên kia chọn
+///
+/// Sau khi chọn, hai bên trao đổi khung xác nhận được mã hóa bằng bộ đã chọn,
+/// chứa toàn bộ danh sách đề nghị và lựa chọn, nên kẻ tấn công ở giữa
+/// không thể ép dùng bộ yếu hơn mà không bị phát hiện.
+///
+/// # Arguments
+/// * `socket` - Socket vừa được kết nối
+/// * `crypt` - Bộ mã hóa dùng chung, không bị thay đổi
+///
+/// # Returns
+/// * `Result<Crypt>` - Bộ mã hóa riêng của kết nối, chỉ dùng bộ đã thỏa thuận
+pub async fn offer(socket: &mut Socket, crypt: &Crypt) -> Result<Crypt> {
+    let ids: Vec<u8> = crypt.suites().iter().map(|suite| suite.id()).collect();
+    socket.send(&ids).await?;
+
+    let mut buf = [0u8; 1];
+    if socket.receive(&mut buf).await? != 1 {
+        return Err(Error::Protocol("invalid cipher suite answer".into()));
+    }
+    let suite = match Suite::from_id(buf[0]) {
+        Some(suite) if ids.contains(&buf[0]) => suite,
+        _ if buf[0] == 0 => return Err(Error::Guard("peer shares no cipher suite".into())),
+        _ => return Err(Error::Protocol(format!("peer picked unoffered cipher suite {}", buf[0]))),
+    };
+    let session = crypt.session(suite, Role::Initiator)?;
+
+    let transcript = [ids.as_slice(), &[suite.id()]].concat();
+    confirm(socket, &session, OFFER, &transcript).await?;
+    verify(socket, &session, ANSWER, &transcript).await?;
+    Ok(session)
+}
+
+/// Chọn bộ đầu tiên bên kia đề nghị mà `crypt` cũng cho phép
+///
+/// Trả lời 0 và thất bại khi không có bộ chung, để không bên nào lặng lẽ hạ cấp.
+///
+/// # Arguments
+/// * `socket` - Socket vừa được chấp nhận
+/// * `crypt` - Bộ mã hóa dùng chung, không bị thay đổi
+///
+/// # Returns
+/// * `Result<Crypt>` - Bộ mã hóa riêng của kết nối, chỉ dùng bộ đã thỏa thuận
+pub async fn answer(socket: &mut Socket, crypt: &Crypt) -> Result<Crypt> {
+    let mut buf = [0u8; 16];
+    let n = socket.receive(&mut buf).await?;
+    let allowed = crypt.suites();
+    let chosen = buf[..n]
+        .iter()
+        .filter_map(|id| Suite::from_id(*id))
+        .find(|suite| allowed.contains(suite));
+
+    socket.send(&[chosen.map_or(0, Suite::id)]).await?;
+    let suite = chosen.ok_or_else(|| Error::Guard("peer shares no cipher suite".into()))?;
+    let session = crypt.session(suite, Role::Responder)?;
+
+    let transcript = [&buf[..n], &[suite.id()]].concat();
+    verify(socket, &session, OFFER, &transcript).await?;
+    confirm(socket, &session, ANSWER, &transcript).await?;
+    Ok(session)
+}
+
+/// Gửi khung xác nhận gồm vai trò và bản ghi thỏa thuận
+async fn confirm(socket: &mut Socket, crypt: &Crypt, role: u8, transcript: &[u8]) -> Result<()> {
+    let frame = crypt.protect(&[&[role], transcript].concat()).await?;
+    socket.send(&frame).await?;
+    Ok(())
+}
+
+/// Nhận khung xác nhận của bên kia và so với bản ghi thỏa thuận cục bộ
+async fn verify(socket: &mut Socket, crypt: &Crypt, role: u8, transcript: &[u8]) -> Result<()> {
+    let mut buf = [0u8; 256];
+    let n = socket.receive(&mut buf).await?;
+    let expected = [&[role], transcript].concat();
+    if crypt.expose(&buf[..n]).await? != expected {
+        return Err(Error::Auth("cipher suite negotiation was tampered with".into()));
+    }
+    Ok(())
+}
+
+/// Bước bắt tay thỏa thuận bộ mã hóa của một `Crypt` dùng chung sau mỗi lần kết nối
+///
+/// Các bản sao dùng chung bộ mã hóa của kết nối gần nhất.
+#[derive(Clone)]
+pub struct Negotiate {
+    crypt: Arc<Crypt>,
+    session: Arc<Mutex<Option<Arc<Crypt>>>>,
+}
+
+impl Negotiate {
+    /// Tạo bước bắt tay đề nghị các bộ mà `crypt` cho phép
+    ///
+    /// # Arguments
+    /// * `crypt` - Bộ mã hóa dùng chung, không bị thay đổi
+    ///
+    /// # Returns
+    /// * `Self` - Bước bắt tay mới
+    pub fn new(crypt: Arc<Crypt>) -> Self {
+        Self {
+            crypt,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Lấy bộ mã hóa riêng của kết nối gần nhất
+    ///
+    /// # Returns
+    /// * `Option<Arc<Crypt>>` - Bộ mã hóa đã thỏa thuận, nếu đã bắt tay thành công
+    pub fn session(&self) -> Option<Arc<Crypt>> {
+        self.session.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[async_trait]
+impl Handshake for Negotiate {
+    async fn handshake(&self, socket: &mut Socket) -> Result<()> {
+        let session = offer(socket, &self.crypt).await?;
+        *self.session.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(session));
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use rand::Rng;
+use tokio::time::sleep;
+
+use crate::core::error::{Error, Result};
+use crate::core::State;
+use crate::core::link::{Linkable, Movable};
+use crate::core::state::Mode;
+use crate::net::{Route, Socket};
+
+/// Cấu hình thời gian chờ lũy thừa giữa các lần quay số lại
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    /// Thời gian chờ của lần thử đầu tiên
+    pub base: Duration,
+    /// Thời gian chờ tối đa giữa hai lần thử
+    pub limit: Duration,
+    /// Số lần thử tối đa cho mỗi lần mất kết nối, 0 là không giới hạn
+    pub tries: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(100),
+            limit: Duration::from_secs(30),
+            tries: 0,
+        }
+    }
+}
+
+impl Backoff {
+    /// Tính thời gian chờ trước lần thử tiếp theo
+    ///
+    /// Thời gian chờ tăng gấp đôi sau mỗi lần thử, bị chặn bởi `limit`,
+    /// và được lấy ngẫu nhiên trong nửa trên để các nút không quay số cùng lúc.
+    ///
+    /// # Arguments
+    /// * `attempt` - Số lần thử đã thất bại, bắt đầu từ 0
+    ///
+    /// # Returns
+    /// * `Duration` - Thời gian chờ
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let grown = self.base.saturating_mul(2u32.saturating_pow(attempt.min(31)));
+        let cap = grown.min(self.limit);
+        let half = cap / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=cap - half)
+    }
+}
+
+/// Trait cho bước bắt tay chạy lại sau mỗi lần kết nối
+#[async_trait]
+pub trait Handshake: Send + Sync {
+    /// Thực hiện bắt tay trên socket vừa kết nối, trước khi gắn các bộ xử lý
+    ///
+    /// # Arguments
+    /// * `socket` - Socket vừa được kết nối
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả bắt tay
+    async fn handshake(&self, socket: &mut Socket) -> Result<()>;
+}
+
+/// Hàm gắn chuỗi bộ xử lý vào mỗi socket mới
+type Chain = Arc<dyn Fn(&mut Socket) + Send + Sync>;
+
+/// Liên kết tự động kết nối lại tới một mục định tuyến
+pub struct Retry {
+    /// Bảng định tuyến dùng để quay số
+    route: Arc<Route>,
+    /// Tên mục định tuyến cần kết nối
+    name: String,
+    /// Cấu hình thời gian chờ
+    backoff: Backoff,
+    /// Chuỗi bộ xử lý được gắn lại sau mỗi lần kết nối
+    chain: Option<Chain>,
+    /// Bước bắt tay được chạy lại sau mỗi lần kết nối
+    handshake: Option<Arc<dyn Handshake>>,
+    /// Socket hiện tại, `None` khi đang mất kết nối
+    socket: Option<Socket>,
+    /// Trạng thái của liên kết
+    state: Arc<State>,
+    /// Các khung đang chờ gửi trong lúc mất kết nối
+    pending: VecDeque<Vec<u8>>,
+    /// Số khung tối đa được giữ lại, 0 là không giữ
+    buffer: usize,
+    /// Số lần thử thất bại liên tiếp
+    attempt: u32,
+    /// Thời điểm sớm nhất được phép quay số lại
+    next: Instant,
+}
+
+impl Retry {
+    /// Tạo một liên kết tự kết nối lại
+    ///
+    /// # Arguments
+    /// * `route` - Bảng định tuyến chứa điểm đến
+    /// * `name` - Tên mục định tuyến cần kết nối
+    ///
+    /// # Returns
+    /// * `Self` - Liên kết mới ở trạng thái `Mode::Init`
+    pub fn new(route: Arc<Route>, name: &str) -> Self {
+        Self {
+            route,
+            name: name.to_string(),
+            backoff: Backoff::default(),
+            chain: None,
+            handshake: None,
+            socket: None,
+            state: Arc::new(State::new()),
+            pending: VecDeque::new(),
+            buffer: 0,
+            attempt: 0,
+            next: Instant::now(),
+        }
+    }
+
+    /// Đặt cấu hình thời gian chờ
+    ///
+    /// # Arguments
+    /// * `backoff` - Cấu hình mới
+    pub fn set_backoff(&mut self, backoff: Backoff) {
+        self.backoff = backoff;
+    }
+
+    /// Đặt hàm gắn chuỗi bộ xử lý cho mỗi socket mới
+    ///
+    /// # Arguments
+    /// * `chain` - Hàm gắn các bộ xử lý
+    pub fn set_chain<F>(&mut self, chain: F)
+    where
+        F: Fn(&mut Socket) + Send + Sync + 'static,
+    {
+        self.chain = Some(Arc::new(chain));
+    }
+
+    /// Đặt bước bắt tay cho mỗi socket mới
+    ///
+    /// # Arguments
+    /// * `handshake` - Bước bắt tay
+    pub fn set_handshake<H: Handshake + 'static>(&mut self, handshake: H) {
+        self.handshake = Some(Arc::new(handshake));
+    }
+
+    /// Đặt số khung tối đa được giữ lại trong lúc mất kết nối
+    ///
+    /// Khi bằng 0, `send` chờ cho tới khi kết nối lại xong.
+    ///
+    /// # Arguments
+    /// * `buffer` - Số khung tối đa
+    pub fn set_buffer(&mut self, buffer: usize) {
+        self.buffer = buffer;
+    }
+
+    /// Lấy bản sao chia sẻ của trạng thái, ví dụ để đăng ký với `Registry`
+    ///
+    /// # Returns
+    /// * `State` - Trạng thái dùng chung với liên kết
+    pub fn shared(&self) -> State {
+        (*self.state).clone()
+    }
+
+    /// Số khung đang chờ gửi
+    ///
+    /// # Returns
+    /// * `usize` - Số khung trong bộ đệm
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Quay số, bắt tay và gắn chuỗi bộ xử lý cho một socket mới
+    async fn dial(&self) -> Result<Socket> {
+        let mut socket = self.route.connect(&self.name).await?;
+        if let Some(handshake) = &self.handshake {
+            handshake.handshake(&mut socket).await?;
+        }
+        if let Some(chain) = &self.chain {
+            chain(&mut socket);
+        }
+        socket.start().await?;
+        Ok(socket)
+    }
+
+    /// Đánh dấu mất kết nối và chuyển sang `Mode::Pause`
+    async fn lose(&mut self) -> Result<()> {
+        if let Some(mut socket) = self.socket.take() {
+            let _ = socket.stop().await;
+        }
+        self.attempt = 0;
+        self.next = Instant::now();
+        self.state.set_mode(Mode::Pause).await
+    }
+
+    /// Thử quay số một lần và gửi lại các khung đang chờ
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `true` nếu đã kết nối lại, lỗi nếu hết số lần thử
+    async fn redial(&mut self) -> Result<bool> {
+        match self.dial().await {
+            Ok(socket) => {
+                self.socket = Some(socket);
+                self.attempt = 0;
+                if !self.flush().await? {
+                    return Ok(false);
+                }
+                self.state.set_mode(Mode::Ready).await?;
+                Ok(true)
+            }
+            Err(e) => {
+                // Lỗi vĩnh viễn như bị từ chối xác thực sẽ không hết khi quay số lại
+                if !e.is_retryable() {
+                    self.state.set_mode(Mode::Close).await?;
+                    return Err(e);
+                }
+                self.attempt += 1;
+                if self.backoff.tries != 0 && self.attempt >= self.backoff.tries {
+                    self.state.set_mode(Mode::Close).await?;
+                    return Err(e);
+                }
+                tracing::debug!("redial {} failed (attempt {}): {}", self.name, self.attempt, e);
+                self.next = Instant::now() + self.backoff.delay(self.attempt - 1);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Quay số lại cho tới khi thành công hoặc hết số lần thử
+    async fn reconnect(&mut self) -> Result<()> {
+        loop {
+            sleep(self.next.saturating_duration_since(Instant::now())).await;
+            if self.redial().await? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Gửi các khung đang chờ theo thứ tự
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `false` nếu kết nối lại bị mất trong lúc gửi
+    async fn flush(&mut self) -> Result<bool> {
+        while let Some(frame) = self.pending.front() {
+            let Some(socket) = self.socket.as_mut() else {
+                return Ok(false);
+            };
+            match socket.send(frame).await {
+                Ok(_) => {
+                    self.pending.pop_front();
+                }
+                Err(e) if e.is_retryable() => {
+                    self.lose().await?;
+                    return Ok(false);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Từ chối thao tác khi liên kết đã đóng
+    async fn ensure_open(&self) -> Result<()> {
+        if self.state.mode().await? == Mode::Close {
+            return Err(Error::Closed("link is closed".into()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Linkable for Retry {
+    /// Kết nối lần đầu, thử lại theo cấu hình thời gian chờ
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả kết nối
+    async fn start(&mut self) -> Result<()> {
+        self.ensure_open().await?;
+        self.reconnect().await
+    }
+
+    /// Đóng liên kết và bỏ các khung đang chờ
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả đóng
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(mut socket) = self.socket.take() {
+            let _ = socket.stop().await;
+        }
+        self.pending.clear();
+        self.state.set_mode(Mode::Close).await
+    }
+
+    /// Lấy trạng thái hiện tại của liên kết
+    ///
+    /// # Returns
+    /// * `Result<Mode>` - `Mode::Pause` trong lúc đang kết nối lại
+    async fn state(&self) -> Result<Mode> {
+        self.state.mode().await
+    }
+}
+
+#[async_trait]
+impl Movable for Retry {
+    async fn send(&mut self, data: &[u8]) -> Result<usize> {
+        self.ensure_open().await?;
+
+        if let Some(socket) = self.socket.as_mut() {
+            match socket.send(data).await {
+                Ok(sent) => return Ok(sent),
+                Err(e) if e.is_retryable() => self.lose().await?,
+                Err(e) => return Err(e),
+            }
+        }
+
+        if self.buffer == 0 {
+            self.reconnect().await?;
+            return match self.socket.as_mut() {
+                Some(socket) => socket.send(data).await,
+                None => Err(Error::Closed("link is not connected".into())),
+            };
+        }
+
+        // Giữ khung lại và chỉ quay số khi đã tới lượt, không chặn người gọi
+        if self.pending.len() >= self.buffer {
+            return Err(Error::Capacity("reconnect buffer is full".into()));
+        }
+        self.pending.push_back(data.to_vec());
+        if self.socket.is_none() && Instant::now() >= self.next {
+            self.redial().await?;
+        }
+        Ok(data.len())
+    }
+
+    async fn receive(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.ensure_open().await?;
+
+        loop {
+            if self.socket.is_none() {
+                self.reconnect().await?;
+            }
+            let Some(socket) = self.socket.as_mut() else {
+                continue;
+            };
+            match socket.receive(buf).await {
+                Ok(received) => return Ok(received),
+                Err(e) if e.is_retryable() => self.lose().await?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
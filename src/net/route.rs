@@ -2,15 +2,17 @@ use std::sync::Arc;
 use std::collections::HashMap;
 use tokio::sync::RwLock;
 use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
 
 use crate::core::error::{Error, Result};
 use crate::core::State;
 use crate::core::link::{Linkable, Settings};
 use crate::core::state::Mode;
+use crate::guard::access::{Direction, Policy, Request};
 use crate::net::Socket;
 
 /// Mục trong bảng định tuyến
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Entry {
     /// Địa chỉ của điểm đến
     pub addr: String,
@@ -18,7 +20,8 @@ pub struct Entry {
     pub weight: u32,
 }
 
-/// Triển khai định tuyến mạng
+/// Triển khai định tuyến mạng, các bản sao dùng chung một bảng định tuyến
+#[derive(Clone)]
 pub struct Route {
     /// Bảng định tuyến lưu trữ các mục
     table: Arc<RwLock<HashMap<String, Entry>>>,
@@ -26,6 +29,8 @@ pub struct Route {
     settings: Arc<Settings>,
     /// Trạng thái của định tuyến
     state: Arc<State>,
+    /// Chính sách truy cập, `None` là cho phép tất cả
+    policy: Option<Arc<Policy>>,
 }
 
 impl Route {
@@ -41,9 +46,18 @@ impl Route {
             table: Arc::new(RwLock::new(HashMap::new())),
             settings: Arc::new(settings),
             state: Arc::new(State::new()),
+            policy: None,
         }
     }
 
+    /// Đặt chính sách truy cập được kiểm tra trước mỗi lần kết nối
+    ///
+    /// # Arguments
+    /// * `policy` - Chính sách truy cập
+    pub fn set_policy(&mut self, policy: Arc<Policy>) {
+        self.policy = Some(policy);
+    }
+
     /// Thêm một mục vào bảng định tuyến
     ///
     /// # Arguments
@@ -82,7 +96,7 @@ impl Route {
         let table = self.table.read().await;
         table.get(name)
             .cloned()
-            .ok_or_else(|| Error::Net("route not found".into()))
+            .ok_or_else(|| Error::NotFound(format!("route {}", name)))
     }
 
     /// Liệt kê tất cả các mục trong bảng định tuyến
@@ -96,7 +110,20 @@ impl Route {
             .collect())
     }
 
-    /// Kết nối tới một điểm đến thông qua định tuyến
+    /// Thay toàn bộ bảng định tuyến, ví dụ khi khôi phục từ bản sao lưu
+    ///
+    /// # Arguments
+    /// * `entries` - Các mục định tuyến mới
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả thay bảng định tuyến
+    pub async fn load(&self, entries: Vec<(String, Entry)>) -> Result<()> {
+        let mut table = self.table.write().await;
+        *table = entries.into_iter().collect();
+        Ok(())
+    }
+
+    /// Kết nối tới một điểm đến thông qua định tuyến, dưới danh tính `Settings::name`
     ///
     /// # Arguments
     /// * `name` - Tên của mục định tuyến cần kết nối
@@ -104,7 +131,29 @@ impl Route {
     /// # Returns
     /// * `Result<Socket>` - Socket đã được kết nối
     pub async fn connect(&self, name: &str) -> Result<Socket> {
+        self.open(&self.settings.name, name, Direction::Outbound).await
+    }
+
+    /// Kết nối tới một điểm đến thay cho một nút khác, ví dụ khi chuyển tiếp hoặc mở đường hầm
+    ///
+    /// # Arguments
+    /// * `peer` - Danh tính của nút yêu cầu
+    /// * `name` - Tên của mục định tuyến cần kết nối
+    /// * `direction` - Chiều của kết nối so với nút hiện tại
+    ///
+    /// # Returns
+    /// * `Result<Socket>` - Socket đã được kết nối hoặc lỗi `Denied` nếu chính sách không cho phép
+    pub async fn open(&self, peer: &str, name: &str, direction: Direction) -> Result<Socket> {
         let entry = self.get(name).await?;
+        if let Some(policy) = &self.policy {
+            let port = entry.addr.rsplit(':').next().and_then(|port| port.parse().ok()).unwrap_or(0);
+            policy.check(Request {
+                peer: peer.to_string(),
+                route: name.to_string(),
+                port,
+                direction,
+            })?;
+        }
         Socket::connect(entry.addr, (*self.settings).clone()).await
     }
 }
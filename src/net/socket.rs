@@ -1,36 +1,348 @@
 use std::sync::Arc;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::{Duration, Instant};
 use tokio::net::{TcpStream, ToSocketAddrs};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::{sleep, sleep_until, timeout};
 use async_trait::async_trait;
 
 use crate::core::error::{Error, Result};
-use crate::core::link::{Linkable, Movable, Settings, Handler};
-use crate::core::state::Mode;
+use crate::core::link::{Linkable, Movable, Settings, Handler, Deadline};
+use crate::core::state::{Measure, Mode};
+use crate::core::State;
+
+/// Bit đánh dấu khung điều khiển trong tiền tố độ dài
+const CONTROL: u32 = 1 << 31;
+
+/// Loại khung điều khiển
+const PING: u8 = 1;
+const PONG: u8 = 2;
+
+/// Số ping chờ pong được ghi nhớ
+const PENDING: usize = 16;
+
+/// Cấu hình nhịp tim cho socket
+#[derive(Debug, Clone, Copy)]
+pub struct Beat {
+    /// Khoảng thời gian giữa hai lần gửi ping
+    pub interval: Duration,
+    /// Số ping liên tiếp không có pong trước khi đóng socket
+    pub miss: u32,
+}
+
+impl Default for Beat {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            miss: 3,
+        }
+    }
+}
+
+/// Luồng byte hai chiều mà socket truyền khung trên đó, ví dụ TCP hoặc TLS
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> Stream for T {}
+
+/// Kết quả chờ khung tiếp theo
+enum Wake {
+    Read(std::io::Result<usize>),
+    Idle,
+    Beat,
+}
 
 /// Network socket implementation
 pub struct Socket {
-    stream: TcpStream,
-    _settings: Arc<Settings>,
+    stream: Box<dyn Stream>,
+    /// Danh tính của bên kia, ví dụ từ chứng chỉ TLS của máy khách
+    peer: Option<String>,
+    settings: Arc<Settings>,
     handlers: Vec<Box<dyn Handler>>,
+    state: Arc<State>,
+    deadline: Deadline,
+    /// Cấu hình nhịp tim, `None` nếu tắt
+    beat: Option<Beat>,
+    /// Thời điểm gửi ping tiếp theo
+    next: Instant,
+    /// Số ping đã gửi nhưng chưa nhận pong
+    missed: u32,
+    /// Mốc thời gian để đánh số ping
+    epoch: Instant,
+    /// Số hiệu các ping đã gửi nhưng chưa nhận pong, cũ nhất trước
+    pending: VecDeque<u64>,
+    /// Tiền tố độ dài đang đọc dở
+    head: [u8; 4],
+    /// Số byte tiền tố đã đọc
+    filled: usize,
 }
 
 impl Socket {
+    /// Kết nối tới một địa chỉ trong thời hạn `Deadline::connect`
+    ///
+    /// # Arguments
+    /// * `addr` - Địa chỉ cần kết nối
+    /// * `settings` - Cài đặt cho socket
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Socket đã kết nối hoặc lỗi `Timeout` nếu quá hạn
     pub async fn connect<A: ToSocketAddrs>(addr: A, settings: Settings) -> Result<Self> {
-        let stream = TcpStream::connect(addr)
+        let deadline = settings.deadline();
+        let stream = timeout(deadline.connect, TcpStream::connect(addr))
             .await
-            .map_err(|e| Error::Net(e.to_string()))?;
+            .map_err(|_| Error::Timeout("connect deadline exceeded".into()))?
+            .map_err(|e| Error::io("connect", e))?;
+
+        Ok(Self::wrap(stream, settings))
+    }
 
-        Ok(Self {
-            stream,
-            _settings: Arc::new(settings),
+    /// Bọc một luồng TCP đã được thiết lập, ví dụ từ `TcpListener::accept`
+    ///
+    /// # Arguments
+    /// * `stream` - Luồng TCP đã kết nối
+    /// * `settings` - Cài đặt cho socket
+    ///
+    /// # Returns
+    /// * `Self` - Socket mới ở trạng thái `Mode::Init`
+    pub fn wrap(stream: TcpStream, settings: Settings) -> Self {
+        // Khung nhỏ như ping/pong cần được gửi ngay để đo thời gian khứ hồi
+        let _ = stream.set_nodelay(true);
+        Self::from_stream(stream, settings)
+    }
+
+    /// Bọc một luồng bất kỳ đã được thiết lập, ví dụ một luồng TLS
+    ///
+    /// # Arguments
+    /// * `stream` - Luồng đã kết nối
+    /// * `settings` - Cài đặt cho socket
+    ///
+    /// # Returns
+    /// * `Self` - Socket mới ở trạng thái `Mode::Init`
+    pub fn from_stream<S: Stream + 'static>(stream: S, settings: Settings) -> Self {
+        let deadline = settings.deadline();
+        Self {
+            stream: Box::new(stream),
+            peer: None,
+            settings: Arc::new(settings),
             handlers: Vec::new(),
-        })
+            state: Arc::new(State::new()),
+            deadline,
+            beat: None,
+            next: Instant::now(),
+            missed: 0,
+            epoch: Instant::now(),
+            pending: VecDeque::new(),
+            head: [0; 4],
+            filled: 0,
+        }
+    }
+
+    /// Danh tính của bên kia, nếu đã được xác thực
+    ///
+    /// # Returns
+    /// * `Option<&str>` - `None` nếu chưa biết danh tính
+    pub fn peer(&self) -> Option<&str> {
+        self.peer.as_deref()
+    }
+
+    /// Gán danh tính đã xác thực của bên kia
+    ///
+    /// # Arguments
+    /// * `peer` - Danh tính của bên kia
+    pub fn set_peer(&mut self, peer: &str) {
+        self.peer = Some(peer.to_string());
     }
 
     pub fn add_handler<H: Handler + 'static>(&mut self, handler: H) {
         self.handlers.push(Box::new(handler));
     }
 
+    /// Lấy các thời hạn đang áp dụng
+    ///
+    /// # Returns
+    /// * `Deadline` - Các thời hạn hiện tại
+    pub fn deadline(&self) -> Deadline {
+        self.deadline
+    }
+
+    /// Ghi đè các thời hạn được tính từ cấu hình
+    ///
+    /// # Arguments
+    /// * `deadline` - Các thời hạn mới
+    pub fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    /// Bật nhịp tim với cấu hình cho trước
+    ///
+    /// Ping được gửi và pong được trả lời trong lúc socket đang chờ nhận,
+    /// nên cả hai phía cần gọi `receive` để giữ liên kết sống.
+    ///
+    /// # Arguments
+    /// * `beat` - Cấu hình nhịp tim
+    pub fn set_beat(&mut self, beat: Beat) {
+        self.beat = Some(beat);
+        self.next = Instant::now() + beat.interval;
+        self.missed = 0;
+    }
+
+    /// Lấy bản sao chia sẻ của trạng thái, ví dụ để đăng ký với `Registry`
+    ///
+    /// # Returns
+    /// * `State` - Trạng thái dùng chung với socket
+    pub fn shared(&self) -> State {
+        (*self.state).clone()
+    }
+
+    /// Lấy các chỉ số của socket, bao gồm thời gian khứ hồi
+    ///
+    /// # Returns
+    /// * `Result<Measure>` - Các chỉ số hiện tại
+    pub async fn measure(&self) -> Result<Measure> {
+        self.state.measure().await
+    }
+
+    /// Gửi một ping; pong được xử lý trong lần `receive` tiếp theo
+    ///
+    /// # Returns
+    /// * `Result<()>` - Kết quả gửi ping
+    pub async fn ping(&mut self) -> Result<()> {
+        self.ensure_open().await?;
+        self.send_ping().await
+    }
+
+    /// Gửi ping và ghi nhớ số hiệu để chỉ chấp nhận pong tương ứng
+    async fn send_ping(&mut self) -> Result<()> {
+        let id = self.epoch.elapsed().as_micros() as u64;
+        if self.pending.len() >= PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(id);
+        self.control(PING, id).await
+    }
+
+    /// Gửi một khung điều khiển
+    async fn control(&mut self, kind: u8, id: u64) -> Result<()> {
+        let mut body = [0u8; 9];
+        body[0] = kind;
+        body[1..].copy_from_slice(&id.to_be_bytes());
+        self.write_frame(CONTROL | body.len() as u32, &body).await
+    }
+
+    /// Ghi tiền tố độ dài và dữ liệu trong thời hạn ghi
+    async fn write_frame(&mut self, header: u32, body: &[u8]) -> Result<()> {
+        // Ghi tiền tố và dữ liệu trong một lần để không bị Nagle chia nhỏ
+        let mut frame = Vec::with_capacity(4 + body.len());
+        frame.extend_from_slice(&header.to_be_bytes());
+        frame.extend_from_slice(body);
+        let stream = &mut self.stream;
+        within(&self.state, self.deadline.write, "write", async {
+            stream.write_all(&frame).await?;
+            stream.flush().await
+        }).await
+    }
+
+    /// Chờ tiền tố độ dài của khung tiếp theo, gửi ping khi tới lượt
+    ///
+    /// Tiền tố được đọc từng phần vào `head` để việc gửi ping xen giữa
+    /// không làm mất byte đã đọc.
+    async fn read_header(&mut self) -> Result<u32> {
+        let idle = sleep(self.deadline.idle);
+        tokio::pin!(idle);
+        loop {
+            let wake = {
+                let beat = async {
+                    match self.beat {
+                        Some(_) => sleep_until(self.next.into()).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    read = self.stream.read(&mut self.head[self.filled..]) => Wake::Read(read),
+                    _ = &mut idle => Wake::Idle,
+                    _ = beat => Wake::Beat,
+                }
+            };
+            match wake {
+                Wake::Read(read) => {
+                    let n = read.map_err(|e| Error::io("read", e))?;
+                    if n == 0 {
+                        return Err(Error::Closed("connection closed by peer".into()));
+                    }
+                    self.filled += n;
+                    if self.filled == self.head.len() {
+                        self.filled = 0;
+                        return Ok(u32::from_be_bytes(self.head));
+                    }
+                }
+                Wake::Idle => return Err(self.expire("idle").await),
+                Wake::Beat => {
+                    let Some(beat) = self.beat else { continue };
+                    if self.missed >= beat.miss {
+                        return Err(self.expire("heartbeat").await);
+                    }
+                    self.missed += 1;
+                    self.next = Instant::now() + beat.interval;
+                    self.send_ping().await?;
+                }
+            }
+        }
+    }
+
+    /// Xử lý một khung điều khiển đã nhận
+    async fn handle_control(&mut self, body: &[u8]) -> Result<()> {
+        if body.len() != 9 {
+            return Err(Error::Protocol("malformed control frame".into()));
+        }
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&body[1..]);
+        let id = u64::from_be_bytes(id);
+        match body[0] {
+            PING => self.control(PONG, id).await,
+            PONG => {
+                // Pong không khớp ping nào đã gửi sẽ cho thời gian khứ hồi sai
+                let Some(index) = self.pending.iter().position(|sent| *sent == id) else {
+                    return Ok(());
+                };
+                self.pending.drain(..=index);
+                self.missed = 0;
+                let now = self.epoch.elapsed();
+                let sent = Duration::from_micros(id);
+                if now >= sent {
+                    self.state.record_rtt(now - sent).await?;
+                }
+                Ok(())
+            }
+            kind => Err(Error::Protocol(format!("unknown control frame {}", kind))),
+        }
+    }
+
+    /// Đóng socket khi luồng dừng giữa một khung và trả về lỗi tương ứng
+    ///
+    /// Phần thân chưa đọc còn trong luồng, nên đọc tiếp sẽ hiểu nhầm dữ liệu thành độ dài.
+    async fn abort(&mut self, error: Error) -> Error {
+        if let Err(e) = self.state.record_fault(&error).await {
+            return e;
+        }
+        if let Err(e) = self.state.set_mode(Mode::Close).await {
+            return e;
+        }
+        let _ = self.stream.shutdown().await;
+        error
+    }
+
+    /// Đóng socket sau khi quá hạn và trả về lỗi tương ứng
+    async fn expire(&self, what: &str) -> Error {
+        let error = Error::Timeout(format!("{} deadline exceeded", what));
+        if let Err(e) = self.state.record_fault(&error).await {
+            return e;
+        }
+        if let Err(e) = self.state.set_mode(Mode::Close).await {
+            return e;
+        }
+        error
+    }
+
     async fn process_outgoing(&self, data: &[u8]) -> Result<Vec<u8>> {
         let mut processed = data.to_vec();
         for handler in &self.handlers {
@@ -46,57 +358,105 @@ impl Socket {
         }
         Ok(processed)
     }
+
+    /// Từ chối thao tác khi socket đã đóng
+    async fn ensure_open(&self) -> Result<()> {
+        if self.state.mode().await? == Mode::Close {
+            return Err(Error::Closed("socket is closed".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Chạy một thao tác I/O trong thời hạn cho trước.
+/// Khi quá hạn, luồng có thể đã bị đọc/ghi dở dang nên socket bị đóng.
+async fn within<T, F>(state: &State, limit: Duration, what: &str, op: F) -> Result<T>
+where
+    F: Future<Output = std::io::Result<T>>,
+{
+    match timeout(limit, op).await {
+        Ok(result) => result.map_err(|e| Error::io(what, e)),
+        Err(_) => {
+            let error = Error::Timeout(format!("{} deadline exceeded", what));
+            state.record_fault(&error).await?;
+            state.set_mode(Mode::Close).await?;
+            Err(error)
+        }
+    }
 }
 
 #[async_trait]
 impl Linkable for Socket {
     async fn start(&mut self) -> Result<()> {
-        Ok(())
+        self.state.set_mode(Mode::Ready).await
     }
 
     async fn stop(&mut self) -> Result<()> {
+        self.state.set_mode(Mode::Close).await?;
+        // Bên kia có thể đã đóng trước, lỗi shutdown không ảnh hưởng trạng thái
+        let _ = self.stream.shutdown().await;
         Ok(())
     }
 
     async fn state(&self) -> Result<Mode> {
-        Ok(Mode::Ready)
+        self.state.mode().await
     }
 }
 
 #[async_trait]
 impl Movable for Socket {
     async fn send(&mut self, data: &[u8]) -> Result<usize> {
+        self.ensure_open().await?;
+
         // Process data through handlers
         let processed = self.process_outgoing(data).await?;
-        
-        // Send data length first (4 bytes)
+
+        // Send length prefix (4 bytes) and processed data within the write deadline
         let len = processed.len() as u32;
-        self.stream.write_all(&len.to_be_bytes()).await?;
-        
-        // Send processed data
-        self.stream.write_all(&processed).await?;
-        self.stream.flush().await?;
-        
+        if processed.len() > self.settings.size || processed.len() >= CONTROL as usize {
+            return Err(Error::Capacity("frame too large".into()));
+        }
+        self.write_frame(len, &processed).await?;
+        self.state.record_send(processed.len()).await?;
+
         Ok(data.len())
     }
 
     async fn receive(&mut self, buf: &mut [u8]) -> Result<usize> {
-        // Read length prefix (4 bytes)
-        let mut len_bytes = [0u8; 4];
-        self.stream.read_exact(&mut len_bytes).await?;
-        let len = u32::from_be_bytes(len_bytes) as usize;
-        
-        // Read exact amount of data
-        let mut data = vec![0u8; len];
-        self.stream.read_exact(&mut data).await?;
-        
+        self.ensure_open().await?;
+
+        // Wait for the next data frame, answering control frames in between
+        let data = loop {
+            let header = self.read_header().await?;
+            let len = (header & !CONTROL) as usize;
+
+            // Kiểm tra độ dài do bên kia gửi trước khi cấp phát bộ đệm
+            if header & CONTROL != 0 && len != 9 {
+                return Err(self.abort(Error::Protocol("malformed control frame".into())).await);
+            }
+            if len > self.settings.size {
+                let error = Error::Capacity(format!("frame of {} bytes exceeds {}", len, self.settings.size));
+                return Err(self.abort(error).await);
+            }
+
+            // Read exact amount of data within the read deadline
+            let mut data = vec![0u8; len];
+            within(&self.state, self.deadline.read, "read", self.stream.read_exact(&mut data)).await?;
+
+            if header & CONTROL == 0 {
+                self.state.record_receive(data.len()).await?;
+                break data;
+            }
+            self.handle_control(&data).await?;
+        };
+
         // Process received data through handlers in reverse
         let processed = self.process_incoming(&data).await?;
-        
+
         // Copy processed data to output buffer
         let copy_len = processed.len().min(buf.len());
         buf[..copy_len].copy_from_slice(&processed[..copy_len]);
-        
+
         Ok(copy_len)
     }
-} 
\ No newline at end of file
+}
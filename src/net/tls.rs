@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use sha2::{Digest, Sha256};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::time::timeout;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::core::error::{Error, Result};
+use crate::core::link::Settings;
+use crate::net::Socket;
+use crate::store::file::hex;
+
+/// Chứng chỉ và khóa riêng mà một bên trình ra trong bắt tay TLS
+pub struct Identity {
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl Identity {
+    /// Đọc chuỗi chứng chỉ và khóa riêng từ dữ liệu PEM
+    ///
+    /// # Arguments
+    /// * `cert` - Chuỗi chứng chỉ PEM, chứng chỉ lá trước
+    /// * `key` - Khóa riêng PEM (PKCS#8, PKCS#1 hoặc SEC1)
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Danh tính hoặc lỗi `Guard` nếu PEM không hợp lệ
+    pub fn from_pem(cert: &[u8], key: &[u8]) -> Result<Self> {
+        let chain = certs(cert)?;
+        let key = rustls_pemfile::private_key(&mut &key[..])
+            .map_err(|e| Error::Guard(format!("invalid private key PEM: {}", e)))?
+            .ok_or_else(|| Error::Guard("no private key in PEM".into()))?;
+        Ok(Self { chain, key })
+    }
+
+    /// Đọc chuỗi chứng chỉ và khóa riêng từ hai tệp PEM
+    ///
+    /// # Arguments
+    /// * `cert` - Đường dẫn tệp chứng chỉ
+    /// * `key` - Đường dẫn tệp khóa riêng
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Danh tính hoặc lỗi khi đọc tệp
+    pub async fn load<P: AsRef<Path>>(cert: P, key: P) -> Result<Self> {
+        Self::from_pem(&read(cert.as_ref()).await?, &read(key.as_ref()).await?)
+    }
+
+    /// Dấu vân tay của chứng chỉ lá
+    ///
+    /// # Returns
+    /// * `String` - Dạng `sha256:<hex>`
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.chain[0])
+    }
+}
+
+/// Tập chứng chỉ gốc dùng để xác minh bên kia
+#[derive(Clone)]
+pub struct Trust {
+    roots: Arc<RootCertStore>,
+}
+
+impl Trust {
+    /// Đọc các chứng chỉ gốc từ dữ liệu PEM
+    ///
+    /// # Arguments
+    /// * `pem` - Một hoặc nhiều chứng chỉ CA dạng PEM
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Tập chứng chỉ gốc hoặc lỗi `Guard`
+    pub fn from_pem(pem: &[u8]) -> Result<Self> {
+        let mut roots = RootCertStore::empty();
+        for cert in certs(pem)? {
+            roots.add(cert).map_err(|e| Error::Guard(format!("invalid CA certificate: {}", e)))?;
+        }
+        Ok(Self { roots: Arc::new(roots) })
+    }
+
+    /// Đọc các chứng chỉ gốc từ tệp PEM
+    ///
+    /// # Arguments
+    /// * `path` - Đường dẫn tệp CA
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Tập chứng chỉ gốc hoặc lỗi khi đọc tệp
+    pub async fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_pem(&read(path.as_ref()).await?)
+    }
+}
+
+/// Phía máy chủ TLS, tùy chọn yêu cầu chứng chỉ máy khách (mTLS)
+pub struct Server {
+    acceptor: TlsAcceptor,
+    /// Dấu vân tay chứng chỉ máy khách tới danh tính peer
+    peers: HashMap<String, String>,
+}
+
+impl Server {
+    /// Tạo máy chủ TLS
+    ///
+    /// # Arguments
+    /// * `identity` - Chứng chỉ và khóa của máy chủ
+    /// * `clients` - CA của máy khách; nếu có, mọi máy khách phải trình chứng chỉ do CA này ký
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Máy chủ hoặc lỗi `Guard` nếu cấu hình không hợp lệ
+    pub fn new(identity: Identity, clients: Option<Trust>) -> Result<Self> {
+        let provider = provider();
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(invalid)?;
+        let builder = match clients {
+            Some(trust) => {
+                let verifier = WebPkiClientVerifier::builder_with_provider(trust.roots, provider)
+                    .build()
+                    .map_err(|e| Error::Guard(format!("invalid client verifier: {}", e)))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder.with_single_cert(identity.chain, identity.key).map_err(invalid)?;
+
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+            peers: HashMap::new(),
+        })
+    }
+
+    /// Gán danh tính peer cho một chứng chỉ máy khách
+    ///
+    /// Máy khách đã xác minh nhưng chưa được gán sẽ mang danh tính là dấu vân tay của chứng chỉ.
+    ///
+    /// # Arguments
+    /// * `cert` - Chứng chỉ lá PEM của máy khách
+    /// * `peer` - Danh tính peer, ví dụ tên dùng trong `Policy`
+    ///
+    /// # Returns
+    /// * `Result<()>` - Lỗi `Guard` nếu PEM không hợp lệ
+    pub fn map(&mut self, cert: &[u8], peer: &str) -> Result<()> {
+        let cert = certs(cert)?.remove(0);
+        self.peers.insert(fingerprint(&cert), peer.to_string());
+        Ok(())
+    }
+
+    /// Lắng nghe kết nối TLS tại một địa chỉ
+    ///
+    /// # Arguments
+    /// * `addr` - Địa chỉ lắng nghe
+    /// * `settings` - Cài đặt cho các socket được chấp nhận
+    ///
+    /// # Returns
+    /// * `Result<Listener>` - Bộ lắng nghe hoặc lỗi `Io`
+    pub async fn bind<A: ToSocketAddrs>(&self, addr: A, settings: Settings) -> Result<Listener> {
+        let listener = TcpListener::bind(addr).await.map_err(|e| Error::io("bind", e))?;
+        Ok(Listener {
+            listener,
+            acceptor: self.acceptor.clone(),
+            peers: Arc::new(self.peers.clone()),
+            settings,
+        })
+    }
+}
+
+/// Bộ lắng nghe kết nối TLS, việc bắt tay do `Incoming::finish` thực hiện
+pub struct Listener {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    peers: Arc<HashMap<String, String>>,
+    settings: Settings,
+}
+
+impl Listener {
+    /// Địa chỉ đang lắng nghe, hữu ích khi bind cổng 0
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Chấp nhận một kết nối TCP mà chưa bắt tay TLS
+    ///
+    /// Bắt tay có thể kéo dài tới `Deadline::connect`, nên được tách ra `Incoming::finish`
+    /// để vòng chấp nhận không bị một máy khách chậm chặn lại, ví dụ
+    /// `tokio::spawn(incoming.finish())`.
+    ///
+    /// # Returns
+    /// * `Result<Incoming>` - Kết nối đang chờ bắt tay hoặc lỗi `Io`
+    pub async fn accept(&self) -> Result<Incoming> {
+        let (stream, _) = self.listener.accept().await.map_err(|e| Error::io("accept", e))?;
+        let _ = stream.set_nodelay(true);
+        Ok(Incoming {
+            stream,
+            acceptor: self.acceptor.clone(),
+            peers: self.peers.clone(),
+            settings: self.settings.clone(),
+        })
+    }
+}
+
+/// Kết nối đã được chấp nhận nhưng chưa bắt tay TLS
+pub struct Incoming {
+    stream: TcpStream,
+    acceptor: TlsAcceptor,
+    peers: Arc<HashMap<String, String>>,
+    settings: Settings,
+}
+
+impl Incoming {
+    /// Địa chỉ của máy khách
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        Ok(self.stream.peer_addr()?)
+    }
+
+    /// Bắt tay TLS trong thời hạn `Deadline::connect`
+    ///
+    /// # Returns
+    /// * `Result<Socket>` - Socket với `peer()` là danh tính của máy khách khi dùng mTLS,
+    ///   lỗi `Auth` nếu chứng chỉ bị từ chối hoặc `Timeout` nếu quá hạn
+    pub async fn finish(self) -> Result<Socket> {
+        let deadline = self.settings.deadline();
+        let stream = timeout(deadline.connect, self.acceptor.accept(self.stream))
+            .await
+            .map_err(|_| Error::Timeout("TLS handshake deadline exceeded".into()))?
+            .map_err(handshake)?;
+
+        let peer = stream.get_ref().1
+            .peer_certificates()
+            .and_then(|chain| chain.first())
+            .map(fingerprint)
+            .map(|fp| self.peers.get(&fp).cloned().unwrap_or(fp));
+
+        let mut socket = Socket::from_stream(stream, self.settings);
+        if let Some(peer) = peer {
+            socket.set_peer(&peer);
+        }
+        Ok(socket)
+    }
+}
+
+/// Phía máy khách TLS, tùy chọn trình chứng chỉ của mình cho mTLS
+pub struct Client {
+    connector: TlsConnector,
+}
+
+impl Client {
+    /// Tạo máy khách TLS
+    ///
+    /// # Arguments
+    /// * `trust` - CA dùng để xác minh máy chủ
+    /// * `identity` - Chứng chỉ máy khách khi máy chủ yêu cầu mTLS
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Máy khách hoặc lỗi `Guard` nếu cấu hình không hợp lệ
+    pub fn new(trust: Trust, identity: Option<Identity>) -> Result<Self> {
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(invalid)?
+            .with_root_certificates(trust.roots);
+        let config = match identity {
+            Some(identity) => builder.with_client_auth_cert(identity.chain, identity.key).map_err(invalid)?,
+            None => builder.with_no_client_auth(),
+        };
+        Ok(Self {
+            connector: TlsConnector::from(Arc::new(config)),
+        })
+    }
+
+    /// Kết nối và bắt tay TLS trong thời hạn `Deadline::connect`
+    ///
+    /// # Arguments
+    /// * `addr` - Địa chỉ máy chủ
+    /// * `name` - Tên máy chủ phải có trong chứng chỉ của nó
+    /// * `settings` - Cài đặt cho socket
+    ///
+    /// # Returns
+    /// * `Result<Socket>` - Socket đã bắt tay, lỗi `Auth` nếu chứng chỉ máy chủ bị từ chối
+    ///   hoặc `Timeout` nếu quá hạn
+    pub async fn connect<A: ToSocketAddrs>(&self, addr: A, name: &str, settings: Settings) -> Result<Socket> {
+        let server = ServerName::try_from(name.to_string())
+            .map_err(|_| Error::Guard(format!("invalid server name {}", name)))?;
+        let deadline = settings.deadline();
+        let stream = timeout(deadline.connect, async {
+            let stream = TcpStream::connect(addr).await.map_err(|e| Error::io("connect", e))?;
+            let _ = stream.set_nodelay(true);
+            self.connector.connect(server, stream).await.map_err(handshake)
+        })
+        .await
+        .map_err(|_| Error::Timeout("TLS handshake deadline exceeded".into()))??;
+
+        let mut socket = Socket::from_stream(stream, settings);
+        socket.set_peer(name);
+        Ok(socket)
+    }
+}
+
+/// Dấu vân tay SHA-256 của một chứng chỉ DER
+///
+/// # Returns
+/// * `String` - Dạng `sha256:<hex>`
+pub fn fingerprint(cert: &CertificateDer<'_>) -> String {
+    format!("sha256:{}", hex(&Sha256::digest(cert.as_ref())))
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn certs(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut &pem[..])
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(|e| Error::Guard(format!("invalid certificate PEM: {}", e)))?;
+    if certs.is_empty() {
+        return Err(Error::Guard("no certificate in PEM".into()));
+    }
+    Ok(certs)
+}
+
+async fn read(path: &Path) -> Result<Vec<u8>> {
+    tokio::fs::read(path).await.map_err(|e| Error::io(path.display().to_string(), e))
+}
+
+fn invalid(e: rustls::Error) -> Error {
+    Error::Guard(format!("invalid TLS configuration: {}", e))
+}
+
+/// Chứng chỉ bị từ chối là lỗi xác thực, các lỗi khác là lỗi I/O
+fn handshake(e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::InvalidData {
+        Error::Auth(format!("TLS handshake failed: {}", e))
+    } else {
+        Error::io("TLS handshake", e)
+    }
+}
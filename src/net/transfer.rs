@@ -0,0 +1,391 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::error::{Error, Result};
+use crate::core::link::{Deadline, Movable, Storable};
+use crate::net::Socket;
+use crate::store::File;
+
+/// Kích thước khối mặc định, để khối cùng `HEADER` vừa `Settings::size` mặc định
+const CHUNK: usize = 32 * 1024;
+
+/// Kích thước khối lớn nhất được chấp nhận từ bên gửi
+const LIMIT: usize = 16 * 1024 * 1024;
+
+/// Bộ đệm cho các thông điệp điều khiển
+const CONTROL: usize = 1024 * 1024;
+
+/// Khối dữ liệu: `DATA | chỉ số (8) | SHA-256 (32) | dữ liệu`
+const DATA: u8 = 1;
+/// Luồng không còn khối nào
+const END: u8 = 2;
+const HEADER: usize = 1 + 8 + 32;
+
+/// Thời gian chờ mặc định để bên nhận băm lại toàn bộ tệp sau khối cuối
+const FINISH: Duration = Duration::from_secs(600);
+
+/// Số khối được ghi giữa hai lần lưu tiến độ
+const SAVE: usize = 16;
+
+/// Thư mục con chứa mọi tệp nhận được, để bên gửi không ghi đè tệp khác trong kho
+const DIR: &str = "transfer";
+
+/// Đuôi của tệp đang nhận và tệp tiến độ của nó
+const PART: &str = ".part";
+const STATE: &str = ".part.json";
+
+/// Thông điệp điều khiển, luôn đi trên socket đầu tiên
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// Bên gửi mô tả tệp
+    Offer { name: String, size: u64, chunk: usize, hash: String },
+    /// Bên nhận báo các khối đã xác minh, dưới dạng các khoảng `[đầu, cuối)`
+    Have { ranges: Vec<(u64, u64)> },
+    /// Bên nhận báo kết quả sau khi kiểm tra toàn bộ tệp
+    Done { ok: bool, reason: Option<String> },
+}
+
+/// Tiến độ của một lần nhận, lưu cạnh tệp đang nhận để tiếp tục sau khi mất kết nối
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Progress {
+    size: u64,
+    chunk: usize,
+    hash: String,
+    verified: BTreeSet<u64>,
+}
+
+/// Tiến độ báo cho hàm theo dõi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// Số byte đã xác minh, kể cả phần đã có từ lần trước
+    pub done: u64,
+    pub total: u64,
+}
+
+/// Kết quả của một lần truyền
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub name: String,
+    pub size: u64,
+    /// SHA-256 của toàn bộ tệp dạng hex
+    pub hash: String,
+    /// Số byte đã truyền trong lần này
+    pub moved: u64,
+    /// Số byte bỏ qua vì bên nhận đã có từ lần trước
+    pub resumed: u64,
+}
+
+/// Hàm theo dõi tiến độ
+type Observer = Arc<dyn Fn(Status) + Send + Sync>;
+
+/// Truyền tệp giữa hai peer qua một hoặc nhiều `Socket` song song
+///
+/// Tệp được chia khối, mỗi khối kèm SHA-256 riêng; bên nhận ghi khối vào
+/// `transfer/<tên>.part` và lưu các khối đã xác minh vào `transfer/<tên>.part.json`,
+/// nên lần truyền sau chỉ gửi các khối còn thiếu. Khi đủ khối, bên nhận kiểm tra
+/// SHA-256 của toàn bộ tệp rồi mới đổi tên thành `transfer/<tên>`.
+///
+/// Mỗi khung khối dài `chunk + 41` byte và phải vừa `Settings::size` của các socket.
+pub struct Transfer {
+    file: File,
+    chunk: usize,
+    finish: Duration,
+    observer: Option<Observer>,
+}
+
+impl Transfer {
+    /// Tạo bộ truyền đọc và ghi tệp trong `file`
+    ///
+    /// # Arguments
+    /// * `file` - Kho tệp nguồn hoặc đích
+    ///
+    /// # Returns
+    /// * `Self` - Bộ truyền với khối 32 KiB
+    pub fn new(file: File) -> Self {
+        Self {
+            file,
+            chunk: CHUNK,
+            finish: FINISH,
+            observer: None,
+        }
+    }
+
+    /// Đặt kích thước khối khi gửi
+    ///
+    /// # Arguments
+    /// * `chunk` - Số byte mỗi khối, từ 1 tới 16 MiB; cộng thêm 41 byte đầu khung
+    ///   vẫn phải vừa `Settings::size`
+    pub fn set_chunk(&mut self, chunk: usize) {
+        self.chunk = chunk.clamp(1, LIMIT);
+    }
+
+    /// Đặt thời gian chờ kết quả sau khối cuối, khi bên nhận băm lại toàn bộ tệp
+    ///
+    /// # Arguments
+    /// * `finish` - Thời gian chờ tối đa, mặc định 10 phút; không ngắn hơn thời hạn của socket
+    pub fn set_finish(&mut self, finish: Duration) {
+        self.finish = finish;
+    }
+
+    /// Đặt hàm được gọi mỗi khi một khối được gửi hoặc xác minh
+    ///
+    /// # Arguments
+    /// * `observer` - Hàm nhận tiến độ
+    pub fn on_progress<F>(&mut self, observer: F)
+    where
+        F: Fn(Status) + Send + Sync + 'static,
+    {
+        self.observer = Some(Arc::new(observer));
+    }
+
+    /// Gửi tệp `path` dưới tên `name`, chia khối trên tất cả các socket
+    ///
+    /// # Arguments
+    /// * `path` - Đường dẫn tệp trong kho
+    /// * `name` - Tên tệp ở bên nhận, không chứa dấu phân cách đường dẫn
+    /// * `sockets` - Các socket đã kết nối tới bên nhận, ít nhất một
+    ///
+    /// # Returns
+    /// * `Result<Report>` - Kết quả, lỗi `Invalid` nếu bên nhận không xác minh được tệp
+    pub async fn send(&self, path: &str, name: &str, sockets: &mut [Socket]) -> Result<Report> {
+        if sockets.is_empty() {
+            return Err(Error::Net("no socket to send on".into()));
+        }
+        let size = self.file.metadata(path).await?.size;
+        let hash = self.file.hash(path).await?;
+        let chunk = self.chunk;
+        let count = size.div_ceil(chunk as u64);
+
+        let offer = Message::Offer { name: name.to_string(), size, chunk, hash: hash.clone() };
+        send(&mut sockets[0], &offer).await?;
+        let have = match receive(&mut sockets[0]).await? {
+            Message::Have { ranges } => ranges,
+            other => return Err(Error::Protocol(format!("expected have, got {:?}", other))),
+        };
+
+        let mut queue: VecDeque<u64> = (0..count).collect();
+        queue.retain(|index| !have.iter().any(|(start, end)| (*start..*end).contains(index)));
+        let resumed = size - queue.iter().map(|index| length(*index, size, chunk)).sum::<u64>();
+        let queue = Mutex::new(queue);
+        let done = Mutex::new(resumed);
+
+        let streams = sockets.iter_mut().map(|socket| async {
+            let mut moved = 0;
+            loop {
+                // Take the guard in its own statement so it is not held across the awaits below
+                let next = lock(&queue).pop_front();
+                let Some(index) = next else {
+                    break;
+                };
+                let data = self.file.read_range(path, index * chunk as u64, chunk as u64).await?;
+                let mut frame = Vec::with_capacity(HEADER + data.len());
+                frame.push(DATA);
+                frame.extend_from_slice(&index.to_be_bytes());
+                frame.extend_from_slice(&Sha256::digest(&data));
+                frame.extend_from_slice(&data);
+                socket.send(&frame).await?;
+                moved += data.len() as u64;
+                self.notify(&done, data.len() as u64, size);
+            }
+            socket.send(&[END]).await?;
+            Ok::<u64, Error>(moved)
+        });
+        let moved = try_join_all(streams).await?.into_iter().sum();
+
+        // The receiver hashes the whole file before answering, which may outlast the idle deadline
+        let deadline = sockets[0].deadline();
+        sockets[0].set_deadline(Deadline {
+            read: deadline.read.max(self.finish),
+            idle: deadline.idle.max(self.finish),
+            ..deadline
+        });
+        let done = receive(&mut sockets[0]).await;
+        sockets[0].set_deadline(deadline);
+        match done? {
+            Message::Done { ok: true, .. } => Ok(Report { name: name.to_string(), size, hash, moved, resumed }),
+            Message::Done { reason, .. } => Err(Error::Invalid {
+                rule: "sha256".into(),
+                reason: reason.unwrap_or_else(|| "receiver rejected the file".into()),
+            }),
+            other => Err(Error::Protocol(format!("expected done, got {:?}", other))),
+        }
+    }
+
+    /// Nhận một tệp trên tất cả các socket, tiếp tục từ lần nhận dở trước nếu có
+    ///
+    /// Nếu kết nối đứt giữa chừng, tiến độ được lưu lại và lỗi được trả về;
+    /// gọi lại với các socket mới để nhận nốt phần còn thiếu.
+    ///
+    /// # Arguments
+    /// * `sockets` - Các socket đã kết nối tới bên gửi, cùng số lượng và thứ tự như bên gửi
+    ///
+    /// # Returns
+    /// * `Result<Report>` - Kết quả với tệp nằm ở `transfer/<tên>`, lỗi `Invalid` nếu SHA-256
+    ///   của toàn bộ tệp không khớp hoặc `Protocol` nếu bên gửi vi phạm giao thức
+    pub async fn receive(&self, sockets: &mut [Socket]) -> Result<Report> {
+        if sockets.is_empty() {
+            return Err(Error::Net("no socket to receive on".into()));
+        }
+        let (name, size, chunk, hash) = match receive(&mut sockets[0]).await? {
+            Message::Offer { name, size, chunk, hash } if (1..=LIMIT).contains(&chunk) => (name, size, chunk, hash),
+            other => return Err(Error::Protocol(format!("invalid offer {:?}", other))),
+        };
+        let target = confine(&name)?;
+        let part = format!("{}{}", target, PART);
+        let state = format!("{}{}", target, STATE);
+        let count = size.div_ceil(chunk as u64);
+
+        let mut progress = Progress { size, chunk, hash: hash.clone(), verified: BTreeSet::new() };
+        if let Some(saved) = self.load(&state).await? {
+            let valid = saved.verified.last().is_none_or(|last| *last < count);
+            if valid && (saved.size, saved.chunk, &saved.hash) == (size, chunk, &hash) {
+                progress.verified = saved.verified;
+            }
+        }
+        if progress.verified.is_empty() {
+            Storable::delete(&self.file, &part).await?;
+        }
+        send(&mut sockets[0], &Message::Have { ranges: ranges(&progress.verified) }).await?;
+
+        let resumed = progress.verified.iter().map(|index| length(*index, size, chunk)).sum();
+        let progress = Mutex::new(progress);
+        let done = Mutex::new(resumed);
+
+        let streams = sockets.iter_mut().map(|socket| async {
+            let mut buf = vec![0u8; HEADER + chunk];
+            let mut moved = 0;
+            let mut unsaved = 0;
+            loop {
+                let n = socket.receive(&mut buf).await?;
+                match buf[..n].first() {
+                    Some(&END) => break,
+                    Some(&DATA) if n >= HEADER => {}
+                    _ => return Err(Error::Protocol("invalid transfer frame".into())),
+                }
+                let index = u64::from_be_bytes(buf[1..9].try_into().unwrap_or_default());
+                if index >= count {
+                    return Err(Error::Protocol(format!("chunk {} out of range, file has {}", index, count)));
+                }
+                let offset = index.checked_mul(chunk as u64)
+                    .ok_or_else(|| Error::Protocol(format!("chunk {} offset overflows", index)))?;
+                let data = &buf[HEADER..n];
+                if Sha256::digest(data).as_slice() != &buf[9..HEADER] || data.len() as u64 != length(index, size, chunk) {
+                    // Left unverified, so the next attempt asks for it again
+                    tracing::warn!("chunk {} of {} failed verification", index, name);
+                    continue;
+                }
+
+                self.file.write_at(&part, offset, data).await?;
+                lock(&progress).verified.insert(index);
+                moved += data.len() as u64;
+                self.notify(&done, data.len() as u64, size);
+                unsaved += 1;
+                if unsaved == SAVE {
+                    self.save(&state, &progress).await?;
+                    unsaved = 0;
+                }
+            }
+            Ok::<u64, Error>(moved)
+        });
+        let result = try_join_all(streams).await;
+        self.save(&state, &progress).await?;
+        let moved = result?.into_iter().sum();
+
+        let missing = count - lock(&progress).verified.len() as u64;
+        if missing > 0 {
+            return reject(&mut sockets[0], format!("{} chunks missing or corrupt", missing)).await;
+        }
+        if size == 0 {
+            self.file.write(&part, &[]).await?;
+        }
+        let actual = self.file.hash(&part).await?;
+        if actual != hash {
+            // Every chunk matched but the whole file does not, so start over next time
+            Storable::delete(&self.file, &state).await?;
+            return reject(&mut sockets[0], format!("file hash {} does not match {}", actual, hash)).await;
+        }
+
+        self.file.rename(&part, &target).await?;
+        Storable::delete(&self.file, &state).await?;
+        send(&mut sockets[0], &Message::Done { ok: true, reason: None }).await?;
+        Ok(Report { name, size, hash, moved, resumed })
+    }
+
+    fn notify(&self, done: &Mutex<u64>, len: u64, total: u64) {
+        let done = {
+            let mut done = lock(done);
+            *done += len;
+            *done
+        };
+        if let Some(observer) = &self.observer {
+            observer(Status { done, total });
+        }
+    }
+
+    async fn load(&self, state: &str) -> Result<Option<Progress>> {
+        match Storable::get(&self.file, state).await? {
+            Some(json) => Ok(serde_json::from_slice(&json).ok()),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, state: &str, progress: &Mutex<Progress>) -> Result<()> {
+        let json = serde_json::to_vec(&*lock(progress)).map_err(|e| Error::Store(e.to_string()))?;
+        self.file.write(state, &json).await
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Số byte của khối `index` trong tệp `size` byte
+fn length(index: u64, size: u64, chunk: usize) -> u64 {
+    size.saturating_sub(index.saturating_mul(chunk as u64)).min(chunk as u64)
+}
+
+/// Đường dẫn trong `DIR` cho tên tệp bên gửi đặt
+///
+/// # Returns
+/// * `Result<String>` - Đường dẫn, lỗi `Protocol` nếu tên rỗng hoặc chứa dấu phân cách
+fn confine(name: &str) -> Result<String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(Error::Protocol(format!("invalid file name {:?}", name)));
+    }
+    Ok(format!("{}/{}", DIR, name))
+}
+
+/// Gộp các chỉ số liên tiếp thành khoảng `[đầu, cuối)`
+fn ranges(indices: &BTreeSet<u64>) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for &index in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == index => *end += 1,
+            _ => ranges.push((index, index + 1)),
+        }
+    }
+    ranges
+}
+
+/// Báo bên gửi rằng tệp không được chấp nhận
+async fn reject(socket: &mut Socket, reason: String) -> Result<Report> {
+    send(socket, &Message::Done { ok: false, reason: Some(reason.clone()) }).await?;
+    Err(Error::Invalid { rule: "sha256".into(), reason })
+}
+
+async fn send(socket: &mut Socket, message: &Message) -> Result<()> {
+    let json = serde_json::to_vec(message).map_err(|e| Error::Protocol(e.to_string()))?;
+    socket.send(&json).await?;
+    Ok(())
+}
+
+async fn receive(socket: &mut Socket) -> Result<Message> {
+    let mut buf = vec![0u8; CONTROL];
+    let n = socket.receive(&mut buf).await?;
+    serde_json::from_slice(&buf[..n]).map_err(|e| Error::Protocol(format!("invalid transfer message: {}", e)))
+}
@@ -1,60 +1,419 @@
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{BTreeMap, HashMap};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
 use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use tokio::task::JoinHandle;
 
-use crate::core::error::Result;
+use crate::core::error::{Error, Result};
+use crate::core::link::{Linkable, Storable};
+use crate::core::state::{Mode, State};
 
+/// Number of shards of an unbounded cache
+const SHARDS: usize = 16;
+
+/// Default time between two sweeps of the background task
+const SWEEP: Duration = Duration::from_secs(1);
+
+/// Which entry is evicted when the cache is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evict {
+    /// Least recently used
+    Lru,
+    /// Least frequently used, ties broken by least recently used
+    Lfu,
+}
+
+/// Capacity of the cache; `None` means unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bounds {
+    pub entries: Option<usize>,
+    /// Total size of keys and values
+    pub bytes: Option<usize>,
+}
+
+impl Bounds {
+    /// Share of the bounds held by each of `shards` shards
+    fn split(self, shards: usize) -> Self {
+        Self {
+            entries: self.entries.map(|max| max.div_ceil(shards)),
+            bytes: self.bytes.map(|max| max.div_ceil(shards)),
+        }
+    }
+}
+
+/// Counters since the cache was created, plus its current size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+/// Position in eviction order, lowest first
+type Rank = (u64, u64);
+
+struct Entry {
+    value: Vec<u8>,
+    /// `None` when the TTL is too long to represent, so the entry never expires
+    expires: Option<Instant>,
+    /// Recency and frequency, updated by readers under the read lock
+    tick: AtomicU64,
+    uses: AtomicU64,
+    /// Rank under which the entry is filed in `Shard::order`
+    filed: Rank,
+    /// Key of the entry in `Shard::expiry`, if it expires
+    due: Option<(Instant, u64)>,
+}
+
+impl Entry {
+    fn live(&self, now: Instant) -> bool {
+        self.expires.is_none_or(|expires| expires > now)
+    }
+
+    fn rank(&self, evict: Evict) -> Rank {
+        let tick = self.tick.load(Ordering::Relaxed);
+        match evict {
+            Evict::Lru => (tick, 0),
+            Evict::Lfu => (self.uses.load(Ordering::Relaxed), tick),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shard {
+    map: HashMap<String, Entry>,
+    /// Eviction index; ranks only grow, so stale positions are fixed lazily when evicting
+    order: BTreeMap<Rank, String>,
+    /// Expiry index, soonest first
+    expiry: BTreeMap<(Instant, u64), String>,
+    bytes: usize,
+    clock: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    expirations: AtomicU64,
+}
+
+impl Shard {
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn insert(&mut self, evict: Evict, key: String, value: Vec<u8>, expires: Option<Instant>) {
+        let tick = self.tick();
+        let mut entry = Entry {
+            value,
+            expires,
+            tick: AtomicU64::new(tick),
+            uses: AtomicU64::new(0),
+            filed: (0, 0),
+            due: expires.map(|expires| (expires, tick)),
+        };
+        entry.filed = entry.rank(evict);
+        self.bytes += key.len() + entry.value.len();
+        self.order.insert(entry.filed, key.clone());
+        if let Some(due) = entry.due {
+            self.expiry.insert(due, key.clone());
+        }
+        self.map.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.map.remove(key)?;
+        self.order.remove(&entry.filed);
+        if let Some(due) = &entry.due {
+            self.expiry.remove(due);
+        }
+        self.bytes -= key.len() + entry.value.len();
+        Some(entry)
+    }
+
+    /// Evicts the lowest ranked entry, refiling entries that were used since they were filed
+    fn evict(&mut self, evict: Evict) -> bool {
+        while let Some((filed, key)) = self.order.pop_first() {
+            let Some(entry) = self.map.get_mut(&key) else {
+                continue;
+            };
+            let rank = entry.rank(evict);
+            if rank != filed {
+                entry.filed = rank;
+                self.order.insert(rank, key);
+                continue;
+            }
+            self.remove(&key);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Removes entries that expired by `now`
+    fn sweep(&mut self, now: Instant) -> usize {
+        let mut removed = 0;
+        while let Some(entry) = self.expiry.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let key = entry.remove();
+            if self.remove(&key).is_some() {
+                removed += 1;
+            }
+        }
+        self.expirations.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
+    fn full(&self, bounds: &Bounds, extra: usize) -> bool {
+        bounds.entries.is_some_and(|max| self.map.len() >= max)
+            || bounds.bytes.is_some_and(|max| self.bytes + extra > max)
+    }
+}
+
+/// Key-value cache with a default TTL, optional capacity bounds and LRU or LFU eviction
+///
+/// Keys are spread over shards; reads only take a shard's read lock. Expired entries are
+/// dropped on access, by `cleanup`, or by a background sweeper between `start` and `stop`.
 #[derive(Clone)]
 pub struct Cache {
-    store: Arc<RwLock<HashMap<String, (Vec<u8>, Instant)>>>,
+    shards: Arc<[RwLock<Shard>]>,
+    hasher: RandomState,
     ttl: Duration,
+    bounds: Bounds,
+    evict: Evict,
+    interval: Duration,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    state: State,
 }
 
 impl Cache {
+    /// Creates an unbounded cache whose entries live for `ttl`
     pub fn new(ttl: Duration) -> Self {
+        Self::sharded(ttl, Bounds::default(), Evict::Lru, SHARDS)
+    }
+
+    /// Creates a single-shard cache that evicts entries in exact LRU or LFU order to stay within `bounds`
+    pub fn bounded(ttl: Duration, bounds: Bounds, evict: Evict) -> Self {
+        Self::sharded(ttl, bounds, evict, 1)
+    }
+
+    /// Creates a cache of `shards` shards, each holding an equal share of `bounds`
+    ///
+    /// More shards let more writers proceed in parallel, but eviction order is then only kept per shard.
+    pub fn sharded(ttl: Duration, bounds: Bounds, evict: Evict, shards: usize) -> Self {
+        let shards = shards.max(1);
         Self {
-            store: Arc::new(RwLock::new(HashMap::new())),
+            shards: (0..shards).map(|_| RwLock::new(Shard::default())).collect(),
+            hasher: RandomState::new(),
             ttl,
+            bounds: bounds.split(shards),
+            evict,
+            interval: SWEEP,
+            task: Arc::new(Mutex::new(None)),
+            state: State::new(),
         }
     }
 
+    /// Sets the time between two sweeps of the background task
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
     pub async fn set(&self, key: &str, value: Vec<u8>) -> Result<()> {
-        let mut store = self.store.write().await;
-        store.insert(key.to_string(), (value, Instant::now()));
+        self.set_for(key, value, self.ttl).await
+    }
+
+    /// Stores a value that expires after `ttl` instead of the cache default
+    ///
+    /// A `ttl` too long to add to the current time, such as `Duration::MAX`, never expires.
+    /// Fails with `Error::Capacity` if the entry alone is larger than the byte bound.
+    pub async fn set_for(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<()> {
+        let size = key.len() + value.len();
+        if self.bounds.bytes.is_some_and(|max| size > max) {
+            return Err(Error::Capacity(format!("cache entry {} of {} bytes exceeds the cache size", key, size)));
+        }
+
+        let mut shard = self.write(key);
+        shard.remove(key);
+        while shard.full(&self.bounds, size) && shard.evict(self.evict) {}
+        shard.insert(self.evict, key.to_string(), value, Instant::now().checked_add(ttl));
         Ok(())
     }
 
     pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
-        let mut store = self.store.write().await;
-        
-        if let Some((value, time)) = store.get(key) {
-            if time.elapsed() > self.ttl {
-                store.remove(key);
-                Ok(None)
-            } else {
-                Ok(Some(value.clone()))
+        let now = Instant::now();
+        {
+            let shard = self.read(key);
+            match shard.map.get(key) {
+                Some(entry) if entry.live(now) => {
+                    entry.tick.store(shard.tick(), Ordering::Relaxed);
+                    entry.uses.fetch_add(1, Ordering::Relaxed);
+                    shard.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(Some(entry.value.clone()));
+                }
+                Some(_) => {}
+                None => {
+                    shard.misses.fetch_add(1, Ordering::Relaxed);
+                    return Ok(None);
+                }
             }
-        } else {
-            Ok(None)
         }
+
+        // Expired: only now take the write lock, and recheck since a writer may have replaced it
+        let mut shard = self.write(key);
+        if shard.map.get(key).is_some_and(|entry| !entry.live(now)) {
+            shard.remove(key);
+            shard.expirations.fetch_add(1, Ordering::Relaxed);
+        }
+        shard.misses.fetch_add(1, Ordering::Relaxed);
+        Ok(None)
     }
 
     pub async fn remove(&self, key: &str) -> Result<()> {
-        let mut store = self.store.write().await;
-        store.remove(key);
+        self.write(key).remove(key);
         Ok(())
     }
 
     pub async fn clear(&self) -> Result<()> {
-        let mut store = self.store.write().await;
-        store.clear();
+        for shard in self.shards.iter() {
+            let mut shard = shard.write().unwrap_or_else(|e| e.into_inner());
+            shard.map.clear();
+            shard.order.clear();
+            shard.expiry.clear();
+            shard.bytes = 0;
+        }
         Ok(())
     }
 
-    pub async fn cleanup(&self) -> Result<()> {
-        let mut store = self.store.write().await;
-        store.retain(|_, (_, time)| time.elapsed() <= self.ttl);
+    /// Drops expired entries and returns how many were removed
+    pub async fn cleanup(&self) -> Result<usize> {
+        Ok(sweep(&self.shards))
+    }
+
+    /// Lists the unexpired keys starting with `prefix`, in order
+    pub async fn keys(&self, prefix: &str) -> Vec<String> {
+        let now = Instant::now();
+        let mut keys: Vec<String> = self.shards.iter()
+            .flat_map(|shard| {
+                let shard = shard.read().unwrap_or_else(|e| e.into_inner());
+                shard.map.iter()
+                    .filter(|(key, entry)| entry.live(now) && key.starts_with(prefix))
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    pub async fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap_or_else(|e| e.into_inner()).map.len()).sum()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    pub async fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for shard in self.shards.iter() {
+            let shard = shard.read().unwrap_or_else(|e| e.into_inner());
+            stats.hits += shard.hits.load(Ordering::Relaxed);
+            stats.misses += shard.misses.load(Ordering::Relaxed);
+            stats.evictions += shard.evictions.load(Ordering::Relaxed);
+            stats.expirations += shard.expirations.load(Ordering::Relaxed);
+            stats.entries += shard.map.len();
+            stats.bytes += shard.bytes;
+        }
+        stats
+    }
+
+    fn index(&self, key: &str) -> usize {
+        (self.hasher.hash_one(key) % self.shards.len() as u64) as usize
+    }
+
+    fn read(&self, key: &str) -> RwLockReadGuard<'_, Shard> {
+        self.shards[self.index(key)].read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self, key: &str) -> RwLockWriteGuard<'_, Shard> {
+        self.shards[self.index(key)].write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl Storable for Cache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Cache::get(self, key).await
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.set(key, value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.remove(key).await
+    }
+
+    /// Checks without counting a hit or refreshing the entry
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let now = Instant::now();
+        Ok(self.read(key).map.get(key).is_some_and(|entry| entry.live(now)))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.keys(prefix).await)
+    }
+}
+
+fn sweep(shards: &[RwLock<Shard>]) -> usize {
+    let now = Instant::now();
+    shards
+        .iter()
+        .map(|shard| shard.write().unwrap_or_else(|e| e.into_inner()).sweep(now))
+        .sum()
+}
+
+#[async_trait]
+impl Linkable for Cache {
+    /// Starts the background sweeper; it also ends once every clone of the cache is dropped
+    async fn start(&mut self) -> Result<()> {
+        self.state.set_mode(Mode::Ready).await?;
+        let shards = Arc::downgrade(&self.shards);
+        let interval = self.interval;
+        let task = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(interval);
+            timer.tick().await;
+            loop {
+                timer.tick().await;
+                let Some(shards) = shards.upgrade() else {
+                    break;
+                };
+                let removed = sweep(&shards);
+                if removed > 0 {
+                    tracing::debug!("cache sweeper expired {} entries", removed);
+                }
+            }
+        });
+        if let Some(old) = self.task.lock().unwrap_or_else(|e| e.into_inner()).replace(task) {
+            old.abort();
+        }
         Ok(())
     }
-} 
\ No newline at end of file
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(task) = self.task.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            task.abort();
+        }
+        self.state.set_mode(Mode::Close).await
+    }
+
+    async fn state(&self) -> Result<Mode> {
+        self.state.mode().await
+    }
+}
@@ -1,41 +1,473 @@
+use std::borrow::Cow;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use async_trait::async_trait;
+use rand::Rng;
 
-use crate::core::error::Result;
+use crate::core::error::{Error, Result};
+use crate::core::link::{Op, Storable};
+use crate::guard::Crypt;
+use crate::store::File;
 
+/// Journal size above which it is compacted, once at least half of it is stale
+const COMPACT: u64 = 1 << 20;
+
+/// Checksum and body length before each record
+const HEADER: usize = 8;
+
+const SET: u8 = 1;
+const REMOVE: u8 = 2;
+const CLEAR: u8 = 3;
+/// Records nested in the value, applied all or nothing
+const BATCH: u8 = 4;
+
+/// Append-only log of every change, replayed on open
+///
+/// Each record is `crc32 | len | op | key len | key | value` with big-endian lengths;
+/// a torn or corrupt tail left by a crash is cut off during recovery. With a `Crypt`
+/// everything after the length is sealed, bound to the journal path.
+struct Journal {
+    path: PathBuf,
+    file: fs::File,
+    crypt: Option<Arc<Crypt>>,
+    /// Associated data of sealed records
+    context: Vec<u8>,
+    /// Bytes sealing adds to each record
+    overhead: u64,
+    /// Current size of the log
+    size: u64,
+    /// Size the log would have if it only held the live entries
+    live: u64,
+    compact: u64,
+}
+
+impl Journal {
+    async fn open(path: PathBuf, context: &str, crypt: Option<Arc<Crypt>>) -> Result<(Self, HashMap<String, Vec<u8>>)> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| Error::io("create directory", e))?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .await
+            .map_err(|e| Error::io(path.display().to_string(), e))?;
+
+        let mut log = Vec::new();
+        file.read_to_end(&mut log)
+            .await
+            .map_err(|e| Error::io("read journal", e))?;
+        let context = context.as_bytes().to_vec();
+        let unseal = crypt.as_deref().map(|crypt| (crypt, &context[..]));
+        let (map, valid) = replay(&log, unseal)?;
+        if valid < log.len() {
+            tracing::warn!("journal {} has {} corrupt trailing bytes, truncating", path.display(), log.len() - valid);
+            file.set_len(valid as u64)
+                .await
+                .map_err(|e| Error::io("truncate journal", e))?;
+            file.sync_data()
+                .await
+                .map_err(|e| Error::io("sync journal", e))?;
+        }
+
+        let overhead = match &crypt {
+            Some(crypt) => crypt.seal(&[], &context)?.len() as u64,
+            None => 0,
+        };
+        let mut journal = Self {
+            path,
+            file,
+            crypt,
+            context,
+            overhead,
+            size: valid as u64,
+            live: 0,
+            compact: COMPACT,
+        };
+        journal.live = map.iter().map(|(key, value)| journal.cost(key, value)).sum();
+        Ok((journal, map))
+    }
+
+    /// Appends one record, sealing it when the journal is encrypted
+    ///
+    /// A failed write is cut off again, so later records never follow a partial one.
+    async fn append(&mut self, op: u8, key: &str, value: &[u8]) -> Result<()> {
+        let record = self.encode(op, key, value)?;
+        let written = match self.file.write_all(&record).await {
+            Ok(()) => self.file.sync_data().await.map_err(|e| Error::io("sync journal", e)),
+            Err(e) => Err(Error::io("write journal", e)),
+        };
+        if let Err(e) = written {
+            if let Err(cut) = self.file.set_len(self.size).await {
+                tracing::warn!("journal {} could not be truncated after a failed write: {}", self.path.display(), cut);
+            }
+            return Err(e);
+        }
+        self.size += record.len() as u64;
+        Ok(())
+    }
+
+    fn encode(&self, op: u8, key: &str, value: &[u8]) -> Result<Vec<u8>> {
+        let body = body(op, key, value);
+        match &self.crypt {
+            Some(crypt) => Ok(frame(&crypt.seal(&body, &self.context)?)),
+            None => Ok(frame(&body)),
+        }
+    }
+
+    /// Size of the record storing `key` and `value`
+    fn cost(&self, key: &str, value: &[u8]) -> u64 {
+        (HEADER + 5 + key.len() + value.len()) as u64 + self.overhead
+    }
+
+    fn stale(&self) -> bool {
+        self.size > self.compact && self.size > 2 * self.live
+    }
+
+    /// Rewrites the log with one record per live entry, replacing it atomically
+    ///
+    /// Sealed records are re-encrypted with the current key of the ring.
+    async fn compact(&mut self, map: &HashMap<String, Vec<u8>>) -> Result<()> {
+        let parent = self.path.parent().map(PathBuf::from).unwrap_or_default();
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = parent.join(format!(".{}.{:016x}.compact", name, rand::thread_rng().gen::<u64>()));
+        let mut log = Vec::with_capacity(self.live as usize);
+        for (key, value) in map {
+            log.extend(self.encode(SET, key, value)?);
+        }
+
+        let written = async {
+            let mut file = fs::File::create(&temp)
+                .await
+                .map_err(|e| Error::io("create journal", e))?;
+            file.write_all(&log)
+                .await
+                .map_err(|e| Error::io("write journal", e))?;
+            file.sync_all()
+                .await
+                .map_err(|e| Error::io("sync journal", e))?;
+            fs::rename(&temp, &self.path)
+                .await
+                .map_err(|e| Error::io("replace journal", e))
+        };
+        if let Err(e) = written.await {
+            let _ = fs::remove_file(&temp).await;
+            return Err(e);
+        }
+
+        // Switch to the new log at once, so appends never go to the replaced file
+        self.file = fs::OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| Error::io(self.path.display().to_string(), e))?;
+        self.size = log.len() as u64;
+        self.live = self.size;
+
+        // Persist the rename itself
+        let dir = if parent.as_os_str().is_empty() { PathBuf::from(".") } else { parent };
+        fs::File::open(&dir)
+            .await
+            .map_err(|e| Error::io("open journal directory", e))?
+            .sync_all()
+            .await
+            .map_err(|e| Error::io("sync journal directory", e))
+    }
+}
+
+struct Inner {
+    map: HashMap<String, Vec<u8>>,
+    journal: Option<Journal>,
+}
+
+/// Key-value store, kept in memory and optionally persisted to a journal
 #[derive(Clone)]
 pub struct Data {
-    store: Arc<RwLock<HashMap<String, Vec<u8>>>>,
+    store: Arc<RwLock<Inner>>,
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Data {
+    /// Creates an in-memory store whose contents are lost when dropped
     pub fn new() -> Self {
         Self {
-            store: Arc::new(RwLock::new(HashMap::new())),
+            store: Arc::new(RwLock::new(Inner {
+                map: HashMap::new(),
+                journal: None,
+            })),
+        }
+    }
+
+    /// Opens a durable store journaled at `path` under the root of `file`, recovering its contents
+    pub async fn open(file: &File, path: &str) -> Result<Self> {
+        Self::journaled(file, path, None).await
+    }
+
+    /// Opens a durable store like `open`, with every journal record encrypted by `crypt`
+    ///
+    /// Recovery fails with `Error::Decrypt` if a record was sealed with a key the ring no longer holds.
+    pub async fn sealed(file: &File, path: &str, crypt: Arc<Crypt>) -> Result<Self> {
+        Self::journaled(file, path, Some(crypt)).await
+    }
+
+    async fn journaled(file: &File, path: &str, crypt: Option<Arc<Crypt>>) -> Result<Self> {
+        let (journal, map) = Journal::open(file.resolve(path).await?, path, crypt).await?;
+        Ok(Self {
+            store: Arc::new(RwLock::new(Inner {
+                map,
+                journal: Some(journal),
+            })),
+        })
+    }
+
+    /// Sets the journal size above which it is compacted
+    pub async fn set_compact(&self, bytes: u64) {
+        if let Some(journal) = self.store.write().await.journal.as_mut() {
+            journal.compact = bytes;
         }
     }
 
     pub async fn set(&self, key: &str, value: Vec<u8>) -> Result<()> {
         let mut store = self.store.write().await;
-        store.insert(key.to_string(), value);
+        let Inner { map, journal } = &mut *store;
+        if let Some(journal) = journal {
+            journal.append(SET, key, &value).await?;
+            journal.live += journal.cost(key, &value);
+            if let Some(old) = map.get(key) {
+                journal.live -= journal.cost(key, old);
+            }
+        }
+        map.insert(key.to_string(), value);
+        Self::maintain(&mut store).await;
         Ok(())
     }
 
     pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
         let store = self.store.read().await;
-        Ok(store.get(key).cloned())
+        Ok(store.map.get(key).cloned())
     }
 
     pub async fn remove(&self, key: &str) -> Result<()> {
         let mut store = self.store.write().await;
-        store.remove(key);
+        let Inner { map, journal } = &mut *store;
+        if !map.contains_key(key) {
+            return Ok(());
+        }
+        if let Some(journal) = journal {
+            journal.append(REMOVE, key, &[]).await?;
+            journal.live -= journal.cost(key, &map[key]);
+        }
+        map.remove(key);
+        Self::maintain(&mut store).await;
         Ok(())
     }
 
     pub async fn clear(&self) -> Result<()> {
         let mut store = self.store.write().await;
-        store.clear();
+        if let Some(journal) = store.journal.as_mut() {
+            journal.append(CLEAR, "", &[]).await?;
+            journal.live = 0;
+        }
+        store.map.clear();
+        Self::maintain(&mut store).await;
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// Applies the operations together; with a journal they are written as one record
+    pub async fn batch(&self, ops: Vec<Op>) -> Result<()> {
+        let mut store = self.store.write().await;
+        let Inner { map, journal } = &mut *store;
+        if let Some(journal) = journal {
+            let mut records = Vec::new();
+            for op in &ops {
+                match op {
+                    Op::Put(key, value) => records.extend(encode(SET, key, value)),
+                    Op::Delete(key) => records.extend(encode(REMOVE, key, &[])),
+                }
+            }
+            journal.append(BATCH, "", &records).await?;
+        }
+
+        for op in ops {
+            match op {
+                Op::Put(key, value) => {
+                    if let Some(journal) = journal {
+                        journal.live += journal.cost(&key, &value);
+                        if let Some(old) = map.get(&key) {
+                            journal.live -= journal.cost(&key, old);
+                        }
+                    }
+                    map.insert(key, value);
+                }
+                Op::Delete(key) => {
+                    if let (Some(journal), Some(old)) = (journal.as_mut(), map.get(&key)) {
+                        journal.live -= journal.cost(&key, old);
+                    }
+                    map.remove(&key);
+                }
+            }
+        }
+        Self::maintain(&mut store).await;
+        Ok(())
+    }
+
+    /// Lists the keys starting with `prefix`, in order
+    pub async fn keys(&self, prefix: &str) -> Vec<String> {
+        let store = self.store.read().await;
+        let keys: BTreeSet<&String> = store.map.keys().filter(|key| key.starts_with(prefix)).collect();
+        keys.into_iter().cloned().collect()
+    }
+
+    /// Lists the entries whose key starts with `prefix`, in key order, as one consistent view
+    pub async fn entries(&self, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        let store = self.store.read().await;
+        let mut entries: Vec<_> = store.map.iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    /// Rewrites the journal to hold only the live entries
+    pub async fn compact(&self) -> Result<()> {
+        let mut store = self.store.write().await;
+        let Inner { map, journal } = &mut *store;
+        match journal {
+            Some(journal) => journal.compact(map).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Compacts a stale journal; the write that triggered it is already durable, so a failure is only logged
+    async fn maintain(store: &mut Inner) {
+        if let Some(journal) = store.journal.as_mut().filter(|journal| journal.stale()) {
+            if let Err(e) = journal.compact(&store.map).await {
+                tracing::warn!("journal {} could not be compacted: {}", journal.path.display(), e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Storable for Data {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Data::get(self, key).await
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.set(key, value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.remove(key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.store.read().await.map.contains_key(key))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.keys(prefix).await)
+    }
+
+    async fn batch(&self, ops: Vec<Op>) -> Result<()> {
+        Data::batch(self, ops).await
+    }
+}
+
+/// Record nested in a batch; the batch record as a whole is sealed
+fn encode(op: u8, key: &str, value: &[u8]) -> Vec<u8> {
+    frame(&body(op, key, value))
+}
+
+fn body(op: u8, key: &str, value: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(5 + key.len() + value.len());
+    body.push(op);
+    body.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    body.extend_from_slice(key.as_bytes());
+    body.extend_from_slice(value);
+    body
+}
+
+fn frame(body: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(HEADER + body.len());
+    record.extend_from_slice(&crc32fast::hash(body).to_be_bytes());
+    record.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    record.extend_from_slice(body);
+    record
+}
+
+/// Applies every intact record, returning the contents and the length of the valid prefix
+///
+/// A sealed record that is intact but fails to decrypt is an error rather than a torn
+/// tail, so a wrong key never truncates the journal.
+fn replay(log: &[u8], unseal: Option<(&Crypt, &[u8])>) -> Result<(HashMap<String, Vec<u8>>, usize)> {
+    let mut map = HashMap::new();
+    let offset = apply(&mut map, log, unseal)?;
+    Ok((map, offset))
+}
+
+fn apply(map: &mut HashMap<String, Vec<u8>>, log: &[u8], unseal: Option<(&Crypt, &[u8])>) -> Result<usize> {
+    let mut offset = 0;
+    while let Some((body, next)) = unframe(log, offset) {
+        let body = match unseal {
+            Some((crypt, context)) => Cow::Owned(crypt.open(body, context)?),
+            None => Cow::Borrowed(body),
+        };
+        let Some((op, key, value)) = decode(&body) else {
+            break;
+        };
+        match op {
+            SET => {
+                map.insert(key, value.to_vec());
+            }
+            REMOVE => {
+                map.remove(&key);
+            }
+            BATCH => {
+                apply(map, value, None)?;
+            }
+            _ => map.clear(),
+        }
+        offset = next;
+    }
+    Ok(offset)
+}
+
+/// Returns the body of the record at `offset` if its checksum matches, and where the next one starts
+fn unframe(log: &[u8], offset: usize) -> Option<(&[u8], usize)> {
+    let header = log.get(offset..offset + HEADER)?;
+    let crc = u32::from_be_bytes(header[..4].try_into().ok()?);
+    let len = u32::from_be_bytes(header[4..].try_into().ok()?) as usize;
+    let body = log.get(offset + HEADER..offset + HEADER + len)?;
+    if crc32fast::hash(body) != crc {
+        return None;
+    }
+    Some((body, offset + HEADER + len))
+}
+
+fn decode(body: &[u8]) -> Option<(u8, String, &[u8])> {
+    if body.len() < 5 {
+        return None;
+    }
+    let op = body[0];
+    let klen = u32::from_be_bytes(body[1..5].try_into().ok()?) as usize;
+    let key = std::str::from_utf8(body.get(5..5 + klen)?).ok()?.to_string();
+    if !matches!(op, SET | REMOVE | CLEAR | BATCH) {
+        return None;
+    }
+    Some((op, key, &body[5 + klen..]))
+}
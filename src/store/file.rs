@@ -1,9 +1,32 @@
-use std::path::{Path, PathBuf};
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::SystemTime;
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, ReadBuf, Take};
+use async_trait::async_trait;
+use rand::Rng;
+use sha2::{Digest, Sha256};
 
 use crate::core::error::{Error, Result};
+use crate::core::link::Storable;
 
+/// Suffix of the temporary file a write goes to before it is renamed into place
+const TEMP: &str = ".partial";
+
+/// Size, modification time and kind of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Meta {
+    pub size: u64,
+    pub modified: SystemTime,
+    pub dir: bool,
+}
+
+/// Files under a root directory, addressed by `/`-separated relative paths
+///
+/// Paths that are absolute, contain `..`, or pass through a symlink are rejected
+/// with `Error::Denied`, so untrusted names cannot reach outside the root.
 #[derive(Clone)]
 pub struct File {
     root: PathBuf,
@@ -16,53 +39,415 @@ impl File {
         }
     }
 
+    /// Directory under which every path is resolved
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Writes the whole file atomically: readers see either the old or the new content
     pub async fn write(&self, path: &str, data: &[u8]) -> Result<()> {
-        let path = self.root.join(path);
-        
+        let mut writer = self.create(path).await?;
+        writer.write_all(data)
+            .await
+            .map_err(|e| Error::io("write file", e))?;
+        writer.finish().await?;
+        Ok(())
+    }
+
+    /// Starts streaming a new content for `path`, which replaces the old one on `Writer::finish`
+    pub async fn create(&self, path: &str) -> Result<Writer> {
+        let target = path;
+        let path = self.resolve(path).await?;
+        if path == self.root {
+            return Err(Error::Denied(format!("path {} is not a file", target)));
+        }
+        let parent = path.parent().unwrap_or(&self.root).to_path_buf();
+        fs::create_dir_all(&parent)
+            .await
+            .map_err(|e| Error::io("create directory", e))?;
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = parent.join(format!(".{}.{:016x}{}", name, rand::thread_rng().gen::<u64>(), TEMP));
+        let file = fs::File::create(&temp)
+            .await
+            .map_err(|e| Error::io("create file", e))?;
+        Ok(Writer {
+            file,
+            temp,
+            path,
+            hasher: Sha256::new(),
+            size: 0,
+            done: false,
+        })
+    }
+
+    /// Streams the whole file, hashing it on the way
+    pub async fn open(&self, path: &str) -> Result<Reader> {
+        self.open_range(path, 0, u64::MAX).await
+    }
+
+    /// Streams at most `len` bytes starting at `offset`
+    pub async fn open_range(&self, path: &str, offset: u64, len: u64) -> Result<Reader> {
+        let path = self.resolve(path).await?;
+        let mut file = fs::File::open(&path)
+            .await
+            .map_err(|e| missing(&path, e))?;
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(|e| Error::io("seek file", e))?;
+        Ok(Reader {
+            inner: file.take(len),
+            hasher: Sha256::new(),
+        })
+    }
+
+    /// Reads at most `len` bytes starting at `offset`; fewer are returned at the end of the file
+    pub async fn read_range(&self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let mut reader = self.open_range(path, offset, len).await?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)
+            .await
+            .map_err(|e| Error::io("read file", e))?;
+        Ok(data)
+    }
+
+    /// Appends to a file, creating it if needed, and returns its new size
+    ///
+    /// Unlike `write` this is not atomic: a crash may leave part of `data` appended.
+    pub async fn append(&self, path: &str, data: &[u8]) -> Result<u64> {
+        let path = self.resolve(path).await?;
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)
                 .await
-                .map_err(|e| Error::Store(e.to_string()))?;
+                .map_err(|e| Error::io("create directory", e))?;
         }
-
-        let mut file = fs::File::create(&path)
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .map_err(|e| Error::io("open file", e))?;
+        file.write_all(data)
             .await
-            .map_err(|e| Error::Store(e.to_string()))?;
+            .map_err(|e| Error::io("write file", e))?;
+        file.sync_data()
+            .await
+            .map_err(|e| Error::io("sync file", e))?;
+        let meta = file.metadata()
+            .await
+            .map_err(|e| Error::io("read metadata", e))?;
+        Ok(meta.len())
+    }
 
+    /// Writes `data` at `offset`, creating the file if needed and keeping the rest of it
+    pub async fn write_at(&self, path: &str, offset: u64, data: &[u8]) -> Result<()> {
+        let path = self.resolve(path).await?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| Error::io("create directory", e))?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .await
+            .map_err(|e| Error::io("open file", e))?;
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(|e| Error::io("seek file", e))?;
         file.write_all(data)
             .await
-            .map_err(|e| Error::Store(e.to_string()))?;
+            .map_err(|e| Error::io("write file", e))?;
+        file.sync_data()
+            .await
+            .map_err(|e| Error::io("sync file", e))
+    }
 
-        Ok(())
+    /// Moves a file, replacing any file already at `to`
+    pub async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let from = self.resolve(from).await?;
+        let to = self.resolve(to).await?;
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| Error::io("create directory", e))?;
+        }
+        fs::rename(&from, &to)
+            .await
+            .map_err(|e| missing(&from, e))
+    }
+
+    /// SHA-256 of the file content as lowercase hex, computed without loading it whole
+    pub async fn hash(&self, path: &str) -> Result<String> {
+        let mut reader = self.open(path).await?;
+        tokio::io::copy(&mut reader, &mut tokio::io::sink())
+            .await
+            .map_err(|e| Error::io("read file", e))?;
+        Ok(reader.digest())
     }
 
     pub async fn read(&self, path: &str) -> Result<Vec<u8>> {
-        let path = self.root.join(path);
-        
+        let path = self.resolve(path).await?;
+
         let mut file = fs::File::open(&path)
             .await
-            .map_err(|e| Error::Store(e.to_string()))?;
+            .map_err(|e| missing(&path, e))?;
 
         let mut data = Vec::new();
         file.read_to_end(&mut data)
             .await
-            .map_err(|e| Error::Store(e.to_string()))?;
+            .map_err(|e| Error::io("read file", e))?;
 
         Ok(data)
     }
 
     pub async fn remove(&self, path: &str) -> Result<()> {
-        let path = self.root.join(path);
-        
+        let path = self.resolve(path).await?;
+
         fs::remove_file(&path)
             .await
-            .map_err(|e| Error::Store(e.to_string()))?;
+            .map_err(|e| missing(&path, e))?;
 
         Ok(())
     }
 
     pub async fn exists(&self, path: &str) -> Result<bool> {
-        let path = self.root.join(path);
-        Ok(path.exists())
+        let path = self.resolve(path).await?;
+        Ok(fs::try_exists(&path).await.unwrap_or(false))
     }
-} 
\ No newline at end of file
+
+    pub async fn metadata(&self, path: &str) -> Result<Meta> {
+        let path = self.resolve(path).await?;
+        let meta = fs::metadata(&path)
+            .await
+            .map_err(|e| missing(&path, e))?;
+        Ok(Meta {
+            size: meta.len(),
+            modified: meta.modified().map_err(|e| Error::io("read metadata", e))?,
+            dir: meta.is_dir(),
+        })
+    }
+
+    /// Names of the files and directories directly inside `dir`, in order; `""` is the root
+    pub async fn list(&self, dir: &str) -> Result<Vec<String>> {
+        let path = self.resolve(dir).await?;
+        let mut entries = fs::read_dir(&path)
+            .await
+            .map_err(|e| missing(&path, e))?;
+
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(|e| Error::io("read directory", e))? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !temporary(&name) {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Paths of every file below `dir`, relative to the root and in order; symlinks are skipped
+    pub async fn walk(&self, dir: &str) -> Result<Vec<String>> {
+        let mut pending = vec![self.resolve(dir).await?];
+        let mut paths = Vec::new();
+        while let Some(dir) = pending.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error::io(dir.display().to_string(), e)),
+            };
+            while let Some(entry) = entries.next_entry().await.map_err(|e| Error::io("read directory", e))? {
+                let path = entry.path();
+                let kind = entry.file_type().await.map_err(|e| Error::io("read directory", e))?;
+                if kind.is_dir() {
+                    pending.push(path);
+                } else if kind.is_file() && !temporary(&entry.file_name().to_string_lossy()) {
+                    if let Ok(relative) = path.strip_prefix(&self.root) {
+                        let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+                        paths.push(parts.join("/"));
+                    }
+                }
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Maps a relative path to a location under the root, rejecting anything that could escape it
+    pub(crate) async fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let denied = || Error::Denied(format!("path {} escapes the store root", path));
+        let mut parts = Vec::new();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(part) => parts.push(part),
+                Component::CurDir => {}
+                _ => return Err(denied()),
+            }
+        }
+
+        // A symlink anywhere below the root could point outside it
+        let mut resolved = self.root.clone();
+        let mut present = true;
+        for part in parts {
+            resolved.push(part);
+            if !present {
+                continue;
+            }
+            match fs::symlink_metadata(&resolved).await {
+                Ok(meta) if meta.file_type().is_symlink() => return Err(denied()),
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => present = false,
+                Err(e) => return Err(Error::io(resolved.display().to_string(), e)),
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+/// Streaming reader returned by `File::open`, hashing everything it reads
+pub struct Reader {
+    inner: Take<fs::File>,
+    hasher: Sha256,
+}
+
+impl Reader {
+    /// SHA-256 of the bytes read so far as lowercase hex
+    pub fn digest(&self) -> String {
+        hex(&self.hasher.clone().finalize())
+    }
+}
+
+impl AsyncRead for Reader {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let this = &mut *self;
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.hasher.update(&buf.filled()[before..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Streaming writer returned by `File::create`
+///
+/// Data goes to a temporary file that `finish` moves into place; if the writer is
+/// dropped first, the temporary file is removed and the old content is kept.
+pub struct Writer {
+    file: fs::File,
+    temp: PathBuf,
+    path: PathBuf,
+    hasher: Sha256,
+    size: u64,
+    done: bool,
+}
+
+impl Writer {
+    /// Number of bytes written so far
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Syncs and renames the file into place, returning the SHA-256 of its content as lowercase hex
+    pub async fn finish(mut self) -> Result<String> {
+        self.file.flush()
+            .await
+            .map_err(|e| Error::io("write file", e))?;
+        self.file.sync_all()
+            .await
+            .map_err(|e| Error::io("sync file", e))?;
+        fs::rename(&self.temp, &self.path)
+            .await
+            .map_err(|e| Error::io("rename file", e))?;
+        self.done = true;
+
+        // Persist the rename itself
+        if let Some(parent) = self.path.parent() {
+            if let Ok(dir) = fs::File::open(parent).await {
+                let _ = dir.sync_all().await;
+            }
+        }
+        Ok(hex(&self.hasher.clone().finalize()))
+    }
+}
+
+impl AsyncWrite for Writer {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        let this = &mut *self;
+        let n = ready!(Pin::new(&mut this.file).poll_write(cx, buf))?;
+        this.hasher.update(&buf[..n]);
+        this.size += n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.file).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.file).poll_shutdown(cx)
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = std::fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Whether `name` is a write in progress, named `.{name}.{16 hex digits}.partial`
+fn temporary(name: &str) -> bool {
+    let Some((target, suffix)) = name.strip_suffix(TEMP).and_then(|rest| rest.rsplit_once('.')) else {
+        return false;
+    };
+    target.len() > 1 && target.starts_with('.')
+        && suffix.len() == 16 && suffix.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Lowercase hex of `bytes`
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Keys are paths relative to the root; listing walks the whole tree
+#[async_trait]
+impl Storable for File {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.read(key).await {
+            Ok(data) => Ok(Some(data)),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.write(key, &value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match self.remove(key).await {
+            Err(Error::NotFound(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        File::exists(self, key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = self.walk("").await?;
+        keys.retain(|key| key.starts_with(prefix));
+        Ok(keys)
+    }
+}
+
+/// Chuyển lỗi "không tồn tại" thành `Error::NotFound`, giữ nguyên các lỗi I/O khác
+fn missing(path: &Path, error: std::io::Error) -> Error {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => Error::NotFound(path.display().to_string()),
+        _ => Error::io(path.display().to_string(), error),
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use async_trait::async_trait;
+
+use crate::core::error::{Error, Result};
+use crate::core::link::{Op, Storable};
+
+/// Two stores stacked as one: reads try `front` first, writes go through to both
+///
+/// `back` is the source of truth, for example a durable `Data` behind a `Cache`.
+/// Values read from `back` are copied into `front`; listing only asks `back`.
+/// Errors of `front` are logged and never fail a call: a key whose write to `front`
+/// failed is evicted from it, so it is read from `back` again, and a value read
+/// from `back` while a write was in flight is not kept in `front`.
+pub struct Layer<F, B> {
+    front: F,
+    back: B,
+    /// Bumped by every write between updating `back` and `front`
+    generation: AtomicU64,
+}
+
+impl<F: Storable, B: Storable> Layer<F, B> {
+    pub fn new(front: F, back: B) -> Self {
+        Self {
+            front,
+            back,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    pub fn front(&self) -> &F {
+        &self.front
+    }
+
+    pub fn back(&self) -> &B {
+        &self.back
+    }
+
+    /// Drops `key` from `front` after a failed write so a stale value is never served
+    async fn evict(&self, key: &str, error: Error) {
+        tracing::warn!("front store failed for {}, evicting: {}", key, error);
+        if let Err(e) = self.front.delete(key).await {
+            tracing::warn!("front store could not evict {}: {}", key, e);
+        }
+    }
+}
+
+#[async_trait]
+impl<F: Storable, B: Storable> Storable for Layer<F, B> {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.front.get(key).await {
+            Ok(Some(value)) => return Ok(Some(value)),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("front store failed to read {}: {}", key, e),
+        }
+        let generation = self.generation.load(Ordering::SeqCst);
+        let value = self.back.get(key).await?;
+        if let Some(value) = &value {
+            if let Err(e) = self.front.put(key, value.clone()).await {
+                self.evict(key, e).await;
+            } else if self.generation.load(Ordering::SeqCst) != generation {
+                // A write landed meanwhile and may have reached `front` before this fill
+                if let Err(e) = self.front.delete(key).await {
+                    tracing::warn!("front store could not evict {}: {}", key, e);
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.back.put(key, value.clone()).await?;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.front.put(key, value).await {
+            self.evict(key, e).await;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.back.delete(key).await?;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.front.delete(key).await {
+            tracing::warn!("front store failed to delete {}: {}", key, e);
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        if self.front.exists(key).await.unwrap_or(false) {
+            return Ok(true);
+        }
+        self.back.exists(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        self.back.list(prefix).await
+    }
+
+    async fn batch(&self, ops: Vec<Op>) -> Result<()> {
+        self.back.batch(ops.clone()).await?;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let keys: Vec<String> = ops.iter().map(|op| match op {
+            Op::Put(key, _) | Op::Delete(key) => key.clone(),
+        }).collect();
+        if let Err(e) = self.front.batch(ops).await {
+            tracing::warn!("front store failed to apply a batch of {} keys: {}", keys.len(), e);
+            for key in keys {
+                let _ = self.front.delete(&key).await;
+            }
+        }
+        Ok(())
+    }
+}
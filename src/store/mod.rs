@@ -1,7 +1,15 @@
 pub mod data;
 pub mod cache;
 pub mod file;
+pub mod layer;
+pub mod replica;
+pub mod snapshot;
+pub mod vault;
 
 pub use data::Data;
 pub use cache::Cache;
-pub use file::File; 
\ No newline at end of file
+pub use file::File;
+pub use layer::Layer;
+pub use replica::Replica;
+pub use snapshot::Snapshot;
+pub use vault::Vault; 
\ No newline at end of file
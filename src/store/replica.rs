@@ -0,0 +1,689 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::core::error::{Error, Result};
+use crate::core::link::{Guardable, Linkable, Movable, Op, Settings, Storable};
+use crate::core::state::Mode;
+use crate::core::State;
+use crate::net::Socket;
+use crate::net::route::{Entry, Route};
+use crate::store::file;
+
+/// Bytes of each frame kept free for the message envelope and the guard
+const RESERVE: usize = 512;
+
+/// Default time between two anti-entropy rounds
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default age after which tombstones are collected
+const HORIZON: Duration = Duration::from_secs(3600);
+
+/// How far ahead of the local clock a remote write may be stamped, in milliseconds
+const SKEW: u64 = 60_000;
+
+/// Keys holding route entries, followed by the route name
+pub const ROUTES: &str = "route/";
+
+/// Protects and exposes every message exchanged between members
+pub type Guard = Arc<dyn Guardable + Send + Sync>;
+
+/// Value of a key stamped by the write that set it; a `None` value marks a delete
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Record {
+    value: Option<Vec<u8>>,
+    /// Hybrid clock of the write, in milliseconds
+    time: u64,
+    /// Node that made the write
+    node: String,
+}
+
+impl Record {
+    /// Whether this write replaces `other`; the order is total, so every node keeps the same one
+    fn wins(&self, other: &Record) -> bool {
+        (self.time, &self.node, &self.value) > (other.time, &other.node, &other.value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// Opens every connection with the port the sender listens on, matched against the members
+    Hello { port: u16 },
+    /// Writes just made on the sender
+    Update { entries: Vec<(String, Record)> },
+    /// Starts an anti-entropy round with the digest of the sender's contents
+    Sync { digest: String },
+    /// The contents match the digest, or match again after merging `Full`
+    Same,
+    /// One page of every record, sent by both sides when the digests differ
+    Full { entries: Vec<(String, Record)>, last: bool },
+}
+
+struct Inner {
+    map: BTreeMap<String, Record>,
+    clock: u64,
+    /// Age after which tombstones are collected
+    horizon: Duration,
+}
+
+impl Inner {
+    /// Stamps a local write after every write seen so far, local or remote
+    fn tick(&mut self) -> u64 {
+        self.clock = now().max(self.clock.saturating_add(1));
+        self.clock
+    }
+
+    /// Keeps the winning record of each key, returning the ones that replaced another
+    ///
+    /// Records stamped further than `SKEW` ahead of the local clock are dropped, so a
+    /// peer cannot pin a key or the clock in the future; so are tombstones past the horizon.
+    fn merge(&mut self, entries: Vec<(String, Record)>) -> Vec<(String, Record)> {
+        let (limit, expired) = (now().saturating_add(SKEW), self.expired());
+        let mut changed = Vec::new();
+        for (key, record) in entries {
+            if record.time > limit {
+                tracing::warn!("replica dropped {} stamped {} ms ahead by {}", key, record.time - now(), record.node);
+                continue;
+            }
+            if record.value.is_none() && record.time < expired {
+                continue;
+            }
+            self.clock = self.clock.max(record.time);
+            if self.map.get(&key).is_none_or(|current| record.wins(current)) {
+                self.map.insert(key.clone(), record.clone());
+                changed.push((key, record));
+            }
+        }
+        changed
+    }
+
+    /// Drops tombstones older than the horizon, returning how many
+    fn collect(&mut self) -> usize {
+        let expired = self.expired();
+        let before = self.map.len();
+        self.map.retain(|_, record| record.value.is_some() || record.time >= expired);
+        before - self.map.len()
+    }
+
+    /// Time before which tombstones are collected
+    fn expired(&self) -> u64 {
+        now().saturating_sub(self.horizon.as_millis() as u64)
+    }
+
+    fn entries(&self) -> Vec<(String, Record)> {
+        self.map.iter().map(|(key, record)| (key.clone(), record.clone())).collect()
+    }
+
+    fn digest(&self) -> Result<String> {
+        let json = serde_json::to_vec(&self.map).map_err(|e| Error::Store(e.to_string()))?;
+        Ok(file::hex(&Sha256::digest(&json)))
+    }
+}
+
+/// Writes waiting to be pushed to one peer
+#[derive(Default)]
+struct Outbox {
+    pending: BTreeMap<String, Record>,
+    /// Whether a task is pushing them
+    busy: bool,
+}
+
+/// State shared by a replica, its clones and its background tasks
+struct Node {
+    inner: RwLock<Inner>,
+    peers: RwLock<Vec<String>>,
+    guard: RwLock<Option<Guard>>,
+    route: RwLock<Option<Route>>,
+    outboxes: Mutex<HashMap<String, Outbox>>,
+    /// Address the node listens on, while started
+    bound: Mutex<Option<SocketAddr>>,
+    settings: Settings,
+}
+
+impl Node {
+    fn peers(&self) -> Vec<String> {
+        self.peers.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn guard(&self) -> Option<Guard> {
+        self.guard.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn bound(&self) -> Option<SocketAddr> {
+        *self.bound.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether `addr` is the address of a configured member
+    async fn member(&self, addr: SocketAddr) -> bool {
+        for peer in self.peers() {
+            if let Ok(mut addrs) = tokio::net::lookup_host(peer.as_str()).await {
+                if addrs.any(|resolved| resolved == addr) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Queues writes for `peer`, returning whether a task must be started to push them
+    fn enqueue(&self, peer: &str, entries: &[(String, Record)]) -> bool {
+        let mut outboxes = self.outboxes.lock().unwrap_or_else(|e| e.into_inner());
+        let outbox = outboxes.entry(peer.to_string()).or_default();
+        for (key, record) in entries {
+            if outbox.pending.get(key).is_none_or(|queued| record.wins(queued)) {
+                outbox.pending.insert(key.clone(), record.clone());
+            }
+        }
+        !std::mem::replace(&mut outbox.busy, true)
+    }
+
+    /// Takes the writes queued for `peer`; when there are none the pushing task must stop
+    fn dequeue(&self, peer: &str) -> Vec<(String, Record)> {
+        let mut outboxes = self.outboxes.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(outbox) = outboxes.get_mut(peer) else {
+            return Vec::new();
+        };
+        if outbox.pending.is_empty() {
+            outbox.busy = false;
+        }
+        std::mem::take(&mut outbox.pending).into_iter().collect()
+    }
+
+    /// Drops the writes queued for `peer` after a failed push; the next round carries them
+    fn abandon(&self, peer: &str) {
+        let mut outboxes = self.outboxes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(outbox) = outboxes.get_mut(peer) {
+            outbox.pending.clear();
+            outbox.busy = false;
+        }
+    }
+
+    /// Merges remote records and mirrors the routes among them
+    async fn merge(&self, entries: Vec<(String, Record)>) {
+        let changed = write(&self.inner).merge(entries);
+        self.publish(&changed).await;
+    }
+
+    /// Applies changed `route/` records to the attached route table
+    async fn publish(&self, changed: &[(String, Record)]) {
+        let Some(route) = self.route.read().unwrap_or_else(|e| e.into_inner()).clone() else {
+            return;
+        };
+        for (key, record) in changed {
+            let Some(name) = key.strip_prefix(ROUTES) else {
+                continue;
+            };
+            let result = match &record.value {
+                Some(value) => match serde_json::from_slice::<Entry>(value) {
+                    Ok(entry) => route.add(name.to_string(), entry).await,
+                    Err(e) => Err(Error::Protocol(format!("invalid route entry: {}", e))),
+                },
+                None => route.remove(name).await,
+            };
+            if let Err(e) = result {
+                tracing::warn!("replica could not apply route {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Splits records into `Full` or `Update` pages that each fit `Settings::size`
+    fn pages(&self, entries: Vec<(String, Record)>) -> Result<Vec<Vec<(String, Record)>>> {
+        let budget = self.settings.size.saturating_sub(RESERVE);
+        let mut pages = vec![Vec::new()];
+        let mut used = 0;
+        for entry in entries {
+            let cost = serde_json::to_vec(&entry).map_err(|e| Error::Protocol(e.to_string()))?.len() + 1;
+            if cost > budget {
+                return Err(Error::Capacity(format!("replica record {} of {} bytes exceeds {}", entry.0, cost, budget)));
+            }
+            if used + cost > budget {
+                pages.push(Vec::new());
+                used = 0;
+            }
+            used += cost;
+            if let Some(page) = pages.last_mut() {
+                page.push(entry);
+            }
+        }
+        Ok(pages)
+    }
+
+    async fn send(&self, socket: &mut Socket, message: &Message) -> Result<()> {
+        let json = serde_json::to_vec(message).map_err(|e| Error::Protocol(e.to_string()))?;
+        let frame = match self.guard() {
+            Some(guard) => guard.protect(&json).await?,
+            None => json,
+        };
+        socket.send(&frame).await?;
+        Ok(())
+    }
+
+    async fn receive(&self, socket: &mut Socket, buf: &mut [u8]) -> Result<Message> {
+        let n = socket.receive(buf).await?;
+        let json = match self.guard() {
+            Some(guard) => guard.expose(&buf[..n]).await?,
+            None => buf[..n].to_vec(),
+        };
+        serde_json::from_slice(&json).map_err(|e| Error::Protocol(format!("invalid replica message: {}", e)))
+    }
+
+    /// Connects to `peer` and introduces this node
+    async fn open(&self, peer: &str) -> Result<Socket> {
+        let mut socket = Socket::connect(peer, self.settings.clone()).await?;
+        socket.start().await?;
+        let port = self.bound().map_or(0, |addr| addr.port());
+        self.send(&mut socket, &Message::Hello { port }).await?;
+        Ok(socket)
+    }
+
+    /// Sends every record as pages of `Full`, the last one flagged
+    async fn send_full(&self, socket: &mut Socket, entries: Vec<(String, Record)>) -> Result<()> {
+        let pages = self.pages(entries)?;
+        let count = pages.len();
+        for (i, entries) in pages.into_iter().enumerate() {
+            self.send(socket, &Message::Full { entries, last: i + 1 == count }).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Key-value store replicated across a fixed set of nodes, converging by last writer wins
+///
+/// Writes are stamped with a hybrid clock and the node name, `Settings::name`, which must
+/// be unique among the members. They are queued for every peer and pushed over one
+/// connection per peer while there are writes to push, and between
+/// `start` and `stop` each peer is also reconciled every interval by comparing digests
+/// and exchanging contents in pages that fit `Settings::size`, so a node that missed
+/// updates catches up once reachable.
+///
+/// Only started members are served: a connection must come from the host of a configured
+/// member and name the port it listens on. That is all that identifies a member unless
+/// `set_guard` protects every message, for example with a `Crypt` sharing a cluster key.
+/// Deletes are kept as tombstones so that they win over older writes arriving late, and
+/// are collected after the horizon; a member partitioned for longer may bring a deleted
+/// key back.
+#[derive(Clone)]
+pub struct Replica {
+    node: Arc<Node>,
+    addr: String,
+    interval: Duration,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    state: State,
+}
+
+impl Replica {
+    /// Creates a node that listens on `addr` once started
+    pub fn new(addr: &str, settings: Settings) -> Self {
+        Self {
+            node: Arc::new(Node {
+                inner: RwLock::new(Inner { map: BTreeMap::new(), clock: 0, horizon: HORIZON }),
+                peers: RwLock::new(Vec::new()),
+                guard: RwLock::new(None),
+                route: RwLock::new(None),
+                outboxes: Mutex::new(HashMap::new()),
+                bound: Mutex::new(None),
+                settings,
+            }),
+            addr: addr.to_string(),
+            interval: INTERVAL,
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            state: State::new(),
+        }
+    }
+
+    /// Adds the address of another member; members are configured, never discovered
+    pub fn add_peer(&self, addr: &str) {
+        let mut peers = self.node.peers.write().unwrap_or_else(|e| e.into_inner());
+        if !peers.iter().any(|peer| peer == addr) {
+            peers.push(addr.to_string());
+        }
+    }
+
+    pub fn peers(&self) -> Vec<String> {
+        self.node.peers()
+    }
+
+    /// Protects every message with `guard`, which every member must share
+    pub fn set_guard(&mut self, guard: Guard) {
+        *self.node.guard.write().unwrap_or_else(|e| e.into_inner()) = Some(guard);
+    }
+
+    /// Sets the time between anti-entropy rounds, taking effect on the next `start`
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Sets the age after which tombstones are collected, longer than any expected partition
+    pub fn set_horizon(&mut self, horizon: Duration) {
+        write(&self.node.inner).horizon = horizon;
+    }
+
+    /// Address the node listens on, while started
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.node.bound()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.node.settings.name
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(read(&self.node.inner).map.get(key).and_then(|record| record.value.clone()))
+    }
+
+    pub async fn set(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.apply(vec![(key.to_string(), Some(value))]).await
+    }
+
+    pub async fn remove(&self, key: &str) -> Result<()> {
+        self.apply(vec![(key.to_string(), None)]).await
+    }
+
+    /// Lists the live keys starting with `prefix`, in order
+    pub async fn keys(&self, prefix: &str) -> Vec<String> {
+        read(&self.node.inner).map.iter()
+            .filter(|(key, record)| key.starts_with(prefix) && record.value.is_some())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Keeps `route` in step with the `route/` keys, so registrations reach every relay
+    ///
+    /// Entries already in `route` that the replica does not hold are registered.
+    pub async fn attach(&self, route: Route) -> Result<()> {
+        for (name, entry) in route.list().await? {
+            if self.get(&format!("{}{}", ROUTES, name)).await?.is_none() {
+                self.register(&name, &entry).await?;
+            }
+        }
+        let current: Vec<_> = read(&self.node.inner).map.iter()
+            .filter(|(key, _)| key.starts_with(ROUTES))
+            .map(|(key, record)| (key.clone(), record.clone()))
+            .collect();
+        *self.node.route.write().unwrap_or_else(|e| e.into_inner()) = Some(route);
+        self.node.publish(&current).await;
+        Ok(())
+    }
+
+    /// Registers a route entry on every member
+    pub async fn register(&self, name: &str, entry: &Entry) -> Result<()> {
+        let value = serde_json::to_vec(entry).map_err(|e| Error::Store(e.to_string()))?;
+        self.set(&format!("{}{}", ROUTES, name), value).await
+    }
+
+    /// Removes a route entry from every member
+    pub async fn unregister(&self, name: &str) -> Result<()> {
+        self.remove(&format!("{}{}", ROUTES, name)).await
+    }
+
+    /// Runs an anti-entropy round with every peer now, returning how many were reached
+    pub async fn sync(&self) -> usize {
+        reconcile_all(&self.node).await
+    }
+
+    /// Drops tombstones older than the horizon, returning how many; also run every round
+    pub fn collect(&self) -> usize {
+        write(&self.node.inner).collect()
+    }
+
+    /// Stamps and applies local writes, then queues them for the peers
+    async fn apply(&self, changes: Vec<(String, Option<Vec<u8>>)>) -> Result<()> {
+        let entries = {
+            let mut inner = write(&self.node.inner);
+            let entries: Vec<_> = changes.into_iter()
+                .map(|(key, value)| (key, Record { value, time: inner.tick(), node: self.node.settings.name.clone() }))
+                .collect();
+
+            // Refuse writes too large to replicate before any is applied
+            self.node.pages(entries.clone())?;
+            for (key, record) in &entries {
+                inner.map.insert(key.clone(), record.clone());
+            }
+            entries
+        };
+        self.node.publish(&entries).await;
+
+        for peer in self.peers() {
+            if self.node.enqueue(&peer, &entries) {
+                let node = self.node.clone();
+                tokio::spawn(async move { push(&node, &peer).await });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Linkable for Replica {
+    /// Starts listening for peers and the periodic anti-entropy rounds
+    async fn start(&mut self) -> Result<()> {
+        let listener = TcpListener::bind(&self.addr)
+            .await
+            .map_err(|e| Error::io(format!("bind {}", self.addr), e))?;
+        let addr = listener.local_addr().map_err(|e| Error::io("local address", e))?;
+
+        let node = Arc::downgrade(&self.node);
+        let accept = tokio::spawn(async move {
+            loop {
+                let (stream, remote) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Transient errors such as running out of descriptors must not spin the loop
+                        tracing::debug!("replica accept failed: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let Some(node) = node.upgrade() else {
+                    break;
+                };
+                tokio::spawn(async move {
+                    let socket = Socket::wrap(stream, node.settings.clone());
+                    match serve(&node, socket, remote).await {
+                        Ok(()) => {}
+                        Err(Error::Denied(reason)) => tracing::warn!("replica refused {}: {}", remote, reason),
+                        Err(e) => tracing::debug!("replica peer {} dropped: {}", remote, e),
+                    }
+                });
+            }
+        });
+
+        let node = Arc::downgrade(&self.node);
+        let interval = self.interval;
+        let rounds = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(interval);
+            timer.tick().await;
+            loop {
+                timer.tick().await;
+                let Some(node) = node.upgrade() else {
+                    break;
+                };
+                let collected = write(&node.inner).collect();
+                if collected > 0 {
+                    tracing::debug!("replica collected {} tombstones", collected);
+                }
+                reconcile_all(&node).await;
+            }
+        });
+
+        *self.node.bound.lock().unwrap_or_else(|e| e.into_inner()) = Some(addr);
+        let old = std::mem::replace(&mut *self.tasks.lock().unwrap_or_else(|e| e.into_inner()), vec![accept, rounds]);
+        for task in old {
+            task.abort();
+        }
+        self.state.set_mode(Mode::Ready).await
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        for task in self.tasks.lock().unwrap_or_else(|e| e.into_inner()).drain(..) {
+            task.abort();
+        }
+        *self.node.bound.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.state.set_mode(Mode::Close).await
+    }
+
+    async fn state(&self) -> Result<Mode> {
+        self.state.mode().await
+    }
+}
+
+#[async_trait]
+impl Storable for Replica {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Replica::get(self, key).await
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.set(key, value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.remove(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.keys(prefix).await)
+    }
+
+    /// Applies the operations with one push to the peers
+    async fn batch(&self, ops: Vec<Op>) -> Result<()> {
+        self.apply(ops.into_iter()
+            .map(|op| match op {
+                Op::Put(key, value) => (key, Some(value)),
+                Op::Delete(key) => (key, None),
+            })
+            .collect())
+            .await
+    }
+}
+
+/// Answers one peer connection until the peer closes it
+async fn serve(node: &Node, mut socket: Socket, remote: SocketAddr) -> Result<()> {
+    socket.start().await?;
+    let mut buf = vec![0u8; node.settings.size];
+    match node.receive(&mut socket, &mut buf).await? {
+        Message::Hello { port } if node.member(SocketAddr::new(remote.ip(), port)).await => {}
+        Message::Hello { port } => return Err(Error::Denied(format!("port {} is not a configured member", port))),
+        other => return Err(Error::Protocol(format!("expected hello, got {:?}", other))),
+    }
+    loop {
+        let message = match node.receive(&mut socket, &mut buf).await {
+            Ok(message) => message,
+            Err(Error::Closed(_)) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        match message {
+            Message::Update { entries } => node.merge(entries).await,
+            Message::Full { entries, last } => {
+                node.merge(entries).await;
+                if last {
+                    node.send(&mut socket, &Message::Same).await?;
+                }
+            }
+            Message::Sync { digest } => {
+                let full = {
+                    let inner = read(&node.inner);
+                    (inner.digest()? != digest).then(|| inner.entries())
+                };
+                match full {
+                    Some(entries) => node.send_full(&mut socket, entries).await?,
+                    None => node.send(&mut socket, &Message::Same).await?,
+                }
+            }
+            Message::Same | Message::Hello { .. } => {}
+        }
+    }
+}
+
+/// Reconciles with every peer concurrently, returning how many were reached
+async fn reconcile_all(node: &Node) -> usize {
+    let rounds = node.peers().into_iter().map(|peer| async move {
+        let result = reconcile(node, &peer).await;
+        if let Err(e) = &result {
+            tracing::debug!("replica sync with {} failed: {}", peer, e);
+        }
+        result.is_ok()
+    });
+    join_all(rounds).await.into_iter().filter(|ok| *ok).count()
+}
+
+/// One anti-entropy round: if the digests differ, both sides end up with the merged contents
+async fn reconcile(node: &Node, peer: &str) -> Result<()> {
+    let mut socket = node.open(peer).await?;
+    let digest = read(&node.inner).digest()?;
+    node.send(&mut socket, &Message::Sync { digest }).await?;
+
+    let mut buf = vec![0u8; node.settings.size];
+    loop {
+        match node.receive(&mut socket, &mut buf).await? {
+            Message::Same => return socket.stop().await,
+            Message::Full { entries, last } => {
+                node.merge(entries).await;
+                if last {
+                    break;
+                }
+            }
+            other => return Err(Error::Protocol(format!("unexpected replica reply {:?}", other))),
+        }
+    }
+
+    let merged = read(&node.inner).entries();
+    node.send_full(&mut socket, merged).await?;
+    // Wait for the peer to merge too, so both sides hold the same contents on return
+    if !matches!(node.receive(&mut socket, &mut buf).await?, Message::Same) {
+        return Err(Error::Protocol("peer did not confirm the merge".into()));
+    }
+    socket.stop().await
+}
+
+/// Pushes the writes queued for `peer` over one connection until none are left
+///
+/// If the push fails the queued writes are dropped; the peer catches up in the next round.
+async fn push(node: &Node, peer: &str) {
+    let mut socket = None;
+    loop {
+        let entries = node.dequeue(peer);
+        if entries.is_empty() {
+            break;
+        }
+        let sent = async {
+            let socket = match &mut socket {
+                Some(socket) => socket,
+                None => socket.insert(node.open(peer).await?),
+            };
+            for entries in node.pages(entries)? {
+                node.send(socket, &Message::Update { entries }).await?;
+            }
+            Ok::<(), Error>(())
+        };
+        if let Err(e) = sent.await {
+            tracing::debug!("replica push to {} failed: {}", peer, e);
+            node.abandon(peer);
+            break;
+        }
+    }
+    if let Some(mut socket) = socket {
+        let _ = socket.stop().await;
+    }
+}
+
+fn read(inner: &RwLock<Inner>) -> RwLockReadGuard<'_, Inner> {
+    inner.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write(inner: &RwLock<Inner>) -> RwLockWriteGuard<'_, Inner> {
+    inner.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Wall clock in milliseconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::error::{Error, Result};
+use crate::core::export::Registry;
+use crate::core::link::Op;
+use crate::core::state::Measure;
+use crate::core::State;
+use crate::net::route::{Entry, Route};
+use crate::store::{file, Data, File};
+
+/// Archive format written by `Snapshot::encode`
+pub const VERSION: u32 = 1;
+
+/// First line of an archive, describing the body after it
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    /// SHA-256 of the body as lowercase hex
+    digest: String,
+}
+
+/// Routes, stored data and measures of a node, for backups and migrations
+///
+/// An archive is a JSON header line holding the format version and a digest of the
+/// body, followed by the snapshot itself as JSON. `decode` rejects other versions,
+/// digests that do not match and contents that fail `validate`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// When the snapshot was taken, in Unix milliseconds
+    pub created: i64,
+    pub routes: BTreeMap<String, Entry>,
+    /// Values are stored as hex
+    #[serde(with = "hex")]
+    pub data: BTreeMap<String, Vec<u8>>,
+    /// Measures of every state in the registry, by name
+    pub measures: BTreeMap<String, Measure>,
+}
+
+impl Snapshot {
+    /// Copies the current routes, data and measures
+    pub async fn capture(route: &Route, data: &Data, registry: &Registry) -> Result<Self> {
+        let mut measures = BTreeMap::new();
+        for (name, state) in registry.list().await {
+            measures.insert(name, state.measure().await?);
+        }
+        Ok(Self {
+            created: chrono::Utc::now().timestamp_millis(),
+            routes: route.list().await?.into_iter().collect(),
+            data: data.entries("").await.into_iter().collect(),
+            measures,
+        })
+    }
+
+    /// Replaces the routes and data with the snapshot and restores the measures
+    ///
+    /// States missing from `registry` are registered; states not in the snapshot are left alone.
+    /// Nothing is changed if the snapshot fails `validate`. The data is replaced before the routes.
+    pub async fn restore(&self, route: &Route, data: &Data, registry: &Registry) -> Result<()> {
+        self.validate()?;
+
+        // One batch, so the data is either fully replaced or untouched
+        let previous: BTreeMap<String, Vec<u8>> = data.entries("").await.into_iter().collect();
+        data.batch(replace(&previous, &self.data)).await?;
+        route.load(self.routes.clone().into_iter().collect()).await?;
+
+        for (name, measure) in &self.measures {
+            let state = match registry.get(name).await {
+                Some(state) => state,
+                None => {
+                    let state = State::new();
+                    registry.register(name, state.clone()).await;
+                    state
+                }
+            };
+            state.restore(measure.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the contents can be restored; routes and data are taken as `Route` and `Data` accept them
+    pub fn validate(&self) -> Result<()> {
+        for (name, measure) in &self.measures {
+            let latency = &measure.latency;
+            if latency.counts.len() != latency.bounds.len() + 1 {
+                return Err(invalid("measure", format!("latency histogram of {} has {} counts for {} bounds", name, latency.counts.len(), latency.bounds.len())));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let body = serde_json::to_vec(self).map_err(|e| Error::Store(e.to_string()))?;
+        let header = Header {
+            version: VERSION,
+            digest: file::hex(&Sha256::digest(&body)),
+        };
+        let mut archive = serde_json::to_vec(&header).map_err(|e| Error::Store(e.to_string()))?;
+        archive.push(b'\n');
+        archive.extend(body);
+        Ok(archive)
+    }
+
+    /// Reads an archive written by `encode`, checking its version, digest and contents
+    pub fn decode(archive: &[u8]) -> Result<Self> {
+        let split = archive.iter().position(|b| *b == b'\n')
+            .ok_or_else(|| invalid("format", "missing archive header".into()))?;
+        let (header, body) = (&archive[..split], &archive[split + 1..]);
+        let header: Header = serde_json::from_slice(header)
+            .map_err(|e| invalid("format", format!("bad archive header: {}", e)))?;
+        if header.version != VERSION {
+            return Err(invalid("version", format!("unsupported archive version {}, expected {}", header.version, VERSION)));
+        }
+        if file::hex(&Sha256::digest(body)) != header.digest {
+            return Err(invalid("digest", "archive body does not match its digest".into()));
+        }
+
+        let snapshot: Snapshot = serde_json::from_slice(body)
+            .map_err(|e| invalid("format", format!("bad archive body: {}", e)))?;
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    /// Writes the archive atomically to `path`
+    pub async fn save(&self, file: &File, path: &str) -> Result<()> {
+        file.write(path, &self.encode()?).await
+    }
+
+    pub async fn load(file: &File, path: &str) -> Result<Self> {
+        Self::decode(&file.read(path).await?)
+    }
+}
+
+/// Batch turning the entries `from` into `to`
+fn replace(from: &BTreeMap<String, Vec<u8>>, to: &BTreeMap<String, Vec<u8>>) -> Vec<Op> {
+    let mut ops: Vec<Op> = from.keys()
+        .filter(|key| !to.contains_key(*key))
+        .cloned()
+        .map(Op::Delete)
+        .collect();
+    ops.extend(to.iter().map(|(key, value)| Op::Put(key.clone(), value.clone())));
+    ops
+}
+
+fn invalid(rule: &str, reason: String) -> Error {
+    Error::Invalid { rule: rule.into(), reason }
+}
+
+/// Serializes binary values as hex strings, which keeps archives readable and compact
+mod hex {
+    use std::collections::BTreeMap;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error as _;
+
+    use crate::store::file::hex as encode;
+
+    fn decode(text: &str) -> Option<Vec<u8>> {
+        if !text.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..text.len())
+            .step_by(2)
+            .map(|i| text.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+            .collect()
+    }
+
+    pub fn serialize<S: Serializer>(data: &BTreeMap<String, Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(data.iter().map(|(key, value)| (key, encode(value))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, Vec<u8>>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| match decode(&value) {
+                Some(value) => Ok((key, value)),
+                None => Err(D::Error::custom(format!("value of {} is not hex", key))),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::core::error::{Error, Result};
+use crate::core::link::Storable;
+use crate::guard::Crypt;
+use crate::store::File;
+
+/// Marks a file written by `Vault`
+const MAGIC: &[u8; 4] = b"LNKV";
+
+/// Format version following the magic
+const VERSION: u8 = 1;
+
+/// Magic and version before the sealed content
+const HEADER: usize = MAGIC.len() + 1;
+
+/// Files encrypted at rest with a `Crypt`, read and written like a `File`
+///
+/// Each file holds `magic | version | suite | key id | nonce | ciphertext`, with a
+/// fresh random nonce per write. The header and the file path are authenticated, so
+/// a file that was altered, or moved to another path, fails to decrypt with
+/// `Error::Decrypt`. Files sealed with an older key of the ring stay readable until
+/// that key is retired; `rewrap` moves them to the current one.
+#[derive(Clone)]
+pub struct Vault {
+    file: File,
+    crypt: Arc<Crypt>,
+}
+
+impl Vault {
+    pub fn new(file: File, crypt: Arc<Crypt>) -> Self {
+        Self { file, crypt }
+    }
+
+    /// Underlying store, holding the encrypted files
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Encrypts `data` and writes it atomically to `path`
+    pub async fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        let sealed = self.crypt.seal(data, &context(path))?;
+        let mut content = Vec::with_capacity(HEADER + sealed.len());
+        content.extend_from_slice(MAGIC);
+        content.push(VERSION);
+        content.extend(sealed);
+        self.file.write(path, &content).await
+    }
+
+    /// Reads and decrypts `path`
+    pub async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let content = self.file.read(path).await?;
+        self.crypt.open(sealed(path, &content)?, &context(path))
+    }
+
+    /// Id of the key `path` was encrypted with, read from its header
+    pub async fn key(&self, path: &str) -> Result<u32> {
+        let content = self.file.read(path).await?;
+        Crypt::key_id(sealed(path, &content)?)
+            .ok_or_else(|| Error::Decrypt(format!("{} has a truncated header", path)))
+    }
+
+    /// Re-encrypts `path` with the current key if it was sealed with another, returning whether it did
+    pub async fn rewrap(&self, path: &str) -> Result<bool> {
+        if self.key(path).await? == self.crypt.ring().current()?.id {
+            return Ok(false);
+        }
+        let data = self.read(path).await?;
+        self.write(path, &data).await?;
+        Ok(true)
+    }
+
+    pub async fn remove(&self, path: &str) -> Result<()> {
+        self.file.remove(path).await
+    }
+
+    pub async fn exists(&self, path: &str) -> Result<bool> {
+        self.file.exists(path).await
+    }
+
+    /// Paths of every file below `dir`, as `File::walk` returns them
+    pub async fn walk(&self, dir: &str) -> Result<Vec<String>> {
+        self.file.walk(dir).await
+    }
+}
+
+#[async_trait]
+impl Storable for Vault {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.read(key).await {
+            Ok(data) => Ok(Some(data)),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.write(key, &value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        Storable::delete(&self.file, key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Vault::exists(self, key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Storable::list(&self.file, prefix).await
+    }
+}
+
+/// Associated data binding the content to its header and path
+fn context(path: &str) -> Vec<u8> {
+    let mut context = Vec::with_capacity(HEADER + path.len());
+    context.extend_from_slice(MAGIC);
+    context.push(VERSION);
+    context.extend_from_slice(path.as_bytes());
+    context
+}
+
+/// Checks the header of a vault file and returns the sealed content after it
+fn sealed<'a>(path: &str, content: &'a [u8]) -> Result<&'a [u8]> {
+    match content.split_at_checked(HEADER) {
+        Some((header, sealed)) if header[..MAGIC.len()] == MAGIC[..] && header[MAGIC.len()] == VERSION => Ok(sealed),
+        _ => Err(Error::Decrypt(format!("{} is not an encrypted file", path))),
+    }
+}
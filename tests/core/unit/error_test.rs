@@ -0,0 +1,27 @@
+use std::error::Error as _;
+use std::io;
+use link::core::error::Error;
+
+#[test]
+fn test_error_retryable() {
+    assert!(Error::Net("reset".into()).is_retryable());
+    assert!(Error::Timeout("read".into()).is_retryable());
+    assert!(Error::Closed("socket is closed".into()).is_retryable());
+    assert!(Error::io("connect", io::Error::from(io::ErrorKind::ConnectionRefused)).is_retryable());
+
+    assert!(!Error::NotFound("route".into()).is_retryable());
+    assert!(!Error::Capacity("group is full".into()).is_retryable());
+    assert!(!Error::Auth("invalid signature".into()).is_retryable());
+    assert!(!Error::Decrypt("aead::Error".into()).is_retryable());
+    assert!(!Error::Protocol("unknown control frame".into()).is_retryable());
+}
+
+#[test]
+fn test_error_source() {
+    let error = Error::io("connect", io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+    assert_eq!(error.kind(), "io");
+    assert_eq!(error.to_string(), "I/O error on connect: refused");
+
+    let source = error.source().and_then(|e| e.downcast_ref::<io::Error>()).unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::ConnectionRefused);
+}
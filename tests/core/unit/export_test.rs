@@ -0,0 +1,75 @@
+use link::core::export::{Export, Registry};
+use link::core::link::Linkable;
+use link::core::state::{Mode, State};
+use link::core::error::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn test_registry_render() {
+    let registry = Registry::new();
+    let state = State::new();
+    state.set_mode(Mode::Ready).await.unwrap();
+    state.record_send(128).await.unwrap();
+    state.record_fault(&Error::Timeout("idle".into())).await.unwrap();
+    registry.register("peer \"a\"", state).await;
+
+    let text = registry.render().await.unwrap();
+    assert!(text.contains("# TYPE link_send_bytes_total counter"));
+    assert!(text.contains("link_send_bytes_total{state=\"peer \\\"a\\\"\"} 128"));
+    assert!(text.contains("link_send_frames_total{state=\"peer \\\"a\\\"\"} 1"));
+    assert!(text.contains("link_mode{state=\"peer \\\"a\\\"\",mode=\"Ready\"} 1"));
+    assert!(text.contains("link_errors_by_kind_total{state=\"peer \\\"a\\\"\",kind=\"timeout\"} 1"));
+    assert!(text.contains("link_latency_seconds_bucket{state=\"peer \\\"a\\\"\",le=\"+Inf\"} 0"));
+
+    registry.unregister("peer \"a\"").await;
+    assert!(!registry.render().await.unwrap().contains("peer"));
+}
+
+#[tokio::test]
+async fn test_export_endpoint() {
+    let registry = Registry::new();
+    let state = State::new();
+    state.record_receive(64).await.unwrap();
+    registry.register("socket", state).await;
+
+    let mut export = Export::new(registry, "127.0.0.1:0");
+    export.start().await.unwrap();
+    assert!(matches!(export.state().await.unwrap(), Mode::Ready));
+    let addr = export.local_addr().unwrap();
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    let response = get(addr, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("link_receive_bytes_total{state=\"socket\"} 64"));
+
+    let response = get(addr, "/other").await;
+    assert!(response.starts_with("HTTP/1.1 404"));
+
+    export.stop().await.unwrap();
+    assert!(matches!(export.state().await.unwrap(), Mode::Close));
+}
+
+#[tokio::test]
+async fn test_export_idle_client() {
+    let mut export = Export::new(Registry::new(), "127.0.0.1:0");
+    export.set_read_timeout(std::time::Duration::from_millis(50));
+    export.start().await.unwrap();
+
+    // A client that never finishes its request is disconnected
+    let mut stream = TcpStream::connect(export.local_addr().unwrap()).await.unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.1\r\n").await.unwrap();
+    let mut response = Vec::new();
+    let read = tokio::time::timeout(std::time::Duration::from_secs(5), stream.read_to_end(&mut response)).await;
+    assert!(read.is_ok(), "the idle connection was kept open");
+    assert!(response.is_empty());
+    export.stop().await.unwrap();
+}
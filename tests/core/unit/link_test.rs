@@ -53,12 +53,12 @@ async fn test_link_size_limits() {
     // Test send with too large data
     let data = vec![0; 8];
     let result = link.send(&data).await;
-    assert!(matches!(result, Err(Error::Net(_))));
+    assert!(matches!(result, Err(Error::Capacity(_))));
     
     // Test receive with too large buffer
     let mut buf = vec![0; 8];
     let result = link.receive(&mut buf).await;
-    assert!(matches!(result, Err(Error::Net(_))));
+    assert!(matches!(result, Err(Error::Capacity(_))));
 }
 
 #[tokio::test]
@@ -81,4 +81,53 @@ async fn test_link_handler() {
     let data = vec![1, 2, 3, 4];
     let sent = link.send(&data).await.unwrap();
     assert_eq!(sent, data.len());
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_link_deadline() {
+    use std::time::Duration;
+    use link::core::link::Deadline;
+
+    // Deadlines are derived from `wait`
+    let settings = Settings {
+        wait: 5,
+        ..Settings::default()
+    };
+    let deadline = settings.deadline();
+    assert_eq!(deadline.connect, Duration::from_secs(5));
+    assert_eq!(deadline.read, Duration::from_secs(5));
+    assert_eq!(deadline.write, Duration::from_secs(5));
+    assert_eq!(deadline.idle, Duration::from_secs(10));
+
+    // An idle link is closed on the next operation
+    let mut link = Link::new(settings);
+    link.set_deadline(Deadline {
+        idle: Duration::from_millis(20),
+        ..deadline
+    });
+    link.start().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let result = link.send(&[1, 2, 3]).await;
+    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(matches!(link.state().await.unwrap(), Mode::Close));
+}
+
+#[tokio::test]
+async fn test_link_active() {
+    use link::core::state::State;
+
+    // Active links accept data the same as ready ones
+    let state = State::new();
+    state.set_mode(Mode::Ready).await.unwrap();
+    state.set_mode(Mode::Active).await.unwrap();
+    assert!(matches!(state.mode().await.unwrap(), Mode::Active));
+
+    let mut link = Link::new(Settings::default());
+    link.start().await.unwrap();
+    link.set_mode(Mode::Active).await.unwrap();
+    let sent = link.send(&[1, 2, 3]).await.unwrap();
+    assert_eq!(sent, 3);
+    let mut buf = vec![0; 3];
+    assert_eq!(link.receive(&mut buf).await.unwrap(), 3);
+}
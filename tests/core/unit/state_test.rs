@@ -77,4 +77,123 @@ async fn test_state_concurrent_access() {
     let measure = state.measure().await.unwrap();
     assert_eq!(measure.send, 100);
     assert_eq!(measure.receive, 100);
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_state_rtt() {
+    use std::time::Duration;
+
+    let state = State::new();
+
+    // First sample seeds the estimate
+    state.record_rtt(Duration::from_millis(80)).await.unwrap();
+    let measure = state.measure().await.unwrap();
+    assert_eq!(measure.rtt, Some(Duration::from_millis(80)));
+    assert_eq!(measure.jitter, Some(Duration::from_millis(40)));
+
+    // Later samples are smoothed
+    state.record_rtt(Duration::from_millis(160)).await.unwrap();
+    let measure = state.measure().await.unwrap();
+    assert_eq!(measure.rtt, Some(Duration::from_millis(90)));
+    assert_eq!(measure.jitter, Some(Duration::from_millis(50)));
+}
+
+#[tokio::test]
+async fn test_state_transitions() {
+    use link::core::error::Error;
+
+    let state = State::new();
+
+    // Illegal transitions are rejected
+    let result = state.set_mode(Mode::Active).await;
+    assert!(matches!(result, Err(Error::State(_))));
+    assert!(matches!(state.mode().await.unwrap(), Mode::Init));
+
+    // Setting the current mode again is a no-op
+    state.set_mode(Mode::Init).await.unwrap();
+    assert!(state.history().await.unwrap().is_empty());
+
+    state.set_mode(Mode::Ready).await.unwrap();
+    state.set_mode(Mode::Close).await.unwrap();
+
+    // Close is terminal
+    let result = state.set_mode(Mode::Ready).await;
+    assert!(matches!(result, Err(Error::State(_))));
+
+    // Each transition is timestamped in order
+    let history = state.history().await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!((history[0].from, history[0].to), (Mode::Init, Mode::Ready));
+    assert_eq!((history[1].from, history[1].to), (Mode::Ready, Mode::Close));
+    assert!(history[0].at <= history[1].at);
+}
+
+#[tokio::test]
+async fn test_state_until() {
+    use std::time::Duration;
+    use tokio::time::{sleep, timeout};
+
+    let state = State::new();
+    let mut receiver = state.subscribe();
+
+    // Wait for Ready from another task
+    let waiter = tokio::spawn({
+        let state = state.clone();
+        async move { state.until(Mode::Ready).await }
+    });
+    sleep(Duration::from_millis(10)).await;
+    state.set_mode(Mode::Ready).await.unwrap();
+    timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap().unwrap();
+
+    receiver.changed().await.unwrap();
+    assert_eq!(*receiver.borrow(), Mode::Ready);
+
+    // Waiting for a mode that can no longer be reached fails on close
+    let waiter = tokio::spawn({
+        let state = state.clone();
+        async move { state.until(Mode::Active).await }
+    });
+    sleep(Duration::from_millis(10)).await;
+    state.set_mode(Mode::Close).await.unwrap();
+    let result = timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_state_rich_measure() {
+    use std::time::Duration;
+    use link::core::error::Error;
+
+    let state = State::new();
+    assert!(state.measure().await.unwrap().uptime().is_none());
+
+    // Frame counts and throughput follow each record
+    state.record_send(1000).await.unwrap();
+    state.record_send(1000).await.unwrap();
+    state.record_receive(500).await.unwrap();
+    let measure = state.measure().await.unwrap();
+    assert_eq!(measure.frames.send, 2);
+    assert_eq!(measure.frames.receive, 1);
+    assert!(measure.send_rate.recent > 0.0);
+    assert!(measure.send_rate.recent >= measure.send_rate.minute);
+    assert!(measure.receive_rate.recent > 0.0);
+    assert!(measure.started().is_some());
+    assert!(measure.uptime().is_some());
+
+    // Errors are counted per kind
+    state.record_fault(&Error::Timeout("read".into())).await.unwrap();
+    state.record_fault(&Error::Timeout("write".into())).await.unwrap();
+    state.record_fault(&Error::Net("reset".into())).await.unwrap();
+    let measure = state.measure().await.unwrap();
+    assert_eq!(measure.error, 3);
+    assert_eq!(measure.errors.get("timeout"), Some(&2));
+    assert_eq!(measure.errors.get("net"), Some(&1));
+
+    // Latency samples land in the matching bucket
+    state.record_latency(Duration::from_micros(500)).await.unwrap();
+    state.record_latency(Duration::from_secs(30)).await.unwrap();
+    let latency = state.measure().await.unwrap().latency;
+    assert_eq!(latency.count, 2);
+    assert_eq!(latency.counts[0], 1);
+    assert_eq!(*latency.counts.last().unwrap(), 1);
+}
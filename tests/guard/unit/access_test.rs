@@ -0,0 +1,68 @@
+use link::core::error::Error;
+use link::guard::Policy;
+use link::guard::access::{Config, Direction, Effect, Grant, Request};
+
+fn request(peer: &str, route: &str, port: u16, direction: Direction) -> Request {
+    Request {
+        peer: peer.into(),
+        route: route.into(),
+        port,
+        direction,
+    }
+}
+
+#[test]
+fn test_policy_grants() {
+    let mut policy = Policy::new();
+    policy.add_group("ops", &["alice", "bob"]);
+    policy.add_grant(Grant::allow("@ops", "db-*"));
+    policy.add_grant(Grant {
+        ports: vec![(8000, 8099)],
+        direction: Direction::Inbound,
+        ..Grant::allow("carol", "web")
+    });
+    policy.add_grant(Grant::deny("bob", "db-primary"));
+
+    // Deny by default
+    assert!(matches!(policy.check(request("mallory", "web", 8080, Direction::Inbound)), Err(Error::Denied(_))));
+
+    // Groups and prefixes
+    policy.check(request("alice", "db-replica", 5432, Direction::Outbound)).unwrap();
+    policy.check(request("bob", "db-replica", 5432, Direction::Outbound)).unwrap();
+
+    // An explicit deny wins over a matching allow
+    assert!(policy.check(request("bob", "db-primary", 5432, Direction::Outbound)).is_err());
+
+    // Ports and directions
+    policy.check(request("carol", "web", 8080, Direction::Inbound)).unwrap();
+    assert!(policy.check(request("carol", "web", 9000, Direction::Inbound)).is_err());
+    assert!(policy.check(request("carol", "web", 8080, Direction::Outbound)).is_err());
+
+    // Every evaluation is logged with the deciding grant
+    let decisions = policy.decisions();
+    assert_eq!(decisions.len(), 7);
+    assert_eq!(decisions[0].grant, None);
+    assert_eq!(decisions[0].effect, Effect::Deny);
+    assert_eq!(decisions[3].grant, Some(2));
+    assert_eq!(decisions[4].grant, Some(1));
+}
+
+#[tokio::test]
+async fn test_policy_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("policy.json");
+    std::fs::write(&path, r#"{
+        "default": "allow",
+        "groups": {"guests": ["eve"]},
+        "grants": [
+            {"subjects": ["@guests"], "routes": ["*"], "effect": "deny"}
+        ]
+    }"#).unwrap();
+
+    let policy = Policy::load(&path).await.unwrap();
+    policy.check(request("dave", "anything", 1, Direction::Outbound)).unwrap();
+    assert!(policy.check(request("eve", "anything", 1, Direction::Outbound)).is_err());
+
+    let config: Config = serde_json::from_str(r#"{"grants": []}"#).unwrap();
+    assert_eq!(config.default, Effect::Deny);
+}
@@ -1,3 +1,4 @@
+use link::core::error::Error;
 use link::core::link::Guardable;
 use link::guard::Auth;
 
@@ -12,7 +13,7 @@ async fn test_auth_protection() {
     
     // Verify protected data format
     assert!(protected.len() > data.len());
-    assert_eq!(protected.len(), data.len() + 32); // HMAC-SHA256 is 32 bytes
+    assert_eq!(protected.len(), data.len() + 32 + 4 + 24); // HMAC-SHA256 is 32 bytes, key id 4, replay header 24
     
     // Test exposing protected data
     let exposed = auth.expose(&protected).await.unwrap();
@@ -56,9 +57,45 @@ async fn test_auth_empty_data() {
     
     // Test protecting empty data
     let protected = auth.protect(&[]).await.unwrap();
-    assert_eq!(protected.len(), 32); // Only signature
+    assert_eq!(protected.len(), 60); // Only signature, key id and replay header
     
     // Test exposing empty data
     let exposed = auth.expose(&protected).await.unwrap();
     assert!(exposed.is_empty());
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_auth_replay() {
+    let sender = Auth::new(b"test_key");
+    let receiver = Auth::new(b"test_key");
+
+    let first = sender.protect(b"first").await.unwrap();
+    let second = sender.protect(b"second").await.unwrap();
+
+    // Frames may arrive out of order but only once
+    assert_eq!(receiver.expose(&second).await.unwrap(), b"second");
+    assert_eq!(receiver.expose(&first).await.unwrap(), b"first");
+    let result = receiver.expose(&first).await;
+    assert!(matches!(result, Err(Error::Auth(_))));
+
+    // Tampering with the sequence number breaks the signature
+    let mut forged = sender.protect(b"third").await.unwrap();
+    forged[43] ^= 1;
+    let result = receiver.expose(&forged).await;
+    assert!(matches!(result, Err(Error::Auth(_))));
+}
+
+#[tokio::test]
+async fn test_auth_sender_restart() {
+    let receiver = Auth::new(b"test_key");
+    let sender = Auth::new(b"test_key");
+    for _ in 0..100 {
+        let frame = sender.protect(b"before").await.unwrap();
+        receiver.expose(&frame).await.unwrap();
+    }
+
+    // A restarted sender numbers its frames from the start again and is still accepted
+    let restarted = Auth::new(b"test_key");
+    let frame = restarted.protect(b"after").await.unwrap();
+    assert_eq!(receiver.expose(&frame).await.unwrap(), b"after");
+}
@@ -1,5 +1,7 @@
-use link::core::link::Guardable;
+use link::core::error::Error;
+use link::core::link::{Guardable, Handler};
 use link::guard::Check;
+use link::guard::check::{Pattern, Rule, Schema};
 
 #[tokio::test]
 async fn test_check_no_rules() {
@@ -95,4 +97,86 @@ async fn test_check_complex_rule() {
     let no_test = b"this is a message";
     let result = check.protect(no_test).await;
     assert!(result.is_err());
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_check_builtin_rules() {
+    let mut check = Check::new();
+    check.add(Rule::Min { len: 2 }).unwrap();
+    check.add(Rule::Max { len: 64 }).unwrap();
+    check.add(Rule::Utf8).unwrap();
+    check.add(Rule::Regex { pattern: "^[a-z ]+$".into() }).unwrap();
+    check.add(Rule::Deny { patterns: vec![Pattern::Text("drop".into())] }).unwrap();
+
+    check.validate(b"hello world").unwrap();
+
+    // Each failure names the rule that rejected the data
+    let rule = |result: Result<(), Error>| match result {
+        Err(Error::Invalid { rule, .. }) => rule,
+        other => panic!("unexpected {:?}", other),
+    };
+    assert_eq!(rule(check.validate(b"a")), "min");
+    assert_eq!(rule(check.validate(&[b'a'; 65])), "max");
+    assert_eq!(rule(check.validate(&[0xFF, 0xFE])), "utf8");
+    assert_eq!(rule(check.validate(b"Hello")), "regex");
+    assert_eq!(rule(check.validate(b"drop table")), "deny");
+
+    // Every failing rule can be listed at once
+    let violations = check.report(b"X");
+    let names: Vec<_> = violations.iter().map(|v| v.rule.as_str()).collect();
+    assert_eq!(names, ["min", "regex"]);
+
+    assert!(matches!(check.add(Rule::Regex { pattern: "(".into() }), Err(Error::Invalid { .. })));
+}
+
+#[tokio::test]
+async fn test_check_json_and_magic() {
+    let schema: Schema = serde_json::from_str(r#"{
+        "type": "object",
+        "required": ["id"],
+        "properties": {
+            "id": {"type": "integer", "minimum": 1},
+            "tags": {"type": "array", "items": {"type": "string", "maxLength": 3}}
+        }
+    }"#).unwrap();
+    let mut json = Check::new();
+    json.add(Rule::Json { schema }).unwrap();
+    json.validate(br#"{"id": 7, "tags": ["a", "bcd"]}"#).unwrap();
+    assert!(json.validate(br#"{"tags": []}"#).is_err());
+    assert!(json.validate(br#"{"id": 0}"#).is_err());
+    assert!(json.validate(br#"{"id": 1, "tags": ["long"]}"#).is_err());
+    assert!(json.validate(b"not json").is_err());
+
+    let mut magic = Check::new();
+    magic.add_named("images", Rule::Magic {
+        allow: vec![Pattern::Bytes(vec![0x89, b'P', b'N', b'G']), Pattern::Text("GIF8".into())],
+    }).unwrap();
+    magic.validate(b"\x89PNG\r\n").unwrap();
+    magic.validate(b"GIF89a").unwrap();
+    assert!(matches!(magic.validate(b"%PDF"), Err(Error::Invalid { rule, .. }) if rule == "images"));
+}
+
+#[tokio::test]
+async fn test_check_config_and_handler() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("check.json");
+    std::fs::write(&path, r#"{
+        "rules": [
+            {"type": "max", "len": 8},
+            {"name": "no-nul", "type": "deny", "patterns": [[0]]}
+        ]
+    }"#).unwrap();
+    let check = Check::load(&path).await.unwrap();
+
+    // The handler path enforces the configured rules
+    assert_eq!(check.handle(b"short").await.unwrap(), b"short");
+    assert!(matches!(check.handle(b"far too long").await, Err(Error::Invalid { rule, .. }) if rule == "max"));
+    assert!(matches!(check.handle(b"a\0b").await, Err(Error::Invalid { rule, .. }) if rule == "no-nul"));
+
+    std::fs::write(&path, r#"{"rules": [{"type": "unknown"}]}"#).unwrap();
+    assert!(Check::load(&path).await.is_err());
+
+    // An empty deny pattern would match everything and is refused up front
+    std::fs::write(&path, r#"{"rules": [{"name": "blank", "type": "deny", "patterns": ["drop", ""]}]}"#).unwrap();
+    assert!(matches!(Check::load(&path).await, Err(Error::Invalid { rule, .. }) if rule == "blank"));
+}
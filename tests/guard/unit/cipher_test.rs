@@ -0,0 +1,55 @@
+use link::core::error::Error;
+use link::core::link::Guardable;
+use link::guard::{Crypt, Role, Suite};
+
+#[tokio::test]
+async fn test_cipher_suites() {
+    let key = b"test_key_12345_test_key_12345_test_k";
+    for (suite, nonce) in [(Suite::ChaCha20Poly1305, 12), (Suite::Aes256Gcm, 12), (Suite::XChaCha20Poly1305, 24)] {
+        let sender = Crypt::new(key);
+        sender.set_suite(suite).unwrap();
+        let receiver = Crypt::new(key);
+
+        let protected = sender.protect(b"test message").await.unwrap();
+        assert_eq!(protected[0], suite.id());
+        assert_eq!(protected.len(), 1 + 4 + 24 + nonce + 12 + 16);
+        assert_eq!(receiver.expose(&protected).await.unwrap(), b"test message");
+    }
+}
+
+#[tokio::test]
+async fn test_cipher_restricted() {
+    let key = b"test_key_12345_test_key_12345_test_k";
+    let mut strict = Crypt::new(key);
+    strict.set_suites(&[Suite::Aes256Gcm]).unwrap();
+    assert_eq!(strict.suite(), Suite::Aes256Gcm);
+    assert!(matches!(strict.set_suite(Suite::ChaCha20Poly1305), Err(Error::Guard(_))));
+    assert!(matches!(strict.set_suites(&[]), Err(Error::Guard(_))));
+
+    // Frames under a suite that is not allowed are refused
+    let protected = Crypt::new(key).protect(b"data").await.unwrap();
+    let result = strict.expose(&protected).await;
+    assert!(matches!(result, Err(Error::Decrypt(_))));
+
+    let protected = strict.protect(b"data").await.unwrap();
+    assert_eq!(Crypt::new(key).expose(&protected).await.unwrap(), b"data");
+}
+
+#[tokio::test]
+async fn test_cipher_session() {
+    let key = b"test_key_12345_test_key_12345_test_k";
+    let sender = Crypt::new(key);
+    let receiver = Crypt::new(key).session(Suite::Aes256Gcm, Role::Responder).unwrap();
+
+    // A session refuses frames under any suite but the negotiated one
+    let protected = sender.protect(b"data").await.unwrap();
+    assert!(matches!(receiver.expose(&protected).await, Err(Error::Decrypt(_))));
+
+    let sender = sender.session(Suite::Aes256Gcm, Role::Initiator).unwrap();
+    let protected = sender.protect(b"data").await.unwrap();
+    assert_eq!(receiver.expose(&protected).await.unwrap(), b"data");
+
+    let mut strict = Crypt::new(key);
+    strict.set_suites(&[Suite::ChaCha20Poly1305]).unwrap();
+    assert!(matches!(strict.session(Suite::Aes256Gcm, Role::Initiator), Err(Error::Guard(_))));
+}
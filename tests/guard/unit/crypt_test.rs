@@ -1,5 +1,7 @@
+use link::core::error::Error;
 use link::core::link::Guardable;
-use link::guard::Crypt;
+use link::guard::{Auth, Crypt};
+use link::guard::derive::{self, Kdf, Phrase};
 
 #[tokio::test]
 async fn test_crypt_protection() {
@@ -12,7 +14,7 @@ async fn test_crypt_protection() {
     
     // Verify protected data format
     assert!(protected.len() > data.len());
-    assert_eq!(protected.len(), data.len() + 12 + 16); // 12 byte nonce + 16 byte tag
+    assert_eq!(protected.len(), data.len() + 1 + 4 + 24 + 12 + 16); // 1 byte suite + 4 byte key id + 24 byte header + 12 byte nonce + 16 byte tag
     
     // Test exposing protected data
     let exposed = crypt.expose(&protected).await.unwrap();
@@ -31,7 +33,7 @@ async fn test_crypt_invalid_data() {
     
     // Test corrupted data
     let mut protected = crypt.protect(b"test").await.unwrap();
-    protected[12] ^= 1; // Flip one bit in ciphertext
+    protected[33] ^= 1; // Flip one bit in ciphertext
     let result = crypt.expose(&protected).await;
     assert!(result.is_err());
 }
@@ -59,7 +61,7 @@ async fn test_crypt_empty_data() {
     
     // Test protecting empty data
     let protected = crypt.protect(&[]).await.unwrap();
-    assert_eq!(protected.len(), 28); // 12 byte nonce + 16 byte tag
+    assert_eq!(protected.len(), 57); // 1 byte suite + 4 byte key id + 24 byte header + 12 byte nonce + 16 byte tag
     
     // Test exposing empty data
     let exposed = crypt.expose(&protected).await.unwrap();
@@ -72,7 +74,7 @@ async fn test_crypt_key_size() {
     let short_key = b"short";
     let crypt = Crypt::new(short_key);
     
-    // Should still work with padded key
+    // Short keys are stretched with HKDF
     let data = b"test message";
     let protected = crypt.protect(data).await.unwrap();
     let exposed = crypt.expose(&protected).await.unwrap();
@@ -82,8 +84,95 @@ async fn test_crypt_key_size() {
     let long_key = b"this_is_a_very_long_key_that_exceeds_32_bytes";
     let crypt = Crypt::new(long_key);
     
-    // Should still work with truncated key
+    // Long keys are compressed with HKDF rather than truncated
     let protected = crypt.protect(data).await.unwrap();
     let exposed = crypt.expose(&protected).await.unwrap();
     assert_eq!(exposed, data);
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_crypt_replay() {
+    let key = b"test_key_12345_test_key_12345_test_k";
+    let sender = Crypt::new(key);
+    let receiver = Crypt::new(key);
+
+    let protected = sender.protect(b"payment").await.unwrap();
+    assert_eq!(receiver.expose(&protected).await.unwrap(), b"payment");
+    let result = receiver.expose(&protected).await;
+    assert!(matches!(result, Err(Error::Auth(_))));
+
+    // The header is bound as associated data
+    let mut forged = sender.protect(b"payment").await.unwrap();
+    forged[13] ^= 1;
+    let result = receiver.expose(&forged).await;
+    assert!(matches!(result, Err(Error::Decrypt(_))));
+}
+
+#[tokio::test]
+async fn test_crypt_raw_key() {
+    assert!(matches!(Crypt::raw(b"short"), Err(Error::Guard(_))));
+    assert!(matches!(Crypt::raw(&[7u8; 33]), Err(Error::Guard(_))));
+
+    // A short key is not zero-padded any more
+    let mut padded = [0u8; 32];
+    padded[..6].copy_from_slice(b"secret");
+    let protected = Crypt::new(b"secret").protect(b"data").await.unwrap();
+    let result = Crypt::raw(&padded).unwrap().expose(&protected).await;
+    assert!(result.is_err());
+
+    // `new` uses the HKDF encryption subkey of the master secret
+    let keys = derive::split(b"master secret");
+    assert_ne!(keys.crypt, keys.auth);
+    let protected = Crypt::new(b"master secret").protect(b"data").await.unwrap();
+    let exposed = Crypt::raw(&keys.crypt).unwrap().expose(&protected).await.unwrap();
+    assert_eq!(exposed, b"data");
+
+    let auth = Auth::new(&keys.auth);
+    let signed = auth.protect(b"data").await.unwrap();
+    assert_eq!(auth.expose(&signed).await.unwrap(), b"data");
+}
+
+#[tokio::test]
+async fn test_crypt_passphrase() {
+    let kdfs = [
+        Kdf::Argon2id { memory: 256, passes: 1, lanes: 1 },
+        Kdf::Scrypt { log: 4, block: 8, lanes: 1 },
+    ];
+    for kdf in kdfs {
+        let phrase = Phrase::new(kdf);
+
+        // The stored parameters reproduce the same key
+        let stored = serde_json::to_string(&phrase).unwrap();
+        let loaded: Phrase = serde_json::from_str(&stored).unwrap();
+        assert_eq!(loaded, phrase);
+
+        let sender = Crypt::passphrase(b"correct horse", &phrase).unwrap();
+        let receiver = Crypt::passphrase(b"correct horse", &loaded).unwrap();
+        let protected = sender.protect(b"data").await.unwrap();
+        assert_eq!(receiver.expose(&protected).await.unwrap(), b"data");
+
+        // A different passphrase or salt gives a different key
+        let wrong = Crypt::passphrase(b"wrong horse", &phrase).unwrap();
+        assert!(wrong.expose(&protected).await.is_err());
+        let salted = Crypt::passphrase(b"correct horse", &Phrase::new(kdf)).unwrap();
+        assert!(salted.expose(&protected).await.is_err());
+    }
+
+    let invalid = Phrase::new(Kdf::Argon2id { memory: 1, passes: 0, lanes: 0 });
+    assert!(matches!(Crypt::passphrase(b"pass", &invalid), Err(Error::Guard(_))));
+}
+
+#[test]
+fn test_crypt_seal_at_rest() {
+    let crypt = Crypt::new(b"test_key_12345_test_key_12345_test_k");
+
+    // Sealed data has no replay header and opens any number of times
+    let sealed = crypt.seal(b"secret", b"path").unwrap();
+    assert_eq!(sealed.len(), 6 + 1 + 4 + 12 + 16);
+    assert_eq!(Crypt::key_id(&sealed), Some(1));
+    assert_eq!(crypt.open(&sealed, b"path").unwrap(), b"secret");
+    assert_eq!(crypt.open(&sealed, b"path").unwrap(), b"secret");
+
+    // The context is authenticated
+    assert!(matches!(crypt.open(&sealed, b"other"), Err(Error::Decrypt(_))));
+}
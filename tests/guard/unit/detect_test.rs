@@ -0,0 +1,86 @@
+use std::time::Duration;
+use link::core::error::Error;
+use link::guard::Detect;
+use link::guard::detect::{Event, Tuning};
+use link::store::File;
+
+fn tuning() -> Tuning {
+    Tuning {
+        threshold: 50.0,
+        cooldown: Duration::from_millis(200),
+        connections: (3, Duration::from_secs(1)),
+        ..Tuning::default()
+    }
+}
+
+#[tokio::test]
+async fn test_detect_scoring() {
+    let detect = Detect::new(tuning());
+
+    // Errors that are not suspicious do not count
+    assert!(detect.observe("10.0.0.1", &Error::Timeout("read".into())).await.unwrap().is_none());
+    assert_eq!(detect.score("10.0.0.1").await, 0.0);
+
+    // Repeated signature failures push the peer over the threshold
+    for _ in 0..2 {
+        let ban = detect.observe("10.0.0.1", &Error::Auth("invalid signature".into())).await.unwrap();
+        assert!(ban.is_none());
+    }
+    detect.admit("10.0.0.1").await.unwrap();
+    let ban = detect.record("10.0.0.1", Event::Decrypt).await.unwrap().unwrap();
+    assert_eq!(ban.reason, "Decrypt");
+    assert!(matches!(detect.admit("10.0.0.1").await, Err(Error::Denied(_))));
+
+    // Other peers are unaffected and bans expire after the cooldown
+    detect.admit("10.0.0.2").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    detect.admit("10.0.0.1").await.unwrap();
+    assert_eq!(detect.prune().await.unwrap(), 1);
+
+    let measure = detect.shared().measure().await.unwrap();
+    assert_eq!(measure.counters.get("ids_auth"), Some(&2));
+    assert_eq!(measure.counters.get("ids_banned"), Some(&1));
+}
+
+#[tokio::test]
+async fn test_detect_burst_and_decay() {
+    let detect = Detect::new(Tuning {
+        burst: 50.0,
+        decay: Duration::from_millis(50),
+        ..tuning()
+    });
+
+    // Scores halve every decay period
+    detect.record("peer", Event::Oversize).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(detect.score("peer").await < 5.0);
+
+    // More than three connections within a second is a burst
+    for _ in 0..3 {
+        assert!(detect.connect("peer").await.unwrap().is_none());
+    }
+    assert!(detect.connect("peer").await.unwrap().is_some());
+    assert!(detect.ban("peer").await.is_some());
+}
+
+#[tokio::test]
+async fn test_detect_persisted() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = File::new(dir.path());
+    let tuning = Tuning { cooldown: Duration::from_secs(60), ..tuning() };
+
+    let detect = Detect::with_store(tuning, file.clone(), "ids/bans.json").await.unwrap();
+    for _ in 0..4 {
+        detect.record("mallory", Event::Protocol).await.unwrap();
+    }
+    assert!(detect.ban("mallory").await.is_some());
+
+    // A new instance reloads the ban list
+    let reloaded = Detect::with_store(tuning, file.clone(), "ids/bans.json").await.unwrap();
+    assert!(matches!(reloaded.admit("mallory").await, Err(Error::Denied(_))));
+    assert_eq!(reloaded.bans().await.len(), 1);
+
+    reloaded.unban("mallory").await.unwrap();
+    let again = Detect::with_store(tuning, file, "ids/bans.json").await.unwrap();
+    again.admit("mallory").await.unwrap();
+}
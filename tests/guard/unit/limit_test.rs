@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use link::core::error::Error;
+use link::core::link::Handler;
+use link::guard::Limit;
+use link::guard::limit::{Action, Budget, Quota};
+
+fn frames(rate: f64, burst: f64) -> Quota {
+    Quota { frames: Some(Budget::new(rate, burst)), ..Quota::default() }
+}
+
+#[tokio::test]
+async fn test_limit_drop() {
+    let limit = Limit::new(frames(1.0, 2.0), Quota::default(), Action::Drop);
+
+    // The burst passes, then frames are dropped until tokens refill
+    limit.frame("a", 10).await.unwrap();
+    limit.frame("a", 10).await.unwrap();
+    assert!(matches!(limit.frame("a", 10).await, Err(Error::Capacity(_))));
+
+    // Other peers have their own bucket
+    limit.frame("b", 10).await.unwrap();
+
+    let measure = limit.shared().measure().await.unwrap();
+    assert_eq!(measure.counters.get("limit_dropped"), Some(&1));
+}
+
+#[tokio::test]
+async fn test_limit_delay_bytes() {
+    let quota = Quota { bytes: Some(Budget::new(1000.0, 100.0)), ..Quota::default() };
+    let limit = Limit::new(quota, Quota::default(), Action::Delay);
+
+    // 100 bytes of burst, then 100 more bytes take about 100ms
+    let start = Instant::now();
+    limit.frame("a", 100).await.unwrap();
+    limit.frame("a", 100).await.unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(80), "{:?}", elapsed);
+
+    let measure = limit.shared().measure().await.unwrap();
+    assert_eq!(measure.counters.get("limit_delayed"), Some(&1));
+}
+
+#[tokio::test]
+async fn test_limit_global() {
+    let limit = Limit::new(Quota::default(), frames(1.0, 2.0), Action::Drop);
+
+    // The global quota is shared by all peers
+    limit.frame("a", 1).await.unwrap();
+    limit.frame("b", 1).await.unwrap();
+    assert!(limit.frame("c", 1).await.is_err());
+}
+
+#[tokio::test]
+async fn test_limit_disconnect_and_admit() {
+    let peer = Quota {
+        frames: Some(Budget::new(1.0, 1.0)),
+        connections: Some(Budget::new(1.0, 2.0)),
+        ..Quota::default()
+    };
+    let mut limit = Limit::new(peer, Quota::default(), Action::Disconnect);
+    limit.set_cooldown(Duration::from_millis(100));
+    let limit = Arc::new(limit);
+
+    // Connections are limited at accept time
+    limit.admit("a").await.unwrap();
+    limit.admit("a").await.unwrap();
+    assert!(matches!(limit.admit("a").await, Err(Error::Capacity(_))));
+
+    // The pipeline stage disconnects a flooding peer, which then cools down
+    let throttle = limit.throttle("b");
+    assert_eq!(throttle.handle(b"one").await.unwrap(), b"one");
+    let error = throttle.handle(b"two").await.unwrap_err();
+    assert!(matches!(error, Error::Denied(_)) && !error.is_retryable());
+    assert!(matches!(limit.admit("b").await, Err(Error::Denied(_))));
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    limit.admit("b").await.unwrap();
+
+    let measure = limit.shared().measure().await.unwrap();
+    assert_eq!(measure.counters.get("limit_disconnected"), Some(&1));
+    assert_eq!(measure.counters.get("limit_refused"), Some(&2));
+    assert_eq!(measure.counters.get("limit_admitted"), Some(&3));
+
+    assert_eq!(limit.prune(Duration::ZERO), 2);
+}
+
+#[tokio::test]
+async fn test_limit_forgets_idle_peers() {
+    let peer = Quota {
+        frames: Some(Budget::new(1.0, 1.0)),
+        ..Quota::default()
+    };
+    let mut limit = Limit::new(peer, Quota::default(), Action::Drop);
+    limit.set_idle(Duration::from_millis(50));
+
+    for i in 0..100 {
+        limit.frame(&format!("peer-{}", i), 1).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // The next frame forgets every idle peer, so only the sender is left to prune
+    limit.frame("last", 1).await.unwrap();
+    assert_eq!(limit.prune(Duration::ZERO), 1);
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use link::core::error::Error;
+use link::guard::{Replay, Role};
+use link::guard::replay::HEADER;
+
+fn header(seq: u64, stamp: u64) -> [u8; HEADER] {
+    session(7, seq, stamp)
+}
+
+fn session(epoch: u64, seq: u64, stamp: u64) -> [u8; HEADER] {
+    let mut header = [0u8; HEADER];
+    header[..8].copy_from_slice(&epoch.to_be_bytes());
+    header[8..16].copy_from_slice(&seq.to_be_bytes());
+    header[16..].copy_from_slice(&stamp.to_be_bytes());
+    header
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+#[test]
+fn test_replay_window() {
+    let replay = Replay::default();
+    let now = now();
+
+    // Out-of-order frames inside the window are accepted once
+    replay.accept(&header(5, now)).unwrap();
+    replay.accept(&header(3, now)).unwrap();
+    replay.accept(&header(4, now)).unwrap();
+    assert!(matches!(replay.accept(&header(3, now)), Err(Error::Auth(_))));
+    assert!(matches!(replay.accept(&header(5, now)), Err(Error::Auth(_))));
+
+    // Frames that fell behind the window are rejected
+    replay.accept(&header(100, now)).unwrap();
+    replay.accept(&header(37, now)).unwrap();
+    assert!(matches!(replay.accept(&header(36, now)), Err(Error::Auth(_))));
+}
+
+#[test]
+fn test_replay_age() {
+    let replay = Replay::new(Duration::from_secs(5));
+    let now = now();
+
+    assert!(matches!(replay.accept(&header(1, now - 10_000)), Err(Error::Auth(_))));
+    assert!(matches!(replay.accept(&header(2, now + 10_000)), Err(Error::Auth(_))));
+    replay.accept(&header(3, now - 1_000)).unwrap();
+
+    // Stamped headers carry increasing sequence numbers
+    let first = replay.stamp();
+    let second = replay.stamp();
+    assert_eq!(first[..8], second[..8]);
+    assert_eq!(u64::from_be_bytes(first[8..16].try_into().unwrap()) + 1, u64::from_be_bytes(second[8..16].try_into().unwrap()));
+}
+
+#[test]
+fn test_replay_new_session() {
+    let replay = Replay::default();
+    let now = now();
+
+    // A restarted sender numbers its frames from 1 again under a new epoch
+    replay.accept(&session(1, 500, now)).unwrap();
+    replay.accept(&session(2, 1, now)).unwrap();
+    replay.accept(&session(2, 2, now)).unwrap();
+
+    // Frames of the earlier session still cannot be replayed
+    assert!(matches!(replay.accept(&session(1, 500, now)), Err(Error::Auth(_))));
+    assert!(matches!(replay.accept(&session(2, 1, now)), Err(Error::Auth(_))));
+    assert!(matches!(replay.accept(&[0u8; 8]), Err(Error::Protocol(_))));
+}
+
+#[test]
+fn test_replay_evicted_session() {
+    let replay = Replay::default();
+    let now = now();
+
+    // The oldest of seventeen sessions is evicted and its frames cannot start a fresh window
+    replay.accept(&session(100, 1, now - 1_000)).unwrap();
+    for epoch in 0..16 {
+        replay.accept(&session(epoch, 1, now)).unwrap();
+    }
+    assert!(matches!(replay.accept(&session(100, 1, now - 1_000)), Err(Error::Auth(_))));
+    assert!(matches!(replay.accept(&session(200, 1, now - 2_000)), Err(Error::Auth(_))));
+
+    // Sessions stamped after the evicted one are still accepted
+    replay.accept(&session(300, 1, now)).unwrap();
+}
+
+#[test]
+fn test_replay_reflected_frame() {
+    let mut initiator = Replay::default();
+    initiator.set_role(Role::Initiator);
+    let mut responder = Replay::default();
+    responder.set_role(Role::Responder);
+
+    // Each side accepts the other's frames but not its own sent back to it
+    let outgoing = initiator.stamp();
+    responder.accept(&outgoing).unwrap();
+    assert!(matches!(initiator.accept(&outgoing), Err(Error::Auth(_))));
+
+    let incoming = responder.stamp();
+    initiator.accept(&incoming).unwrap();
+    assert!(matches!(responder.accept(&incoming), Err(Error::Auth(_))));
+
+    // Without a role both directions are accepted
+    Replay::default().accept(&initiator.stamp()).unwrap();
+    Replay::default().accept(&responder.stamp()).unwrap();
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use link::core::error::Error;
+use link::core::link::Guardable;
+use link::guard::{Auth, Crypt, Ring};
+
+#[test]
+fn test_ring_schedule() {
+    let ring = Ring::new(b"first");
+    assert_eq!(ring.current().unwrap().id, 1);
+
+    // A scheduled key is accepted but not used until it becomes active
+    let later = ring.schedule(b"second", SystemTime::now() + Duration::from_secs(60));
+    assert_eq!(ring.current().unwrap().id, 1);
+    assert!(ring.get(later).is_some());
+
+    let now = ring.schedule(b"third", SystemTime::now());
+    assert_eq!(ring.current().unwrap().id, now);
+
+    // Retired keys are refused and can be pruned
+    ring.retire(1, SystemTime::now()).unwrap();
+    assert!(ring.get(1).is_none());
+    assert_eq!(ring.prune(), 1);
+    assert_eq!(ring.list().len(), 2);
+    assert!(matches!(ring.retire(1, SystemTime::now()), Err(Error::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_auth_rotation() {
+    let ring = Arc::new(Ring::new(b"old_key"));
+    let sender = Auth::with_ring(ring.clone());
+    let receiver = Auth::with_ring(Arc::new(Ring::new(b"old_key")));
+
+    let before = sender.protect(b"before").await.unwrap();
+
+    // Both sides learn the new key; the old one stays valid for the grace period
+    let id = ring.rotate(b"new_key", Duration::from_millis(200));
+    receiver.ring().rotate(b"new_key", Duration::from_millis(200));
+    let after = sender.protect(b"after").await.unwrap();
+    assert_eq!(&after[32..36], &id.to_be_bytes());
+
+    assert_eq!(receiver.expose(&after).await.unwrap(), b"after");
+    assert_eq!(receiver.expose(&before).await.unwrap(), b"before");
+
+    // Once retired, frames under the old key are refused
+    let stale = Auth::new(b"old_key").protect(b"stale").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let result = receiver.expose(&stale).await;
+    assert!(matches!(result, Err(Error::Auth(_))));
+}
+
+#[tokio::test]
+async fn test_crypt_rotation() {
+    let crypt = Crypt::new(b"old_key");
+    let ring = crypt.ring();
+
+    let before = crypt.protect(b"before").await.unwrap();
+    let old = crypt.protect(b"old").await.unwrap();
+    let id = crypt.rotate(b"new_key", Duration::from_secs(60));
+    let after = crypt.protect(b"after").await.unwrap();
+    assert_eq!(&after[1..5], &id.to_be_bytes());
+
+    assert_eq!(crypt.expose(&before).await.unwrap(), b"before");
+    assert_eq!(crypt.expose(&after).await.unwrap(), b"after");
+
+    // Retiring the old key immediately stops accepting its frames
+    ring.retire(1, SystemTime::now()).unwrap();
+    let result = crypt.expose(&old).await;
+    assert!(matches!(result, Err(Error::Decrypt(_))));
+}
+
+#[test]
+fn test_ring_ids_and_subkeys() {
+    let ring = Ring::new(b"first");
+    let second = ring.rotate(b"second", Duration::ZERO);
+    assert_eq!(ring.prune(), 1);
+
+    // Ids keep growing after the newest key is pruned
+    ring.retire(second, SystemTime::now()).unwrap();
+    ring.schedule(b"third", SystemTime::now());
+    assert_eq!(ring.prune(), 1);
+    assert_eq!(ring.schedule(b"fourth", SystemTime::now()), 4);
+
+    // Auth and Crypt sharing the ring never use the same key material
+    let key = ring.current().unwrap();
+    assert_ne!(key.crypt(), key.auth());
+    assert_ne!(key.crypt(), &key.secret[..]);
+}
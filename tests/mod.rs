@@ -3,6 +3,8 @@ mod core {
     mod unit {
         mod link_test;
         mod state_test;
+        mod export_test;
+        mod error_test;
     }
     mod integration {
         mod core_test;
@@ -15,6 +17,10 @@ mod net {
         mod socket_test;
         mod group_test;
         mod route_test;
+        mod retry_test;
+        mod tls_test;
+        mod transfer_test;
+        mod negotiate_test;
     }
     mod integration {
         mod net_test;
@@ -25,8 +31,14 @@ mod net {
 mod guard {
     mod unit {
         mod auth_test;
+        mod access_test;
         mod crypt_test;
+        mod cipher_test;
         mod check_test;
+        mod replay_test;
+        mod ring_test;
+        mod limit_test;
+        mod detect_test;
     }
     mod integration {
         mod guard_test;
@@ -39,6 +51,10 @@ mod store {
         mod data_test;
         mod cache_test;
         mod file_test;
+        mod layer_test;
+        mod vault_test;
+        mod snapshot_test;
+        mod replica_test;
     }
     mod integration {
         mod store_test;
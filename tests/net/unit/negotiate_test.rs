@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use link::core::error::Error;
+use link::core::link::{Guardable, Linkable, Movable, Settings};
+use link::guard::{Crypt, Suite};
+use link::net::{negotiate, Negotiate, Socket};
+use link::net::retry::Handshake;
+use tokio::net::TcpListener;
+
+async fn pair() -> (Socket, Socket) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut client = Socket::connect(addr, Settings::default()).await.unwrap();
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut server = Socket::wrap(stream, Settings::default());
+    client.start().await.unwrap();
+    server.start().await.unwrap();
+    (client, server)
+}
+
+fn strict(suites: &[Suite]) -> Crypt {
+    let mut crypt = Crypt::new(b"key");
+    crypt.set_suites(suites).unwrap();
+    crypt
+}
+
+#[tokio::test]
+async fn test_negotiate_suite() {
+    let (mut client, mut server) = pair().await;
+    let crypt = Arc::new(Crypt::new(b"key"));
+    assert_eq!(crypt.suite(), Suite::ChaCha20Poly1305);
+
+    // The server mandates AES-256-GCM; the client switches to it
+    let answer = tokio::spawn(async move {
+        negotiate::answer(&mut server, &strict(&[Suite::Aes256Gcm])).await
+    });
+    let negotiate = Negotiate::new(crypt.clone());
+    negotiate.handshake(&mut client).await.unwrap();
+    let client = negotiate.session().unwrap();
+    let server = answer.await.unwrap().unwrap();
+    assert_eq!(client.suite(), Suite::Aes256Gcm);
+    assert_eq!(server.suite(), Suite::Aes256Gcm);
+
+    // The shared guard is left untouched and the sessions talk only to each other
+    assert_eq!(crypt.suite(), Suite::ChaCha20Poly1305);
+    let frame = client.protect(b"data").await.unwrap();
+    assert_eq!(server.expose(&frame).await.unwrap(), b"data");
+    assert!(matches!(client.expose(&frame).await, Err(Error::Auth(_))));
+}
+
+#[tokio::test]
+async fn test_negotiate_concurrent_sessions() {
+    let crypt = Arc::new(Crypt::new(b"key"));
+    let (mut first, mut first_server) = pair().await;
+    let (mut second, mut second_server) = pair().await;
+
+    // Two peers negotiate different suites against the same shared guard
+    let answers = tokio::spawn(async move {
+        let aes = negotiate::answer(&mut first_server, &strict(&[Suite::Aes256Gcm])).await.unwrap();
+        let chacha = negotiate::answer(&mut second_server, &strict(&[Suite::ChaCha20Poly1305])).await.unwrap();
+        (aes, chacha)
+    });
+    let (aes, chacha) = tokio::join!(negotiate::offer(&mut first, &crypt), negotiate::offer(&mut second, &crypt));
+    let (aes, chacha) = (aes.unwrap(), chacha.unwrap());
+    let (aes_server, chacha_server) = answers.await.unwrap();
+    assert_eq!(aes.suite(), Suite::Aes256Gcm);
+    assert_eq!(chacha.suite(), Suite::ChaCha20Poly1305);
+
+    // Each session keeps its own suite and refuses frames under the other one
+    let frame = aes.protect(b"first").await.unwrap();
+    assert_eq!(aes_server.expose(&frame).await.unwrap(), b"first");
+    let frame = chacha.protect(b"second").await.unwrap();
+    assert_eq!(chacha_server.expose(&frame).await.unwrap(), b"second");
+    assert!(matches!(aes_server.expose(&chacha.protect(b"x").await.unwrap()).await, Err(Error::Decrypt(_))));
+}
+
+#[tokio::test]
+async fn test_negotiate_no_common_suite() {
+    let (mut client, mut server) = pair().await;
+
+    let answer = tokio::spawn(async move {
+        negotiate::answer(&mut server, &strict(&[Suite::Aes256Gcm])).await
+    });
+    let result = negotiate::offer(&mut client, &strict(&[Suite::ChaCha20Poly1305])).await;
+    assert!(matches!(result, Err(Error::Guard(_))));
+    assert!(matches!(answer.await.unwrap(), Err(Error::Guard(_))));
+}
+
+#[tokio::test]
+async fn test_negotiate_downgrade() {
+    let (mut client, mut near) = pair().await;
+    let (mut far, mut server) = pair().await;
+
+    // A relay in the middle strips the preferred suite from the offer
+    let relay = tokio::spawn(async move {
+        let mut buf = vec![0u8; 256];
+        let n = near.receive(&mut buf).await.unwrap();
+        far.send(&buf[1..n]).await.unwrap();
+        let n = far.receive(&mut buf).await.unwrap();
+        near.send(&buf[..n]).await.unwrap();
+        if let Ok(n) = near.receive(&mut buf).await {
+            let _ = far.send(&buf[..n]).await;
+        }
+        if let Ok(n) = far.receive(&mut buf).await {
+            let _ = near.send(&buf[..n]).await;
+        }
+    });
+    let answer = tokio::spawn(async move {
+        negotiate::answer(&mut server, &Crypt::new(b"key")).await
+    });
+    let crypt = strict(&[Suite::Aes256Gcm, Suite::ChaCha20Poly1305]);
+    let offer = tokio::time::timeout(std::time::Duration::from_secs(5), negotiate::offer(&mut client, &crypt)).await;
+    assert!(matches!(answer.await.unwrap(), Err(Error::Auth(_))));
+    assert!(!matches!(offer, Ok(Ok(_))));
+    let _ = relay.await;
+}
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use link::core::error::Result;
+use link::core::link::{Settings, Linkable, Movable};
+use link::core::state::Mode;
+use link::net::{Socket, Route, Retry};
+use link::net::retry::{Backoff, Handshake};
+use link::net::route::Entry;
+use tokio::net::TcpListener;
+
+struct Counter(Arc<AtomicUsize>);
+
+#[async_trait::async_trait]
+impl Handshake for Counter {
+    async fn handshake(&self, _socket: &mut Socket) -> Result<()> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+async fn route_to(addr: std::net::SocketAddr) -> Arc<Route> {
+    let route = Route::new(Settings::default());
+    route.add("server".into(), Entry {
+        addr: addr.to_string(),
+        weight: 1,
+    }).await.unwrap();
+    Arc::new(route)
+}
+
+#[test]
+fn test_backoff_delay() {
+    let backoff = Backoff {
+        base: Duration::from_millis(100),
+        limit: Duration::from_secs(1),
+        tries: 0,
+    };
+
+    // Delay doubles with jitter in the upper half
+    for attempt in 0..3 {
+        let full = Duration::from_millis(100 * 2u64.pow(attempt));
+        let delay = backoff.delay(attempt);
+        assert!(delay >= full / 2 && delay <= full, "attempt {}: {:?}", attempt, delay);
+    }
+
+    // Delay is capped by the limit
+    let delay = backoff.delay(20);
+    assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn test_retry_reconnect() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let shakes = Arc::new(AtomicUsize::new(0));
+    let chains = Arc::new(AtomicUsize::new(0));
+
+    let mut retry = Retry::new(route_to(addr).await, "server");
+    retry.set_handshake(Counter(shakes.clone()));
+    retry.set_chain({
+        let chains = chains.clone();
+        move |_socket| {
+            chains.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    retry.start().await.unwrap();
+    assert!(matches!(retry.state().await.unwrap(), Mode::Ready));
+
+    // Remote side drops the first connection
+    let (first, _) = listener.accept().await.unwrap();
+    drop(first);
+
+    // Receiving redials and picks up the new connection
+    let server = async {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = Socket::wrap(stream, Settings::default());
+        socket.send(b"again").await.unwrap();
+        socket
+    };
+    let mut buf = vec![0; 16];
+    let (received, _socket) = tokio::join!(retry.receive(&mut buf), server);
+    assert_eq!(&buf[..received.unwrap()], b"again");
+
+    assert!(matches!(retry.state().await.unwrap(), Mode::Ready));
+    assert_eq!(shakes.load(Ordering::SeqCst), 2);
+    assert_eq!(chains.load(Ordering::SeqCst), 2);
+
+    retry.stop().await.unwrap();
+    assert!(matches!(retry.state().await.unwrap(), Mode::Close));
+}
+
+#[tokio::test]
+async fn test_retry_buffer() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut retry = Retry::new(route_to(addr).await, "server");
+    retry.set_backoff(Backoff {
+        base: Duration::from_millis(10),
+        limit: Duration::from_millis(20),
+        tries: 0,
+    });
+    retry.set_buffer(8);
+    retry.start().await.unwrap();
+
+    // Remote side goes away entirely
+    let (first, _) = listener.accept().await.unwrap();
+    drop(first);
+    drop(listener);
+
+    // Sends keep succeeding while frames are held back
+    for _ in 0..50 {
+        retry.send(b"held").await.unwrap();
+        if retry.pending() > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(matches!(retry.state().await.unwrap(), Mode::Pause));
+    let held = retry.pending();
+    assert!(held > 0);
+
+    // Remote side comes back on the same address
+    let listener = TcpListener::bind(addr).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    retry.send(b"after").await.unwrap();
+    assert_eq!(retry.pending(), 0);
+    assert!(matches!(retry.state().await.unwrap(), Mode::Ready));
+
+    // Held frames arrive in order before the new one
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut socket = Socket::wrap(stream, Settings::default());
+    let mut buf = vec![0; 16];
+    for _ in 0..held {
+        let received = socket.receive(&mut buf).await.unwrap();
+        assert_eq!(&buf[..received], b"held");
+    }
+    let received = socket.receive(&mut buf).await.unwrap();
+    assert_eq!(&buf[..received], b"after");
+}
+
+#[tokio::test]
+async fn test_retry_gives_up() {
+    // Reserve a port with nothing listening on it
+    let addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+    let mut retry = Retry::new(route_to(addr).await, "server");
+    retry.set_backoff(Backoff {
+        base: Duration::from_millis(5),
+        limit: Duration::from_millis(10),
+        tries: 3,
+    });
+
+    assert!(retry.start().await.is_err());
+    assert!(matches!(retry.state().await.unwrap(), Mode::Close));
+}
+
+/// Accepts the first handshake and refuses every later one
+struct Revoked(Arc<AtomicUsize>);
+
+#[async_trait::async_trait]
+impl Handshake for Revoked {
+    async fn handshake(&self, _socket: &mut Socket) -> Result<()> {
+        if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Ok(());
+        }
+        Err(link::core::error::Error::Auth("credentials revoked".into()))
+    }
+}
+
+#[tokio::test]
+async fn test_retry_stops_on_permanent_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Unlimited tries, yet a refused handshake ends the link instead of redialing forever
+    let shakes = Arc::new(AtomicUsize::new(0));
+    let mut retry = Retry::new(route_to(addr).await, "server");
+    retry.set_backoff(Backoff {
+        base: Duration::from_millis(5),
+        limit: Duration::from_millis(10),
+        tries: 0,
+    });
+    retry.set_handshake(Revoked(shakes.clone()));
+    retry.start().await.unwrap();
+
+    let (first, _) = listener.accept().await.unwrap();
+    drop(first);
+    let accept = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        stream
+    });
+
+    let mut buf = vec![0; 16];
+    let result = tokio::time::timeout(Duration::from_secs(5), retry.receive(&mut buf)).await.unwrap();
+    assert!(matches!(result, Err(link::core::error::Error::Auth(_))));
+    assert!(matches!(retry.state().await.unwrap(), Mode::Close));
+    assert_eq!(shakes.load(Ordering::SeqCst), 2);
+    accept.abort();
+}
@@ -1,4 +1,5 @@
 use link::core::link::{Settings, Linkable};
+use link::core::error::Error;
 use link::core::state::Mode;
 use link::net::Route;
 use link::net::route::Entry;
@@ -48,7 +49,7 @@ async fn test_route_table() {
     // Test remove route
     route.remove("server1").await.unwrap();
     let result = route.get("server1").await;
-    assert!(result.is_err());
+    assert!(matches!(result, Err(Error::NotFound(_))));
 }
 
 #[tokio::test]
@@ -76,4 +77,44 @@ async fn test_route_connection() {
     
     socket.stop().await.unwrap();
     assert!(matches!(socket.state().await.unwrap(), Mode::Close));
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_route_policy() {
+    use std::sync::Arc;
+    use link::guard::Policy;
+    use link::guard::access::{Direction, Grant};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let settings = Settings {
+        name: "node-a".into(),
+        ..Settings::default()
+    };
+    let mut policy = Policy::new();
+    policy.add_grant(Grant {
+        ports: vec![(addr.port(), addr.port())],
+        direction: Direction::Outbound,
+        ..Grant::allow("node-a", "allowed")
+    });
+    let policy = Arc::new(policy);
+
+    let mut route = Route::new(settings);
+    route.set_policy(policy.clone());
+    for name in ["allowed", "blocked"] {
+        route.add(name.into(), Entry {
+            addr: addr.to_string(),
+            weight: 1,
+        }).await.unwrap();
+    }
+
+    // The policy is evaluated before the connection is opened
+    route.connect("allowed").await.unwrap();
+    assert!(matches!(route.connect("blocked").await, Err(Error::Denied(_))));
+
+    // Forwarded requests are evaluated for the requesting peer
+    let result = route.open("node-b", "allowed", Direction::Inbound).await;
+    assert!(matches!(result, Err(Error::Denied(_))));
+    assert_eq!(policy.decisions().len(), 3);
+}
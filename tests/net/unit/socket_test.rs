@@ -58,4 +58,215 @@ async fn test_socket_data_transfer() {
     let received = socket.receive(&mut buf).await.unwrap();
     assert_eq!(received, data.len());
     assert_eq!(buf, data);
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_socket_idle_timeout() {
+    use std::time::Duration;
+    use link::core::error::Error;
+    use link::core::link::Deadline;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let settings = Settings::default();
+    let mut socket = Socket::connect(addr, settings.clone()).await.unwrap();
+    socket.set_deadline(Deadline {
+        idle: Duration::from_millis(50),
+        ..settings.deadline()
+    });
+    socket.start().await.unwrap();
+
+    // Peer accepts but never writes
+    let (_stream, _) = listener.accept().await.unwrap();
+
+    let mut buf = vec![0; 4];
+    let result = socket.receive(&mut buf).await;
+    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(matches!(socket.state().await.unwrap(), Mode::Close));
+
+    // Closed socket rejects further operations
+    let result = socket.send(&[1, 2, 3, 4]).await;
+    assert!(matches!(result, Err(Error::Closed(_))));
+}
+
+#[tokio::test]
+async fn test_socket_read_timeout() {
+    use std::time::Duration;
+    use link::core::error::Error;
+    use link::core::link::Deadline;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let settings = Settings::default();
+    let mut socket = Socket::connect(addr, settings.clone()).await.unwrap();
+    socket.set_deadline(Deadline {
+        read: Duration::from_millis(50),
+        ..settings.deadline()
+    });
+    socket.start().await.unwrap();
+
+    // Peer announces 8 bytes but only delivers 2
+    let (mut stream, _) = listener.accept().await.unwrap();
+    stream.write_all(&8u32.to_be_bytes()).await.unwrap();
+    stream.write_all(&[1, 2]).await.unwrap();
+
+    let mut buf = vec![0; 8];
+    let result = socket.receive(&mut buf).await;
+    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(matches!(socket.state().await.unwrap(), Mode::Close));
+}
+
+#[tokio::test]
+async fn test_socket_ping_rtt() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut client = Socket::connect(addr, Settings::default()).await.unwrap();
+    client.start().await.unwrap();
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut server = Socket::wrap(stream, Settings::default());
+    server.start().await.unwrap();
+
+    assert!(client.measure().await.unwrap().rtt.is_none());
+
+    // Server answers the ping while waiting for data
+    client.ping().await.unwrap();
+    client.send(b"data").await.unwrap();
+    let mut buf = vec![0; 16];
+    let received = server.receive(&mut buf).await.unwrap();
+    assert_eq!(&buf[..received], b"data");
+
+    // Client consumes the pong before the reply
+    server.send(b"reply").await.unwrap();
+    let received = client.receive(&mut buf).await.unwrap();
+    assert_eq!(&buf[..received], b"reply");
+
+    let measure = client.measure().await.unwrap();
+    assert!(measure.rtt.is_some());
+    assert!(measure.jitter.is_some());
+}
+
+#[tokio::test]
+async fn test_socket_heartbeat() {
+    use std::time::Duration;
+    use link::net::socket::Beat;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut socket = Socket::connect(addr, Settings::default()).await.unwrap();
+    socket.set_beat(Beat {
+        interval: Duration::from_millis(20),
+        miss: 2,
+    });
+    socket.start().await.unwrap();
+
+    // Raw peer answers three pings then sends data
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let peer = tokio::spawn(async move {
+        for _ in 0..3 {
+            let mut frame = [0u8; 13];
+            stream.read_exact(&mut frame).await.unwrap();
+            assert_eq!(u32::from_be_bytes(frame[..4].try_into().unwrap()), (1 << 31) | 9);
+            assert_eq!(frame[4], 1);
+            frame[4] = 2;
+            stream.write_all(&frame).await.unwrap();
+        }
+        stream.write_all(&4u32.to_be_bytes()).await.unwrap();
+        stream.write_all(b"live").await.unwrap();
+        stream
+    });
+
+    let mut buf = vec![0; 16];
+    let received = socket.receive(&mut buf).await.unwrap();
+    assert_eq!(&buf[..received], b"live");
+    assert!(socket.measure().await.unwrap().rtt.is_some());
+    let _stream = peer.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_socket_heartbeat_missed() {
+    use std::time::Duration;
+    use link::core::error::Error;
+    use link::net::socket::Beat;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut socket = Socket::connect(addr, Settings::default()).await.unwrap();
+    socket.set_beat(Beat {
+        interval: Duration::from_millis(20),
+        miss: 2,
+    });
+    socket.start().await.unwrap();
+
+    // Peer accepts but never answers pings
+    let (_stream, _) = listener.accept().await.unwrap();
+
+    let mut buf = vec![0; 4];
+    let result = socket.receive(&mut buf).await;
+    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(matches!(socket.state().await.unwrap(), Mode::Close));
+}
+
+#[tokio::test]
+async fn test_socket_rejects_oversized_frame() {
+    use link::core::error::Error;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let settings = Settings { size: 1024, ..Settings::default() };
+    let mut socket = Socket::connect(addr, settings).await.unwrap();
+    socket.start().await.unwrap();
+
+    // Peer announces a frame far above the configured size
+    let (mut stream, _) = listener.accept().await.unwrap();
+    stream.write_all(&(1u32 << 30).to_be_bytes()).await.unwrap();
+
+    let mut buf = vec![0; 16];
+    assert!(matches!(socket.receive(&mut buf).await, Err(Error::Capacity(_))));
+
+    // The payload is never read, so the socket closes instead of parsing it as a header
+    assert!(matches!(socket.state().await.unwrap(), Mode::Close));
+    assert!(matches!(socket.receive(&mut buf).await, Err(Error::Closed(_))));
+    assert!(matches!(socket.send(&[0; 8]).await, Err(Error::Closed(_))));
+}
+
+#[tokio::test]
+async fn test_socket_rejects_oversized_send() {
+    use link::core::error::Error;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let settings = Settings { size: 1024, ..Settings::default() };
+    let mut socket = Socket::connect(addr, settings).await.unwrap();
+    socket.start().await.unwrap();
+    let (_stream, _) = listener.accept().await.unwrap();
+
+    assert!(matches!(socket.send(&[0; 2048]).await, Err(Error::Capacity(_))));
+    socket.send(&[0; 8]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_socket_ignores_unsolicited_pong() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut socket = Socket::connect(addr, Settings::default()).await.unwrap();
+    socket.start().await.unwrap();
+
+    // Forged pong for a ping that was never sent, then data
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut frame = ((1u32 << 31) | 9).to_be_bytes().to_vec();
+    frame.push(2);
+    frame.extend_from_slice(&1u64.to_be_bytes());
+    stream.write_all(&frame).await.unwrap();
+    stream.write_all(&4u32.to_be_bytes()).await.unwrap();
+    stream.write_all(b"data").await.unwrap();
+
+    let mut buf = vec![0; 16];
+    let received = socket.receive(&mut buf).await.unwrap();
+    assert_eq!(&buf[..received], b"data");
+    assert!(socket.measure().await.unwrap().rtt.is_none());
+}
@@ -0,0 +1,167 @@
+use link::core::error::Error;
+use link::core::link::{Linkable, Movable, Settings};
+use link::net::tls::{Client, Identity, Server, Trust};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair};
+
+/// In-memory certificate authority for tests
+struct Ca {
+    cert: Certificate,
+    key: KeyPair,
+}
+
+impl Ca {
+    fn new(name: &str) -> Self {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        Self { cert, key }
+    }
+
+    fn trust(&self) -> Trust {
+        Trust::from_pem(self.cert.pem().as_bytes()).unwrap()
+    }
+
+    /// Issues a leaf certificate, returning its PEM and the matching identity
+    fn issue(&self, name: &str) -> (String, Identity) {
+        let params = CertificateParams::new(vec![name.to_string()]).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+        let identity = Identity::from_pem(cert.pem().as_bytes(), key.serialize_pem().as_bytes()).unwrap();
+        (cert.pem(), identity)
+    }
+}
+
+#[tokio::test]
+async fn test_tls_transfer() {
+    let ca = Ca::new("test ca");
+    let (_, identity) = ca.issue("localhost");
+    let server = Server::new(identity, None).unwrap();
+    let listener = server.bind("127.0.0.1:0", Settings::default()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accept = tokio::spawn(async move {
+        let mut socket = listener.accept().await.unwrap().finish().await.unwrap();
+        socket.start().await.unwrap();
+        assert_eq!(socket.peer(), None);
+        let mut buf = vec![0; 16];
+        let n = socket.receive(&mut buf).await.unwrap();
+        socket.send(&buf[..n]).await.unwrap();
+    });
+
+    let client = Client::new(ca.trust(), None).unwrap();
+    let mut socket = client.connect(addr, "localhost", Settings::default()).await.unwrap();
+    socket.start().await.unwrap();
+    assert_eq!(socket.peer(), Some("localhost"));
+
+    socket.send(b"over tls").await.unwrap();
+    let mut buf = vec![0; 16];
+    let n = socket.receive(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"over tls");
+    accept.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_untrusted_server() {
+    let ca = Ca::new("test ca");
+    let (_, identity) = ca.issue("localhost");
+    let server = Server::new(identity, None).unwrap();
+    let listener = server.bind("127.0.0.1:0", Settings::default()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { listener.accept().await.unwrap().finish().await });
+
+    let other = Ca::new("other ca");
+    let client = Client::new(other.trust(), None).unwrap();
+    let result = client.connect(addr, "localhost", Settings::default()).await;
+    assert!(matches!(result, Err(Error::Auth(_))));
+}
+
+#[tokio::test]
+async fn test_tls_mutual_identity() {
+    let ca = Ca::new("test ca");
+    let (_, identity) = ca.issue("localhost");
+    let (alice, alice_identity) = ca.issue("alice");
+    let (_, bob_identity) = ca.issue("bob");
+    let bob_fingerprint = bob_identity.fingerprint();
+
+    let mut server = Server::new(identity, Some(ca.trust())).unwrap();
+    server.map(alice.as_bytes(), "alice").unwrap();
+    let listener = server.bind("127.0.0.1:0", Settings::default()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accept = tokio::spawn(async move {
+        let first = listener.accept().await.unwrap().finish().await.unwrap();
+        let second = listener.accept().await.unwrap().finish().await.unwrap();
+        (first.peer().map(String::from), second.peer().map(String::from))
+    });
+
+    // Mapped certificates carry their peer identity, others their fingerprint
+    let client = Client::new(ca.trust(), Some(alice_identity)).unwrap();
+    let _alice = client.connect(addr, "localhost", Settings::default()).await.unwrap();
+    let client = Client::new(ca.trust(), Some(bob_identity)).unwrap();
+    let _bob = client.connect(addr, "localhost", Settings::default()).await.unwrap();
+
+    let (first, second) = accept.await.unwrap();
+    assert_eq!(first.as_deref(), Some("alice"));
+    assert_eq!(second, Some(bob_fingerprint));
+}
+
+#[tokio::test]
+async fn test_tls_mutual_rejects_client() {
+    let ca = Ca::new("test ca");
+    let (_, identity) = ca.issue("localhost");
+    let server = Server::new(identity, Some(ca.trust())).unwrap();
+    let listener = server.bind("127.0.0.1:0", Settings::default()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accept = tokio::spawn(async move {
+        let anonymous = listener.accept().await.unwrap().finish().await;
+        let untrusted = listener.accept().await.unwrap().finish().await;
+        (anonymous.is_err(), untrusted.is_err())
+    });
+
+    // No client certificate
+    let client = Client::new(ca.trust(), None).unwrap();
+    let _ = client.connect(addr, "localhost", Settings::default()).await;
+
+    // Certificate issued by a CA the server does not trust
+    let other = Ca::new("other ca");
+    let (_, stranger) = other.issue("mallory");
+    let client = Client::new(ca.trust(), Some(stranger)).unwrap();
+    let _ = client.connect(addr, "localhost", Settings::default()).await;
+
+    assert_eq!(accept.await.unwrap(), (true, true));
+}
+
+#[tokio::test]
+async fn test_tls_slow_handshake() {
+    let ca = Ca::new("test ca");
+    let (_, identity) = ca.issue("localhost");
+    let server = Server::new(identity, None).unwrap();
+    let listener = server.bind("127.0.0.1:0", Settings::default()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Handshakes run off the accept loop, so a silent client does not hold up the next one
+    let accept = tokio::spawn(async move {
+        let mut handshakes = Vec::new();
+        for _ in 0..2 {
+            handshakes.push(tokio::spawn(listener.accept().await.unwrap().finish()));
+        }
+        handshakes.pop().unwrap().await.unwrap()
+    });
+    let _silent = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let client = Client::new(ca.trust(), None).unwrap();
+    let connect = client.connect(addr, "localhost", Settings::default());
+    let (connected, accepted) = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        tokio::join!(connect, accept)
+    }).await.unwrap();
+    connected.unwrap();
+    accepted.unwrap().unwrap();
+}
+
+#[test]
+fn test_tls_invalid_pem() {
+    assert!(matches!(Identity::from_pem(b"not pem", b"not pem"), Err(Error::Guard(_))));
+    assert!(matches!(Trust::from_pem(b""), Err(Error::Guard(_))));
+}
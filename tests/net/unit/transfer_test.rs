@@ -0,0 +1,225 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use link::core::error::Error;
+use link::core::link::{Deadline, Linkable, Movable, Settings};
+use link::net::{Socket, Transfer};
+use link::store::File;
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
+
+/// Connects `count` socket pairs over loopback, in the same order on both sides
+async fn pairs(count: usize) -> (Vec<Socket>, Vec<Socket>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (mut senders, mut receivers) = (Vec::new(), Vec::new());
+    for _ in 0..count {
+        let mut sender = Socket::connect(addr, Settings::default()).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut receiver = Socket::wrap(stream, Settings::default());
+        sender.start().await.unwrap();
+        receiver.start().await.unwrap();
+        senders.push(sender);
+        receivers.push(receiver);
+    }
+    (senders, receivers)
+}
+
+fn artifact(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+#[tokio::test]
+async fn test_transfer_parallel() {
+    let (source, target) = (tempdir().unwrap(), tempdir().unwrap());
+    let data = artifact(100_000);
+    File::new(source.path()).write("build/app.bin", &data).await.unwrap();
+
+    let mut sender = Transfer::new(File::new(source.path()));
+    sender.set_chunk(4096);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    sender.on_progress(move |status| log.lock().unwrap().push(status));
+    let receiver = Transfer::new(File::new(target.path()));
+
+    let (mut out, mut inp) = pairs(4).await;
+    let (sent, received) = tokio::join!(
+        sender.send("build/app.bin", "app.bin", &mut out),
+        receiver.receive(&mut inp),
+    );
+    let (sent, received) = (sent.unwrap(), received.unwrap());
+
+    assert_eq!(sent.hash, received.hash);
+    assert_eq!((sent.moved, sent.resumed), (100_000, 0));
+    assert_eq!(File::new(target.path()).read("transfer/app.bin").await.unwrap(), data);
+    assert!(!File::new(target.path()).exists("transfer/app.bin.part").await.unwrap());
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 25);
+    assert_eq!(seen.last().unwrap().done, 100_000);
+}
+
+#[tokio::test]
+async fn test_transfer_resume() {
+    let (source, target) = (tempdir().unwrap(), tempdir().unwrap());
+    let data = artifact(10_000);
+    File::new(source.path()).write("app.bin", &data).await.unwrap();
+    let hash: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+
+    // A sender that drops the connection after three chunks
+    let (mut out, mut inp) = pairs(1).await;
+    let receiver = Transfer::new(File::new(target.path()));
+    let partial = async {
+        let offer = format!(r#"{{"type":"offer","name":"app.bin","size":10000,"chunk":1000,"hash":"{}"}}"#, hash);
+        out[0].send(offer.as_bytes()).await.unwrap();
+        let mut buf = vec![0; 1024];
+        out[0].receive(&mut buf).await.unwrap();
+        for index in 0..3u64 {
+            let chunk = &data[index as usize * 1000..(index as usize + 1) * 1000];
+            let mut frame = vec![1u8];
+            frame.extend_from_slice(&index.to_be_bytes());
+            frame.extend_from_slice(&Sha256::digest(chunk));
+            frame.extend_from_slice(chunk);
+            out[0].send(&frame).await.unwrap();
+        }
+        out[0].stop().await.unwrap();
+    };
+    let (_, result) = tokio::join!(partial, receiver.receive(&mut inp));
+    assert!(result.is_err());
+    assert!(File::new(target.path()).exists("transfer/app.bin.part.json").await.unwrap());
+
+    // The next attempt only moves what is missing
+    let mut sender = Transfer::new(File::new(source.path()));
+    sender.set_chunk(1000);
+    let (mut out, mut inp) = pairs(2).await;
+    let (sent, received) = tokio::join!(
+        sender.send("app.bin", "app.bin", &mut out),
+        receiver.receive(&mut inp),
+    );
+    let (sent, received) = (sent.unwrap(), received.unwrap());
+    assert_eq!((sent.moved, sent.resumed), (7000, 3000));
+    assert_eq!(received.resumed, 3000);
+    assert_eq!(File::new(target.path()).read("transfer/app.bin").await.unwrap(), data);
+    assert!(!File::new(target.path()).exists("transfer/app.bin.part.json").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_transfer_rejects_corrupt_chunk() {
+    let target = tempdir().unwrap();
+    let data = artifact(2000);
+    let hash: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+    let receiver = Transfer::new(File::new(target.path()));
+
+    let (mut out, mut inp) = pairs(1).await;
+    let corrupt = async {
+        let offer = format!(r#"{{"type":"offer","name":"app.bin","size":2000,"chunk":1000,"hash":"{}"}}"#, hash);
+        out[0].send(offer.as_bytes()).await.unwrap();
+        let mut buf = vec![0; 1024];
+        out[0].receive(&mut buf).await.unwrap();
+        for index in 0..2u64 {
+            let chunk = &data[index as usize * 1000..(index as usize + 1) * 1000];
+            let mut frame = vec![1u8];
+            frame.extend_from_slice(&index.to_be_bytes());
+            frame.extend_from_slice(&Sha256::digest(chunk));
+            frame.extend_from_slice(chunk);
+            // Flip a byte of the second chunk in flight
+            if index == 1 {
+                let last = frame.len() - 1;
+                frame[last] ^= 0xff;
+            }
+            out[0].send(&frame).await.unwrap();
+        }
+        out[0].send(&[2]).await.unwrap();
+        let n = out[0].receive(&mut buf).await.unwrap();
+        String::from_utf8(buf[..n].to_vec()).unwrap()
+    };
+    let (done, result) = tokio::join!(corrupt, receiver.receive(&mut inp));
+
+    assert!(matches!(result, Err(Error::Invalid { .. })));
+    assert!(done.contains(r#""ok":false"#));
+    assert!(!File::new(target.path()).exists("transfer/app.bin").await.unwrap());
+}
+
+/// Offer for `data` under `name`, split into 1000-byte chunks
+fn offer(name: &str, data: &[u8]) -> String {
+    let hash: String = Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect();
+    format!(r#"{{"type":"offer","name":"{}","size":{},"chunk":1000,"hash":"{}"}}"#, name, data.len(), hash)
+}
+
+#[tokio::test]
+async fn test_transfer_rejects_hostile_sender() {
+    let target = tempdir().unwrap();
+    let receiver = Transfer::new(File::new(target.path()));
+    let data = artifact(2000);
+
+    // Names that would leave the transfer directory
+    for name in ["../data.log", "ids/bans.json", ".."] {
+        let (mut out, mut inp) = pairs(1).await;
+        out[0].send(offer(name, &data).as_bytes()).await.unwrap();
+        assert!(matches!(receiver.receive(&mut inp).await, Err(Error::Protocol(_))));
+    }
+
+    // A chunk index past the end of the file
+    let (mut out, mut inp) = pairs(1).await;
+    let hostile = async {
+        out[0].send(offer("app.bin", &data).as_bytes()).await.unwrap();
+        let mut buf = vec![0; 1024];
+        out[0].receive(&mut buf).await.unwrap();
+        let mut frame = vec![1u8];
+        frame.extend_from_slice(&u64::MAX.to_be_bytes());
+        frame.extend_from_slice(&Sha256::digest([]));
+        out[0].send(&frame).await.unwrap();
+    };
+    let (_, result) = tokio::join!(hostile, receiver.receive(&mut inp));
+    assert!(matches!(result, Err(Error::Protocol(_))));
+}
+
+#[tokio::test]
+async fn test_transfer_ignores_invalid_progress() {
+    let (source, target) = (tempdir().unwrap(), tempdir().unwrap());
+    let data = artifact(2000);
+    File::new(source.path()).write("app.bin", &data).await.unwrap();
+    let hash: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Saved progress claiming chunks the file does not have
+    let state = format!(r#"{{"size":2000,"chunk":1000,"hash":"{}","verified":[0,1,2,3]}}"#, hash);
+    File::new(target.path()).write("transfer/app.bin.part.json", state.as_bytes()).await.unwrap();
+
+    let mut sender = Transfer::new(File::new(source.path()));
+    sender.set_chunk(1000);
+    let receiver = Transfer::new(File::new(target.path()));
+    let (mut out, mut inp) = pairs(1).await;
+    let (sent, received) = tokio::join!(
+        sender.send("app.bin", "app.bin", &mut out),
+        receiver.receive(&mut inp),
+    );
+    assert_eq!(sent.unwrap().moved, 2000);
+    assert_eq!(received.unwrap().resumed, 0);
+    assert_eq!(File::new(target.path()).read("transfer/app.bin").await.unwrap(), data);
+}
+
+#[tokio::test]
+async fn test_transfer_waits_for_slow_verification() {
+    let source = tempdir().unwrap();
+    File::new(source.path()).write("app.bin", &artifact(2000)).await.unwrap();
+    let mut sender = Transfer::new(File::new(source.path()));
+    sender.set_chunk(1000);
+    sender.set_finish(Duration::from_secs(5));
+
+    let (mut out, mut inp) = pairs(1).await;
+    let idle = Duration::from_millis(200);
+    let deadline = Deadline { idle, ..out[0].deadline() };
+    out[0].set_deadline(deadline);
+
+    // The receiver takes longer than the idle deadline to verify the file
+    let slow = async {
+        let mut buf = vec![0; 64 * 1024];
+        inp[0].receive(&mut buf).await.unwrap();
+        inp[0].send(br#"{"type":"have","ranges":[]}"#).await.unwrap();
+        while inp[0].receive(&mut buf).await.unwrap() != 1 {}
+        tokio::time::sleep(idle * 3).await;
+        inp[0].send(br#"{"type":"done","ok":true,"reason":null}"#).await.unwrap();
+    };
+    let (sent, _) = tokio::join!(sender.send("app.bin", "app.bin", &mut out), slow);
+    assert_eq!(sent.unwrap().moved, 2000);
+    assert_eq!(out[0].deadline(), deadline);
+}
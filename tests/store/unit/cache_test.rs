@@ -145,4 +145,129 @@ async fn test_cache_concurrent_access() {
         let result = cache.get(&key).await.unwrap();
         assert_eq!(result, Some(expected));
     }
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_cache_lru_eviction() {
+    use link::store::cache::{Bounds, Evict};
+
+    let bounds = Bounds { entries: Some(2), bytes: None };
+    let cache = Cache::bounded(Duration::from_secs(60), bounds, Evict::Lru);
+    cache.set("a", b"1".to_vec()).await.unwrap();
+    cache.set("b", b"2".to_vec()).await.unwrap();
+
+    // Reading "a" makes "b" the least recently used
+    assert!(cache.get("a").await.unwrap().is_some());
+    cache.set("c", b"3".to_vec()).await.unwrap();
+
+    assert!(cache.get("b").await.unwrap().is_none());
+    assert!(cache.get("a").await.unwrap().is_some());
+    assert!(cache.get("c").await.unwrap().is_some());
+    assert_eq!(cache.len().await, 2);
+}
+
+#[tokio::test]
+async fn test_cache_lfu_eviction() {
+    use link::store::cache::{Bounds, Evict};
+
+    let bounds = Bounds { entries: Some(2), bytes: None };
+    let cache = Cache::bounded(Duration::from_secs(60), bounds, Evict::Lfu);
+    cache.set("a", b"1".to_vec()).await.unwrap();
+    cache.set("b", b"2".to_vec()).await.unwrap();
+    for _ in 0..3 {
+        cache.get("a").await.unwrap();
+    }
+    cache.get("b").await.unwrap();
+
+    // "b" was read more recently but less often
+    cache.set("c", b"3".to_vec()).await.unwrap();
+    assert!(cache.get("a").await.unwrap().is_some());
+    assert!(cache.get("b").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_cache_byte_bound() {
+    use link::core::error::Error;
+    use link::store::cache::{Bounds, Evict};
+
+    let bounds = Bounds { entries: None, bytes: Some(10) };
+    let cache = Cache::bounded(Duration::from_secs(60), bounds, Evict::Lru);
+    cache.set("a", vec![0; 4]).await.unwrap();
+    cache.set("b", vec![0; 4]).await.unwrap();
+    assert_eq!(cache.stats().await.bytes, 10);
+
+    cache.set("c", vec![0; 2]).await.unwrap();
+    assert!(cache.get("a").await.unwrap().is_none());
+    assert_eq!(cache.stats().await.bytes, 8);
+
+    let result = cache.set("d", vec![0; 10]).await;
+    assert!(matches!(result, Err(Error::Capacity(_))));
+}
+
+#[tokio::test]
+async fn test_cache_entry_ttl_and_stats() {
+    let cache = Cache::new(Duration::from_secs(60));
+    cache.set_for("short", b"value".to_vec(), Duration::from_millis(50)).await.unwrap();
+    cache.set("long", b"value".to_vec()).await.unwrap();
+
+    assert!(cache.get("short").await.unwrap().is_some());
+    sleep(Duration::from_millis(80)).await;
+    assert!(cache.get("short").await.unwrap().is_none());
+    assert!(cache.get("long").await.unwrap().is_some());
+    assert!(cache.get("missing").await.unwrap().is_none());
+
+    let stats = cache.stats().await;
+    assert_eq!((stats.hits, stats.misses, stats.expirations), (2, 2, 1));
+    assert_eq!(stats.entries, 1);
+}
+
+#[tokio::test]
+async fn test_cache_sweeper() {
+    use link::core::link::Linkable;
+    use link::core::state::Mode;
+
+    let mut cache = Cache::new(Duration::from_millis(30));
+    cache.set_interval(Duration::from_millis(20));
+    cache.set("key1", b"value1".to_vec()).await.unwrap();
+    cache.set("key2", b"value2".to_vec()).await.unwrap();
+
+    cache.start().await.unwrap();
+    assert_eq!(cache.state().await.unwrap(), Mode::Ready);
+    sleep(Duration::from_millis(100)).await;
+
+    // Expired without any reader touching them
+    assert!(cache.is_empty().await);
+    assert_eq!(cache.stats().await.expirations, 2);
+
+    cache.stop().await.unwrap();
+    assert_eq!(cache.state().await.unwrap(), Mode::Close);
+}
+
+#[tokio::test]
+async fn test_cache_sharded_bounds() {
+    use link::store::cache::{Bounds, Evict};
+
+    let bounds = Bounds { entries: Some(64), bytes: None };
+    let cache = Cache::sharded(Duration::from_secs(60), bounds, Evict::Lru, 8);
+    for i in 0..500 {
+        cache.set(&format!("key{}", i), vec![0; 8]).await.unwrap();
+    }
+    let stats = cache.stats().await;
+    assert!(stats.entries <= 64);
+    assert_eq!(stats.evictions as usize, 500 - stats.entries);
+}
+
+#[tokio::test]
+async fn test_cache_unbounded_ttl() {
+    let cache = Cache::new(Duration::MAX);
+    cache.set("forever", b"value".to_vec()).await.unwrap();
+    cache.set_for("short", b"value".to_vec(), Duration::from_millis(50)).await.unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    // Only the entry with a representable deadline expires
+    assert_eq!(cache.cleanup().await.unwrap(), 1);
+    assert_eq!(cache.get("forever").await.unwrap(), Some(b"value".to_vec()));
+    assert_eq!(cache.keys("").await, vec!["forever".to_string()]);
+    cache.remove("forever").await.unwrap();
+    assert_eq!(cache.get("forever").await.unwrap(), None);
+}
@@ -112,4 +112,131 @@ async fn test_data_empty_values() {
     data.set("", b"value".to_vec()).await.unwrap();
     let result = data.get("").await.unwrap();
     assert_eq!(result, Some(b"value".to_vec()));
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_data_journal_recovery() {
+    use link::store::File;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    {
+        let data = Data::open(&file, "data/peers.log").await.unwrap();
+        data.set("alice", b"10.0.0.1".to_vec()).await.unwrap();
+        data.set("bob", b"10.0.0.2".to_vec()).await.unwrap();
+        data.set("alice", b"10.0.0.3".to_vec()).await.unwrap();
+        data.remove("bob").await.unwrap();
+    }
+
+    let data = Data::open(&file, "data/peers.log").await.unwrap();
+    assert_eq!(data.get("alice").await.unwrap(), Some(b"10.0.0.3".to_vec()));
+    assert_eq!(data.get("bob").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_data_journal_torn_tail() {
+    use link::store::File;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    {
+        let data = Data::open(&file, "peers.log").await.unwrap();
+        data.set("alice", b"one".to_vec()).await.unwrap();
+        data.set("bob", b"two".to_vec()).await.unwrap();
+    }
+
+    // Simulate a crash in the middle of the last record
+    let mut log = file.read("peers.log").await.unwrap();
+    log.truncate(log.len() - 2);
+    file.write("peers.log", &log).await.unwrap();
+
+    let data = Data::open(&file, "peers.log").await.unwrap();
+    assert_eq!(data.get("alice").await.unwrap(), Some(b"one".to_vec()));
+    assert_eq!(data.get("bob").await.unwrap(), None);
+
+    // New writes go after the last intact record
+    data.set("carol", b"three".to_vec()).await.unwrap();
+    drop(data);
+    let data = Data::open(&file, "peers.log").await.unwrap();
+    assert_eq!(data.get("carol").await.unwrap(), Some(b"three".to_vec()));
+}
+
+#[tokio::test]
+async fn test_data_journal_compaction() {
+    use link::store::File;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    let data = Data::open(&file, "routes.log").await.unwrap();
+    data.set_compact(256).await;
+
+    for i in 0..100 {
+        data.set("route", format!("value{}", i).into_bytes()).await.unwrap();
+    }
+    data.set("other", b"kept".to_vec()).await.unwrap();
+
+    // Only the live entries remain after compaction
+    let size = file.read("routes.log").await.unwrap().len();
+    assert!(size < 256, "journal is {} bytes", size);
+    let names: Vec<_> = std::fs::read_dir(temp_dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(names, ["routes.log"]);
+
+    data.clear().await.unwrap();
+    data.set("route", b"last".to_vec()).await.unwrap();
+    drop(data);
+    let data = Data::open(&file, "routes.log").await.unwrap();
+    assert_eq!(data.get("route").await.unwrap(), Some(b"last".to_vec()));
+    assert_eq!(data.get("other").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_data_sealed_journal() {
+    use std::sync::Arc;
+    use link::core::error::Error;
+    use link::guard::Crypt;
+    use link::store::File;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    let crypt = Arc::new(Crypt::new(b"journal secret"));
+    {
+        let data = Data::sealed(&file, "tokens.log", crypt.clone()).await.unwrap();
+        data.set("peer", b"token-1234".to_vec()).await.unwrap();
+        data.batch(vec![link::core::link::Op::Put("other".into(), b"token-5678".to_vec())]).await.unwrap();
+    }
+
+    // Nothing is readable on disk
+    let log = file.read("tokens.log").await.unwrap();
+    assert!(!log.windows(10).any(|w| w == b"token-1234" || w == b"token-5678"));
+
+    let data = Data::sealed(&file, "tokens.log", crypt).await.unwrap();
+    assert_eq!(data.get("peer").await.unwrap(), Some(b"token-1234".to_vec()));
+    assert_eq!(data.get("other").await.unwrap(), Some(b"token-5678".to_vec()));
+    drop(data);
+
+    // A wrong key fails recovery instead of discarding the journal
+    let wrong = Arc::new(Crypt::new(b"other secret"));
+    let result = Data::sealed(&file, "tokens.log", wrong).await;
+    assert!(matches!(result, Err(Error::Decrypt(_))));
+    assert_eq!(file.read("tokens.log").await.unwrap(), log);
+}
+
+#[tokio::test]
+async fn test_data_compaction_failure() {
+    use link::store::File;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    let data = Data::open(&file, "data/routes.log").await.unwrap();
+    data.set_compact(64).await;
+
+    // Compaction cannot create its temporary file, but the writes still succeed
+    std::fs::remove_dir_all(temp_dir.path().join("data")).unwrap();
+    for i in 0..20 {
+        data.set("route", format!("value{}", i).into_bytes()).await.unwrap();
+    }
+    data.remove("route").await.unwrap();
+    data.set("other", b"kept".to_vec()).await.unwrap();
+    assert_eq!(data.get("other").await.unwrap(), Some(b"kept".to_vec()));
+    assert!(data.compact().await.is_err());
+}
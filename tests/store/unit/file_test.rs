@@ -1,5 +1,7 @@
 use tempfile::tempdir;
 use link::store::File;
+use link::core::error::Error;
+use link::core::link::Storable;
 
 #[tokio::test]
 async fn test_file_basic_operations() {
@@ -68,11 +70,11 @@ async fn test_file_error_handling() {
     
     // Test reading non-existent file
     let result = file_store.read("non_existent.txt").await;
-    assert!(result.is_err());
+    assert!(matches!(result, Err(Error::NotFound(_))));
     
     // Test removing non-existent file
     let result = file_store.remove("non_existent.txt").await;
-    assert!(result.is_err());
+    assert!(matches!(result, Err(Error::NotFound(_))));
     
     // Test writing to invalid path
     let result = file_store.write("", b"test").await;
@@ -147,4 +149,153 @@ async fn test_file_large_data() {
     
     assert_eq!(result.len(), data.len());
     assert_eq!(result, data);
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_file_rejects_escapes() {
+    let temp_dir = tempdir().unwrap();
+    let file_store = File::new(temp_dir.path().join("root"));
+
+    for path in ["../outside.txt", "a/../../outside.txt", "/etc/passwd", ""] {
+        let result = file_store.write(path, b"data").await;
+        assert!(matches!(result, Err(Error::Denied(_))), "{:?} was accepted", path);
+    }
+    assert!(matches!(file_store.read("../outside.txt").await, Err(Error::Denied(_))));
+    assert!(!temp_dir.path().join("outside.txt").exists());
+
+    // Dots that stay inside the root are fine
+    file_store.write("./a/b.txt", b"data").await.unwrap();
+    assert_eq!(file_store.read("a/b.txt").await.unwrap(), b"data");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_file_rejects_symlinks() {
+    let temp_dir = tempdir().unwrap();
+    let outside = temp_dir.path().join("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(outside.join("secret.txt"), b"secret").unwrap();
+    let root = temp_dir.path().join("root");
+    std::fs::create_dir_all(&root).unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+
+    let file_store = File::new(&root);
+    assert!(matches!(file_store.read("link/secret.txt").await, Err(Error::Denied(_))));
+    assert!(matches!(file_store.write("link/new.txt", b"data").await, Err(Error::Denied(_))));
+    assert!(!outside.join("new.txt").exists());
+}
+
+#[tokio::test]
+async fn test_file_atomic_write_leaves_no_temp() {
+    let temp_dir = tempdir().unwrap();
+    let file_store = File::new(temp_dir.path());
+
+    for i in 0..10 {
+        file_store.write("state.json", format!("version {}", i).as_bytes()).await.unwrap();
+    }
+    assert_eq!(file_store.read("state.json").await.unwrap(), b"version 9");
+    assert_eq!(file_store.list("").await.unwrap(), vec!["state.json"]);
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn test_file_list_walk_metadata() {
+    let temp_dir = tempdir().unwrap();
+    let file_store = File::new(temp_dir.path());
+    file_store.write("b.txt", b"12345").await.unwrap();
+    file_store.write("a/one.txt", b"1").await.unwrap();
+    file_store.write("a/deep/two.txt", b"22").await.unwrap();
+
+    assert_eq!(file_store.list("").await.unwrap(), vec!["a", "b.txt"]);
+    assert_eq!(file_store.list("a").await.unwrap(), vec!["deep", "one.txt"]);
+    assert_eq!(file_store.walk("").await.unwrap(), vec!["a/deep/two.txt", "a/one.txt", "b.txt"]);
+    assert_eq!(file_store.walk("a/deep").await.unwrap(), vec!["a/deep/two.txt"]);
+
+    let meta = file_store.metadata("b.txt").await.unwrap();
+    assert_eq!(meta.size, 5);
+    assert!(!meta.dir);
+    assert!(meta.modified <= std::time::SystemTime::now());
+    assert!(file_store.metadata("a").await.unwrap().dir);
+    assert!(matches!(file_store.metadata("missing").await, Err(Error::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_file_streaming() {
+    use sha2::{Digest, Sha256};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let temp_dir = tempdir().unwrap();
+    let file_store = File::new(temp_dir.path());
+    let chunk: Vec<u8> = (0..=255u8).collect();
+    let expected = Sha256::digest(chunk.repeat(64));
+    let expected: String = expected.iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Not visible until finished
+    let mut writer = file_store.create("artifact.bin").await.unwrap();
+    for _ in 0..64 {
+        writer.write_all(&chunk).await.unwrap();
+    }
+    assert!(!file_store.exists("artifact.bin").await.unwrap());
+    assert_eq!(writer.size(), 64 * 256);
+    assert_eq!(writer.finish().await.unwrap(), expected);
+
+    let mut reader = file_store.open("artifact.bin").await.unwrap();
+    let mut buf = vec![0; 1000];
+    let mut total = 0;
+    loop {
+        let n = reader.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        total += n;
+    }
+    assert_eq!(total, 64 * 256);
+    assert_eq!(reader.digest(), expected);
+    assert_eq!(file_store.hash("artifact.bin").await.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_file_abandoned_writer() {
+    use tokio::io::AsyncWriteExt;
+
+    let temp_dir = tempdir().unwrap();
+    let file_store = File::new(temp_dir.path());
+    file_store.write("state.json", b"old").await.unwrap();
+
+    let mut writer = file_store.create("state.json").await.unwrap();
+    writer.write_all(b"new but incomplete").await.unwrap();
+    drop(writer);
+
+    assert_eq!(file_store.read("state.json").await.unwrap(), b"old");
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn test_file_lists_partial_names() {
+    let temp_dir = tempdir().unwrap();
+    let file_store = File::new(temp_dir.path());
+    file_store.write("video.partial", b"user data").await.unwrap();
+    file_store.write("dir/.notes.partial", b"user data").await.unwrap();
+
+    // Only names of writes in progress are hidden
+    let _writer = file_store.create("dir/state.json").await.unwrap();
+    assert_eq!(file_store.list("").await.unwrap(), vec!["dir", "video.partial"]);
+    assert_eq!(file_store.list("dir").await.unwrap(), vec![".notes.partial"]);
+    assert_eq!(file_store.walk("").await.unwrap(), vec!["dir/.notes.partial", "video.partial"]);
+    assert_eq!(Storable::list(&file_store, "").await.unwrap(), vec!["dir/.notes.partial", "video.partial"]);
+}
+
+#[tokio::test]
+async fn test_file_range_and_append() {
+    let temp_dir = tempdir().unwrap();
+    let file_store = File::new(temp_dir.path());
+
+    assert_eq!(file_store.append("log/events", b"0123").await.unwrap(), 4);
+    assert_eq!(file_store.append("log/events", b"456789").await.unwrap(), 10);
+    assert_eq!(file_store.read("log/events").await.unwrap(), b"0123456789");
+
+    assert_eq!(file_store.read_range("log/events", 2, 3).await.unwrap(), b"234");
+    assert_eq!(file_store.read_range("log/events", 8, 100).await.unwrap(), b"89");
+    assert!(file_store.read_range("log/events", 20, 5).await.unwrap().is_empty());
+    assert!(matches!(file_store.read_range("missing", 0, 1).await, Err(Error::NotFound(_))));
+}
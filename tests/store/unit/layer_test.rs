@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use tempfile::tempdir;
+use link::core::error::Result;
+use link::core::link::{Op, Storable};
+use link::store::{Cache, Data, File, Layer};
+
+/// Exercises the behaviour every backend must share
+async fn contract(store: &dyn Storable) {
+    assert_eq!(store.get("peers/alice").await.unwrap(), None);
+    assert!(!store.exists("peers/alice").await.unwrap());
+
+    store.put("peers/alice", b"one".to_vec()).await.unwrap();
+    store.put("peers/bob", b"two".to_vec()).await.unwrap();
+    store.put("routes/web", b"three".to_vec()).await.unwrap();
+    assert_eq!(store.get("peers/alice").await.unwrap(), Some(b"one".to_vec()));
+    assert!(store.exists("peers/bob").await.unwrap());
+    assert_eq!(store.list("peers/").await.unwrap(), vec!["peers/alice", "peers/bob"]);
+    assert_eq!(store.list("").await.unwrap().len(), 3);
+
+    store.delete("peers/bob").await.unwrap();
+    store.delete("peers/missing").await.unwrap();
+    assert!(!store.exists("peers/bob").await.unwrap());
+
+    store.batch(vec![
+        Op::Put("peers/carol".into(), b"four".to_vec()),
+        Op::Delete("routes/web".into()),
+    ]).await.unwrap();
+    assert_eq!(store.list("").await.unwrap(), vec!["peers/alice", "peers/carol"]);
+}
+
+#[tokio::test]
+async fn test_storable_backends() {
+    let temp_dir = tempdir().unwrap();
+    let backends: Vec<Arc<dyn Storable>> = vec![
+        Arc::new(Data::new()),
+        Arc::new(Data::open(&File::new(temp_dir.path()), "journal/data.log").await.unwrap()),
+        Arc::new(Cache::new(Duration::from_secs(60))),
+        Arc::new(File::new(temp_dir.path().join("files"))),
+        Arc::new(Layer::new(Cache::new(Duration::from_secs(60)), Data::new())),
+    ];
+    for backend in backends {
+        contract(backend.as_ref()).await;
+    }
+}
+
+#[tokio::test]
+async fn test_layer_read_through() {
+    let layer = Layer::new(Cache::new(Duration::from_secs(60)), Data::new());
+
+    // Written behind the cache's back
+    layer.back().set("route", b"value".to_vec()).await.unwrap();
+    assert_eq!(layer.front().get("route").await.unwrap(), None);
+
+    assert_eq!(Storable::get(&layer, "route").await.unwrap(), Some(b"value".to_vec()));
+    assert_eq!(layer.front().get("route").await.unwrap(), Some(b"value".to_vec()));
+
+    // Deletes reach both layers
+    layer.delete("route").await.unwrap();
+    assert_eq!(layer.front().get("route").await.unwrap(), None);
+    assert_eq!(layer.back().get("route").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_layer_front_best_effort() {
+    // A front whose root is a plain file fails every call
+    let temp_dir = tempdir().unwrap();
+    let blocker = temp_dir.path().join("blocker");
+    std::fs::write(&blocker, b"").unwrap();
+    let layer = Layer::new(File::new(&blocker), Data::new());
+
+    layer.put("route", b"value".to_vec()).await.unwrap();
+    assert_eq!(Storable::get(&layer, "route").await.unwrap(), Some(b"value".to_vec()));
+    assert!(layer.exists("route").await.unwrap());
+    layer.batch(vec![Op::Put("other".into(), b"x".to_vec())]).await.unwrap();
+    layer.delete("route").await.unwrap();
+    assert_eq!(layer.list("").await.unwrap(), vec!["other"]);
+}
+
+/// Back store whose reads take a while, so writes can land during them
+struct Slow(Data);
+
+#[async_trait]
+impl Storable for Slow {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let value = self.0.get(key).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        value
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.0.set(key, value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.0.remove(key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Storable::exists(&self.0, key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.0.keys(prefix).await)
+    }
+
+    async fn batch(&self, ops: Vec<Op>) -> Result<()> {
+        self.0.batch(ops).await
+    }
+}
+
+#[tokio::test]
+async fn test_layer_read_races_write() {
+    let layer = Arc::new(Layer::new(Cache::new(Duration::from_secs(60)), Slow(Data::new())));
+    layer.back().0.set("route", b"old".to_vec()).await.unwrap();
+
+    // The read fetches the old value, then a write lands before it fills the front
+    let reader = layer.clone();
+    let read = tokio::spawn(async move { Storable::get(&*reader, "route").await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    layer.put("route", b"new".to_vec()).await.unwrap();
+    assert_eq!(read.await.unwrap().unwrap(), Some(b"old".to_vec()));
+
+    assert_ne!(layer.front().get("route").await.unwrap(), Some(b"old".to_vec()));
+    assert_eq!(Storable::get(&*layer, "route").await.unwrap(), Some(b"new".to_vec()));
+}
+
+#[tokio::test]
+async fn test_data_batch_durable() {
+    let temp_dir = tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    {
+        let data = Data::open(&file, "data.log").await.unwrap();
+        data.set("stale", b"x".to_vec()).await.unwrap();
+        data.batch(vec![
+            Op::Put("alice".into(), b"1".to_vec()),
+            Op::Put("bob".into(), b"2".to_vec()),
+            Op::Delete("stale".into()),
+        ]).await.unwrap();
+    }
+
+    let data = Data::open(&file, "data.log").await.unwrap();
+    assert_eq!(data.keys("").await, vec!["alice", "bob"]);
+}
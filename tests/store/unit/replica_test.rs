@@ -0,0 +1,259 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use link::core::error::Error;
+use link::core::link::{Linkable, Movable, Settings};
+use link::guard::Crypt;
+use link::net::Socket;
+use link::net::route::{Entry, Route};
+use link::store::Replica;
+
+/// Starts `n` nodes on loopback, named `node0`, `node1`, ..., with no peers yet
+async fn nodes(n: usize) -> Vec<Replica> {
+    let mut nodes = Vec::new();
+    for i in 0..n {
+        let settings = Settings { name: format!("node{}", i), wait: 5, ..Settings::default() };
+        let mut node = Replica::new("127.0.0.1:0", settings);
+        node.set_interval(Duration::from_millis(100));
+        node.start().await.unwrap();
+        nodes.push(node);
+    }
+    nodes
+}
+
+/// Makes every node a peer of every other one
+fn mesh(nodes: &[Replica]) {
+    for node in nodes {
+        for other in nodes {
+            if node.name() != other.name() {
+                node.add_peer(&other.local_addr().unwrap().to_string());
+            }
+        }
+    }
+}
+
+/// Waits until `node` holds `value` for `key`
+async fn until(node: &Replica, key: &str, value: Option<&[u8]>) {
+    for _ in 0..100 {
+        if node.get(key).await.unwrap().as_deref() == value {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{} did not converge on {}", node.name(), key);
+}
+
+#[tokio::test]
+async fn test_replica_propagates_writes() {
+    let nodes = nodes(3).await;
+    mesh(&nodes);
+
+    // A peer registration on one relay is visible on the others
+    nodes[0].set("peer/alice", b"10.0.0.1:7000".to_vec()).await.unwrap();
+    for node in &nodes {
+        until(node, "peer/alice", Some(b"10.0.0.1:7000")).await;
+    }
+
+    nodes[1].remove("peer/alice").await.unwrap();
+    for node in &nodes {
+        until(node, "peer/alice", None).await;
+        assert!(node.keys("peer/").await.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_replica_catches_up() {
+    let nodes = nodes(2).await;
+
+    // Writes made while the nodes did not know each other
+    nodes[0].set("peer/alice", b"a".to_vec()).await.unwrap();
+    nodes[1].set("peer/bob", b"b".to_vec()).await.unwrap();
+    assert_eq!(nodes[1].get("peer/alice").await.unwrap(), None);
+
+    mesh(&nodes);
+    assert_eq!(nodes[0].sync().await, 1);
+    for node in &nodes {
+        assert_eq!(node.keys("peer/").await, vec!["peer/alice", "peer/bob"]);
+    }
+}
+
+#[tokio::test]
+async fn test_replica_last_writer_wins() {
+    let nodes = nodes(3).await;
+
+    // Conflicting writes and a delete, all made before the nodes are connected
+    nodes[0].set("route", b"first".to_vec()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    nodes[1].set("route", b"second".to_vec()).await.unwrap();
+    nodes[2].set("gone", b"old".to_vec()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    nodes[0].remove("gone").await.unwrap();
+
+    // The periodic rounds converge every node on the latest write
+    mesh(&nodes);
+    for node in &nodes {
+        until(node, "route", Some(b"second")).await;
+        until(node, "gone", None).await;
+    }
+}
+
+#[tokio::test]
+async fn test_replica_unreachable_peer() {
+    let nodes = nodes(1).await;
+    nodes[0].add_peer("127.0.0.1:1");
+
+    // Writes stay local when a member is down
+    nodes[0].set("key", b"value".to_vec()).await.unwrap();
+    assert_eq!(nodes[0].get("key").await.unwrap(), Some(b"value".to_vec()));
+    assert_eq!(nodes[0].sync().await, 0);
+}
+
+/// Sends one update for `key` stamped `time` without a guard, claiming to listen on `port`
+async fn stranger(node: &Replica, port: u16, key: &str, time: u64) {
+    let mut socket = Socket::connect(node.local_addr().unwrap(), Settings::default()).await.unwrap();
+    socket.start().await.unwrap();
+    socket.send(format!(r#"{{"type":"hello","port":{}}}"#, port).as_bytes()).await.unwrap();
+    let update = format!(r#"{{"type":"update","entries":[["{}",{{"value":[1],"time":{},"node":"mallory"}}]]}}"#, key, time);
+    socket.send(update.as_bytes()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+/// Starts `n` nodes that protect their messages with a shared key
+async fn nodes_guarded(n: usize) -> Vec<Replica> {
+    let mut nodes = Vec::new();
+    for i in 0..n {
+        let settings = Settings { name: format!("node{}", i), wait: 5, ..Settings::default() };
+        let mut node = Replica::new("127.0.0.1:0", settings);
+        node.set_guard(Arc::new(Crypt::new(b"cluster key")));
+        node.start().await.unwrap();
+        nodes.push(node);
+    }
+    nodes
+}
+
+#[tokio::test]
+async fn test_replica_refuses_strangers() {
+    // Only connections from configured members are served, matched by host and port
+    let nodes = nodes(1).await;
+    nodes[0].add_peer("192.0.2.1:7000");
+    nodes[0].add_peer("127.0.0.1:1");
+    stranger(&nodes[0], 7000, "peer/mallory", now()).await;
+    stranger(&nodes[0], 2, "peer/mallory", now()).await;
+    assert_eq!(nodes[0].get("peer/mallory").await.unwrap(), None);
+
+    // With a guard, members on the same host still need the cluster key
+    let mut nodes = nodes_guarded(2).await;
+    mesh(&nodes);
+    let port = nodes[1].local_addr().unwrap().port();
+    stranger(&nodes[0], port, "peer/mallory", now()).await;
+    assert_eq!(nodes[0].get("peer/mallory").await.unwrap(), None);
+
+    nodes[0].set("peer/alice", b"a".to_vec()).await.unwrap();
+    until(&nodes[1], "peer/alice", Some(b"a")).await;
+    for node in &mut nodes {
+        node.stop().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_replica_bounds_remote_clock() {
+    let nodes = nodes(1).await;
+    nodes[0].add_peer("127.0.0.1:1");
+
+    // A write stamped far in the future is dropped and does not stall the clock
+    stranger(&nodes[0], 1, "peer/future", u64::MAX).await;
+    assert_eq!(nodes[0].get("peer/future").await.unwrap(), None);
+    nodes[0].set("peer/local", b"ok".to_vec()).await.unwrap();
+
+    stranger(&nodes[0], 1, "peer/present", now()).await;
+    assert_eq!(nodes[0].get("peer/present").await.unwrap(), Some(vec![1]));
+}
+
+#[tokio::test]
+async fn test_replica_pages_large_contents() {
+    let mut nodes = Vec::new();
+    for i in 0..2 {
+        let settings = Settings { name: format!("node{}", i), size: 4096, wait: 5 };
+        let mut node = Replica::new("127.0.0.1:0", settings);
+        node.start().await.unwrap();
+        nodes.push(node);
+    }
+
+    // Far more than one frame of contents catches up in pages
+    for i in 0..50 {
+        nodes[0].set(&format!("peer/{:02}", i), vec![7; 300]).await.unwrap();
+    }
+    mesh(&nodes);
+    assert_eq!(nodes[1].sync().await, 1);
+    assert_eq!(nodes[1].keys("peer/").await.len(), 50);
+
+    // A single record that can never fit a frame is refused up front
+    let result = nodes[0].set("peer/huge", vec![7; 8192]).await;
+    assert!(matches!(result, Err(Error::Capacity(_))));
+    assert_eq!(nodes[0].get("peer/huge").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_replica_collects_tombstones() {
+    let settings = Settings { name: "node0".into(), ..Settings::default() };
+    let mut node = Replica::new("127.0.0.1:0", settings);
+    node.set_horizon(Duration::from_millis(50));
+
+    node.set("peer/alice", b"a".to_vec()).await.unwrap();
+    node.remove("peer/alice").await.unwrap();
+    assert_eq!(node.collect(), 0);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(node.collect(), 1);
+    assert_eq!(node.get("peer/alice").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_replica_shares_routes() {
+    let nodes = nodes(2).await;
+    mesh(&nodes);
+    let routes: Vec<Route> = nodes.iter().map(|_| Route::new(Settings::default())).collect();
+
+    // Entries already in a table are registered when it is attached
+    let entry = Entry { addr: "10.0.0.1:7000".into(), weight: 1 };
+    routes[0].add("alice".into(), entry.clone()).await.unwrap();
+    for (node, route) in nodes.iter().zip(&routes) {
+        node.attach(route.clone()).await.unwrap();
+    }
+
+    // A registration on one relay reaches the route table of the other
+    nodes[0].register("bob", &Entry { addr: "10.0.0.2:7000".into(), weight: 2 }).await.unwrap();
+    until(&nodes[1], "route/bob", Some(br#"{"addr":"10.0.0.2:7000","weight":2}"#)).await;
+    assert_eq!(routes[1].get("bob").await.unwrap().weight, 2);
+    until(&nodes[1], "route/alice", Some(br#"{"addr":"10.0.0.1:7000","weight":1}"#)).await;
+    assert_eq!(routes[1].get("alice").await.unwrap(), entry);
+
+    nodes[1].unregister("alice").await.unwrap();
+    until(&nodes[0], "route/alice", None).await;
+    assert!(matches!(routes[0].get("alice").await, Err(Error::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_replica_pushes_over_one_connection() {
+    let settings = Settings { name: "node0".into(), wait: 5, ..Settings::default() };
+    let mut node = Replica::new("127.0.0.1:0", settings);
+    node.start().await.unwrap();
+    let peer = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    node.add_peer(&peer.local_addr().unwrap().to_string());
+
+    // A burst of writes is queued behind the first push instead of opening a connection each
+    for i in 0..50 {
+        node.set(&format!("peer/{}", i), b"x".to_vec()).await.unwrap();
+    }
+    let mut connections = 0;
+    while let Ok(Ok((mut stream, _))) = tokio::time::timeout(Duration::from_millis(300), peer.accept()).await {
+        connections += 1;
+        tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut stream, &mut tokio::io::sink()).await;
+        });
+    }
+    assert!((1..=2).contains(&connections), "{} connections", connections);
+    node.stop().await.unwrap();
+}
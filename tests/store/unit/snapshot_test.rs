@@ -0,0 +1,115 @@
+use std::time::Duration;
+use tempfile::tempdir;
+use link::core::error::Error;
+use link::core::export::Registry;
+use link::core::link::Settings;
+use link::core::State;
+use link::net::route::{Entry, Route};
+use link::store::{Data, File, Snapshot};
+
+async fn node() -> (Route, Data, Registry) {
+    let route = Route::new(Settings::default());
+    route.add("api".into(), Entry { addr: "10.0.0.1:8080".into(), weight: 2 }).await.unwrap();
+    route.add("db".into(), Entry { addr: "10.0.0.2:5432".into(), weight: 1 }).await.unwrap();
+
+    let data = Data::new();
+    data.set("peer/alice", vec![0, 1, 255]).await.unwrap();
+    data.set("peer/bob", b"token".to_vec()).await.unwrap();
+
+    let registry = Registry::new();
+    let state = State::new();
+    state.record_send(100).await.unwrap();
+    state.record_fault(&Error::Timeout("connect".into())).await.unwrap();
+    state.record_rtt(Duration::from_millis(20)).await.unwrap();
+    registry.register("tunnel", state).await;
+    (route, data, registry)
+}
+
+#[tokio::test]
+async fn test_snapshot_round_trip() {
+    let (route, data, registry) = node().await;
+    let temp_dir = tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    Snapshot::capture(&route, &data, &registry).await.unwrap()
+        .save(&file, "backup/node.snapshot").await.unwrap();
+
+    // Restore onto a fresh node with stale contents of its own
+    let target = Route::new(Settings::default());
+    target.add("old".into(), Entry { addr: "10.9.9.9:1".into(), weight: 1 }).await.unwrap();
+    let target_data = Data::new();
+    target_data.set("stale", b"x".to_vec()).await.unwrap();
+    let target_registry = Registry::new();
+
+    let snapshot = Snapshot::load(&file, "backup/node.snapshot").await.unwrap();
+    snapshot.restore(&target, &target_data, &target_registry).await.unwrap();
+
+    let mut routes = target.list().await.unwrap();
+    routes.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut expected = route.list().await.unwrap();
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(routes, expected);
+    assert_eq!(target_data.entries("").await, data.entries("").await);
+
+    let measure = target_registry.get("tunnel").await.unwrap().measure().await.unwrap();
+    assert_eq!(measure.send, 100);
+    assert_eq!(measure.frames.send, 1);
+    assert_eq!(measure.errors.get("timeout"), Some(&1));
+    assert_eq!(measure.rtt, Some(Duration::from_millis(20)));
+    assert_eq!(measure.latency.count, 1);
+}
+
+#[tokio::test]
+async fn test_snapshot_rejects_damaged_archive() {
+    let (route, data, registry) = node().await;
+    let archive = Snapshot::capture(&route, &data, &registry).await.unwrap().encode().unwrap();
+
+    // Any change to the body breaks the digest
+    let mut damaged = archive.clone();
+    let last = damaged.len() - 2;
+    damaged[last] ^= 1;
+    assert!(matches!(Snapshot::decode(&damaged), Err(Error::Invalid { rule, .. }) if rule == "digest"));
+
+    let text = String::from_utf8(archive).unwrap().replacen("\"version\":1", "\"version\":99", 1);
+    assert!(matches!(Snapshot::decode(text.as_bytes()), Err(Error::Invalid { rule, .. }) if rule == "version"));
+
+    assert!(matches!(Snapshot::decode(b"not an archive"), Err(Error::Invalid { rule, .. }) if rule == "format"));
+}
+
+#[tokio::test]
+async fn test_snapshot_validates_before_restore() {
+    let (route, data, registry) = node().await;
+    let mut snapshot = Snapshot::capture(&route, &data, &registry).await.unwrap();
+    snapshot.measures.get_mut("tunnel").unwrap().latency.counts.pop();
+
+    // Invalid snapshots are refused on encode-decode and leave the node untouched
+    let archive = snapshot.encode().unwrap();
+    assert!(matches!(Snapshot::decode(&archive), Err(Error::Invalid { rule, .. }) if rule == "measure"));
+
+    let target = Route::new(Settings::default());
+    target.add("old".into(), Entry { addr: "10.9.9.9:1".into(), weight: 1 }).await.unwrap();
+    let target_data = Data::new();
+    target_data.set("stale", b"x".to_vec()).await.unwrap();
+    let result = snapshot.restore(&target, &target_data, &Registry::new()).await;
+    assert!(matches!(result, Err(Error::Invalid { .. })));
+    assert_eq!(target.list().await.unwrap().len(), 1);
+    assert_eq!(target_data.keys("").await, vec!["stale"]);
+}
+
+#[tokio::test]
+async fn test_snapshot_round_trips_whatever_the_node_accepts() {
+    let (route, data, registry) = node().await;
+    route.add("local".into(), Entry { addr: "/run/link.sock".into(), weight: 1 }).await.unwrap();
+    route.add("".into(), Entry { addr: "10.0.0.3:80".into(), weight: 1 }).await.unwrap();
+    data.set("", b"root".to_vec()).await.unwrap();
+
+    // Captured contents always decode and restore
+    let archive = Snapshot::capture(&route, &data, &registry).await.unwrap().encode().unwrap();
+    let snapshot = Snapshot::decode(&archive).unwrap();
+    let (target, target_data) = (Route::new(Settings::default()), Data::new());
+    snapshot.restore(&target, &target_data, &Registry::new()).await.unwrap();
+
+    assert_eq!(target.get("local").await.unwrap().addr, "/run/link.sock");
+    assert_eq!(target.list().await.unwrap().len(), 4);
+    assert_eq!(target_data.get("").await.unwrap(), Some(b"root".to_vec()));
+    assert_eq!(target_data.entries("").await, data.entries("").await);
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+use link::core::error::Error;
+use link::core::link::Storable;
+use link::guard::Crypt;
+use link::store::{File, Vault};
+
+#[tokio::test]
+async fn test_vault_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    let vault = Vault::new(file.clone(), Arc::new(Crypt::new(b"vault secret")));
+
+    vault.write("keys/identity.pem", b"private key material").await.unwrap();
+    assert_eq!(vault.read("keys/identity.pem").await.unwrap(), b"private key material");
+
+    // The file on disk starts with the header and holds no plaintext
+    let raw = file.read("keys/identity.pem").await.unwrap();
+    assert_eq!(&raw[..5], b"LNKV\x01");
+    assert!(!raw.windows(7).any(|w| w == b"private"));
+
+    // Every write uses a fresh nonce
+    vault.write("keys/identity.pem", b"private key material").await.unwrap();
+    assert_ne!(file.read("keys/identity.pem").await.unwrap(), raw);
+}
+
+#[tokio::test]
+async fn test_vault_rejects_tampering() {
+    let temp_dir = tempdir().unwrap();
+    let file = File::new(temp_dir.path());
+    let vault = Vault::new(file.clone(), Arc::new(Crypt::new(b"vault secret")));
+    vault.write("token", b"peer token").await.unwrap();
+
+    // Flipped ciphertext bit
+    let mut raw = file.read("token").await.unwrap();
+    let last = raw.len() - 1;
+    raw[last] ^= 1;
+    file.write("token", &raw).await.unwrap();
+    assert!(matches!(vault.read("token").await, Err(Error::Decrypt(_))));
+
+    // Content moved to another path
+    vault.write("token", b"peer token").await.unwrap();
+    file.write("moved", &file.read("token").await.unwrap()).await.unwrap();
+    assert!(matches!(vault.read("moved").await, Err(Error::Decrypt(_))));
+
+    // Plaintext file and wrong key
+    file.write("plain", b"not encrypted").await.unwrap();
+    assert!(matches!(vault.read("plain").await, Err(Error::Decrypt(_))));
+    let other = Vault::new(file, Arc::new(Crypt::new(b"other secret")));
+    assert!(matches!(other.read("token").await, Err(Error::Decrypt(_))));
+}
+
+#[tokio::test]
+async fn test_vault_key_rotation() {
+    let temp_dir = tempdir().unwrap();
+    let crypt = Arc::new(Crypt::new(b"first secret"));
+    let vault = Vault::new(File::new(temp_dir.path()), crypt.clone());
+    vault.write("token", b"peer token").await.unwrap();
+    assert_eq!(vault.key("token").await.unwrap(), 1);
+
+    // Old files stay readable after rotation and move to the new key on rewrap
+    let id = crypt.rotate(b"second secret", Duration::from_secs(60));
+    assert_eq!(vault.read("token").await.unwrap(), b"peer token");
+    assert!(vault.rewrap("token").await.unwrap());
+    assert_eq!(vault.key("token").await.unwrap(), id);
+    assert!(!vault.rewrap("token").await.unwrap());
+    assert_eq!(vault.read("token").await.unwrap(), b"peer token");
+}
+
+#[tokio::test]
+async fn test_vault_storable() {
+    let temp_dir = tempdir().unwrap();
+    let vault = Vault::new(File::new(temp_dir.path()), Arc::new(Crypt::new(b"vault secret")));
+
+    vault.put("peers/alice", b"a".to_vec()).await.unwrap();
+    vault.put("peers/bob", b"b".to_vec()).await.unwrap();
+    assert_eq!(Storable::get(&vault, "peers/alice").await.unwrap(), Some(b"a".to_vec()));
+    assert_eq!(Storable::get(&vault, "missing").await.unwrap(), None);
+    assert_eq!(vault.list("peers/").await.unwrap(), vec!["peers/alice", "peers/bob"]);
+
+    vault.delete("peers/alice").await.unwrap();
+    assert!(!Storable::exists(&vault, "peers/alice").await.unwrap());
+}